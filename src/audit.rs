@@ -0,0 +1,233 @@
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::config_updater::{FileOutcome, UpdateStats};
+use crate::exit_code::ExitCode;
+
+/// Length of a run id, in hex characters
+const RUN_ID_LEN: usize = 12;
+
+/// One line of the audit log: a config file that was written
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// UTC time of the write, RFC 3339
+    pub timestamp: String,
+    /// Shared by every entry written by the same process
+    pub run_id: String,
+    /// The subcommand that wrote the file: `update`, `watch` or `serve`
+    pub command: String,
+    pub user: String,
+    pub host: String,
+    /// Absolute path of the file
+    pub path: PathBuf,
+    /// Fingerprints of the tokens the file held before, in field order
+    pub old_fingerprints: Vec<String>,
+    /// Fingerprints of the tokens it holds now
+    pub new_fingerprints: Vec<String>,
+}
+
+impl AuditEntry {
+    /// The entry's time, if its timestamp can be read
+    pub fn time(&self) -> Option<SystemTime> {
+        humantime::parse_rfc3339_weak(&self.timestamp).ok()
+    }
+}
+
+/// An append-only JSONL log with one [`AuditEntry`] per written file.
+///
+/// The file is opened in append mode and created with mode `0600`; each batch of entries is
+/// written with a single call and flushed to disk before the run reports success.
+pub struct AuditLog {
+    path: PathBuf,
+    file: File,
+    run_id: String,
+    command: String,
+    user: String,
+    host: String,
+}
+
+impl AuditLog {
+    /// Opens (or creates) the log at `path` for entries written by `command`
+    pub fn open<P: AsRef<Path>>(path: P, command: &str) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Cannot create the audit log directory {}", parent.display()))?;
+        }
+        let mut options = OpenOptions::new();
+        options.append(true).create(true);
+        // Elsewhere the file inherits the permissions of its directory
+        #[cfg(unix)]
+        options.mode(0o600);
+        let file = options
+            .open(path)
+            .with_context(|| format!("Cannot open the audit log {}", path.display()))?;
+        let host = host_name();
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            run_id: new_run_id(&host),
+            command: command.to_string(),
+            user: user_name(),
+            host,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Appends an entry for each outcome whose file was written, returning how many were written
+    pub fn record(&self, outcomes: &[FileOutcome]) -> Result<usize> {
+        let timestamp = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+        let mut lines = String::new();
+        let mut count = 0;
+        for outcome in outcomes {
+            let Some(change) = &outcome.change else {
+                continue;
+            };
+            let entry = AuditEntry {
+                timestamp: timestamp.clone(),
+                run_id: self.run_id.clone(),
+                command: self.command.clone(),
+                user: self.user.clone(),
+                host: self.host.clone(),
+                path: std::path::absolute(&outcome.path).unwrap_or_else(|_| outcome.path.clone()),
+                old_fingerprints: change.old.clone(),
+                new_fingerprints: change.new.clone(),
+            };
+            lines.push_str(&serde_json::to_string(&entry)?);
+            lines.push('\n');
+            count += 1;
+        }
+        if count > 0 {
+            // One write per batch, so entries from concurrent runs do not interleave
+            (&self.file).write_all(lines.as_bytes())
+                .and_then(|()| self.file.sync_data())
+                .with_context(|| format!("Cannot write to the audit log {}", self.path.display()))?;
+        }
+        Ok(count)
+    }
+
+    /// Records the files written by an update; a failed write becomes an error of the run
+    pub fn record_stats(&self, stats: &mut UpdateStats) {
+        if let Err(e) = self.record(&stats.files) {
+            stats.add_error(format!("{:#}", e));
+        }
+    }
+}
+
+/// A random-looking id for this process, derived from the time, the process id and the host
+fn new_run_id(host: &str) -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    let digest = Sha256::digest(format!("{}:{}:{}", nanos, process::id(), host));
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect::<String>()[..RUN_ID_LEN].to_string()
+}
+
+fn user_name() -> String {
+    ["USER", "LOGNAME", "USERNAME"]
+        .iter()
+        .find_map(|var| env::var(var).ok().filter(|name| !name.is_empty()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn host_name() -> String {
+    ["/proc/sys/kernel/hostname", "/etc/hostname"]
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok())
+        .or_else(|| env::var("HOSTNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Which entries `history` shows
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HistoryQuery {
+    /// Files at or below any of these paths; all files when empty
+    pub paths: Vec<PathBuf>,
+    /// Entries where the file held or now holds this token
+    pub fingerprint: Option<String>,
+    pub run_id: Option<String>,
+    /// Entries written at or after this time
+    pub since: Option<SystemTime>,
+    /// Only the most recent matching entries
+    pub limit: Option<usize>,
+}
+
+impl HistoryQuery {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        (self.paths.is_empty() || self.paths.iter().any(|path| entry.path.starts_with(path)))
+            && self.fingerprint.as_ref().is_none_or(|fingerprint| {
+                entry.old_fingerprints.contains(fingerprint) || entry.new_fingerprints.contains(fingerprint)
+            })
+            && self.run_id.as_ref().is_none_or(|run_id| entry.run_id == *run_id)
+            && self.since.is_none_or(|since| entry.time().is_some_and(|time| time >= since))
+    }
+}
+
+/// Entries read back from an audit log
+#[derive(Debug, PartialEq, Default, Serialize)]
+pub struct HistoryStats {
+    pub entries_read: usize,
+    pub entries_matched: usize,
+    /// Lines that could not be read as entries
+    pub errors: Vec<String>,
+    /// The matching entries, oldest first
+    pub entries: Vec<AuditEntry>,
+}
+
+impl HistoryStats {
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from_counts(self.entries_matched, self.errors.len(), self.entries_read + self.errors.len())
+    }
+}
+
+/// Reads the audit log at `path` and keeps the entries `query` selects
+pub fn read_history<P: AsRef<Path>>(path: P, query: &HistoryQuery) -> Result<HistoryStats> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|e| anyhow!("Cannot read the audit log {}: {}", path.display(), e))?;
+    let mut stats = HistoryStats::default();
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("Cannot read the audit log {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<AuditEntry>(&line) {
+            Ok(entry) => {
+                stats.entries_read += 1;
+                if query.matches(&entry) {
+                    stats.entries.push(entry);
+                }
+            }
+            Err(e) => stats.errors.push(format!("{}:{}: {}", path.display(), number + 1, e)),
+        }
+    }
+    stats.entries_matched = stats.entries.len();
+    if let Some(limit) = query.limit {
+        stats.entries.drain(..stats.entries.len().saturating_sub(limit));
+    }
+    Ok(stats)
+}
+
+/// Parses `--since`: an RFC 3339 time such as `2026-10-01T00:00:00Z`, or a duration such as `7d` before now
+pub fn parse_since(value: &str) -> Result<SystemTime, String> {
+    if let Ok(time) = humantime::parse_rfc3339_weak(value) {
+        return Ok(time);
+    }
+    let duration: Duration = humantime::parse_duration(value)
+        .map_err(|_| format!("expected a time such as 2026-10-01T00:00:00Z or a duration such as 7d, got {:?}", value))?;
+    SystemTime::now().checked_sub(duration).ok_or_else(|| format!("{:?} reaches too far back", value))
+}
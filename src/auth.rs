@@ -0,0 +1,173 @@
+use std::fmt;
+use std::time::Duration;
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use ureq::http::Method;
+use crate::exit_code::ValidationError;
+use crate::secret::SecretToken;
+use crate::vault::{VaultClient, VaultError};
+
+/// Vault auth methods the tool can log in with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoginMethod {
+    /// AppRole: role_id and secret_id
+    #[value(name = "approle")]
+    AppRole,
+    /// Username and password
+    Userpass,
+    /// Create a fresh token from an existing one via auth/token/create
+    Token,
+}
+
+impl LoginMethod {
+    /// Mount path used when none is given
+    pub fn default_mount(self) -> &'static str {
+        match self {
+            LoginMethod::AppRole => "approle",
+            LoginMethod::Userpass => "userpass",
+            LoginMethod::Token => "token",
+        }
+    }
+}
+
+impl fmt::Display for LoginMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LoginMethod::AppRole => "approle",
+            LoginMethod::Userpass => "userpass",
+            LoginMethod::Token => "token",
+        })
+    }
+}
+
+/// Options for `auth/token/create`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenCreateRequest {
+    pub policies: Vec<String>,
+    pub ttl: Option<Duration>,
+    /// Create the token against a token role (`auth/token/create/<role>`)
+    pub role: Option<String>,
+}
+
+/// Credentials for one login attempt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoginCredentials {
+    AppRole { role_id: String, secret_id: SecretToken },
+    Userpass { username: String, password: SecretToken },
+    Token { parent: SecretToken, request: TokenCreateRequest },
+}
+
+impl LoginCredentials {
+    pub fn method(&self) -> LoginMethod {
+        match self {
+            LoginCredentials::AppRole { .. } => LoginMethod::AppRole,
+            LoginCredentials::Userpass { .. } => LoginMethod::Userpass,
+            LoginCredentials::Token { .. } => LoginMethod::Token,
+        }
+    }
+}
+
+/// Token issued by a successful login
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct LoginResult {
+    #[serde(rename = "client_token")]
+    pub token: SecretToken,
+    #[serde(default)]
+    pub policies: Vec<String>,
+    /// Lifetime of the new token in seconds; 0 for tokens that never expire
+    #[serde(default)]
+    pub lease_duration: u64,
+    #[serde(default)]
+    pub renewable: bool,
+    /// Whether the token has no parent; a child token is revoked along with its parent
+    #[serde(default)]
+    pub orphan: bool,
+    /// With `--login token`, the parent token whose revocation would revoke this one too
+    #[serde(skip)]
+    pub parent: Option<SecretToken>,
+}
+
+/// Logs in to Vault and returns the issued token.
+///
+/// `mount` overrides the auth method's default mount path.
+pub fn login(client: &VaultClient, credentials: &LoginCredentials, mount: Option<&str>) -> Result<LoginResult> {
+    let mount = mount.unwrap_or(credentials.method().default_mount()).trim_matches('/');
+
+    let response = match credentials {
+        LoginCredentials::AppRole { role_id, secret_id } => {
+            let body = json!({ "role_id": role_id, "secret_id": secret_id.expose() });
+            client.request(Method::POST, &format!("auth/{}/login", mount), None, Some(&body))?
+        }
+        LoginCredentials::Userpass { username, password } => {
+            let body = json!({ "password": password.expose() });
+            client.request(Method::POST, &format!("auth/{}/login/{}", mount, path_segment(username)), None, Some(&body))?
+        }
+        LoginCredentials::Token { parent, request } => {
+            let path = match &request.role {
+                Some(role) => format!("auth/{}/create/{}", mount, path_segment(role)),
+                None => format!("auth/{}/create", mount),
+            };
+            let mut body = json!({});
+            if !request.policies.is_empty() {
+                body["policies"] = json!(request.policies);
+            }
+            if let Some(ttl) = request.ttl {
+                body["ttl"] = json!(format!("{}s", ttl.as_secs()));
+            }
+            let mut result = parse_auth(client.request(Method::POST, &path, Some(parent), Some(&body))?)?;
+            if !result.orphan {
+                result.parent = Some(parent.clone());
+            }
+            return Ok(result);
+        }
+    };
+
+    parse_auth(response)
+}
+
+/// Unwraps a response-wrapping token through `sys/wrapping/unwrap` and returns the
+/// token it wraps.
+///
+/// Wrapping tokens are single-use: Vault rejects one that was already unwrapped,
+/// expired or tampered with, which is reported as a [`ValidationError`].
+pub fn unwrap_token(client: &VaultClient, wrapping_token: &SecretToken) -> Result<LoginResult> {
+    let response = match client.request(Method::POST, "sys/wrapping/unwrap", Some(wrapping_token), None) {
+        Ok(response) => response,
+        Err(e) => match e.downcast_ref::<VaultError>() {
+            Some(vault_error) if matches!(vault_error.status, 400 | 403) => {
+                return Err(ValidationError::new(format!(
+                    "Wrapping token {} was rejected by Vault (already unwrapped, expired or invalid)",
+                    wrapping_token.fingerprint()
+                )).into());
+            }
+            _ => return Err(e.context("Unwrapping the token failed")),
+        },
+    };
+
+    parse_auth(response)
+        .map_err(|_| anyhow::anyhow!("The wrapped response does not contain a client token"))
+}
+
+/// Percent-encodes `value` for use as one segment of a request path, so a `/`, `?` or `#` in a
+/// username or role cannot reach another endpoint
+fn path_segment(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Extracts the `auth` block of a login response
+fn parse_auth(response: Value) -> Result<LoginResult> {
+    let auth = response.get("auth").filter(|auth| !auth.is_null()).cloned()
+        .ok_or_else(|| anyhow::anyhow!("Vault login response has no auth data"))?;
+    Ok(serde_json::from_value(auth)?)
+}
@@ -1,75 +1,967 @@
-use std::path::Path;
-use std::io::{self, Write};
-use clap::Parser;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use clap::builder::BoolishValueParser;
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
 use anyhow::Result;
+use crate::output::{ColorChoice, OutputOptions};
+use crate::report::OutputFormat;
+use crate::secret::SecretToken;
+use std::env;
+use std::fs;
+use anyhow::Context;
+use zeroize::Zeroizing;
+use crate::audit::{parse_since, AuditLog, HistoryQuery};
+use crate::auth::{login, LoginCredentials, LoginMethod, LoginResult, TokenCreateRequest};
+use crate::config_updater::TokenFields;
+use crate::exit_code::ValidationError;
+use crate::file_scanner::{FileFilter, GitPolicy};
+use crate::hooks::{HookConfig, Hooks};
+use crate::project_config::{Profile, ProjectConfig, CONFIG_FILE_NAME};
+use crate::serve::default_socket_path;
+use crate::token_source::{prompt_secret, TokenSource};
+use crate::vault::{VaultClient, VaultConfig};
+use crate::vault_cli::VaultCliConfig;
 
 /// High-performance concurrent HashiCorp Vault token updater for configuration files
 #[derive(Parser, Debug)]
 #[command(name = "vault-config-updater")]
 #[command(version = "0.1.0")]
 #[command(about = "Updates HashiCorp Vault tokens in config.json and globalConfig.json files")]
-#[command(long_about = "Recursively finds config.json and globalConfig.json files and updates their vaultToken fields concurrently using all available CPU cores.")]
+#[command(override_usage = "vault-config-updater [OPTIONS] [TOKEN] [PATH]...\n       vault-config-updater [OPTIONS] <COMMAND>")]
+#[command(long_about = "Recursively finds config.json and globalConfig.json files and updates their vaultToken fields concurrently using all available CPU cores.\n\nWithout a subcommand the tool runs `update`.")]
 pub struct CliArgs {
+    /// Verbose output
+    #[arg(short, long, global = true, conflicts_with = "quiet")]
+    pub verbose: bool,
+
+    /// Only report errors
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Output format: human-readable text, a JSON document, or NDJSON events
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    /// When to use colors in text output (honors NO_COLOR in auto mode)
+    #[arg(long, global = true, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Use plain ASCII text output without emoji
+    #[arg(long, global = true)]
+    pub ascii: bool,
+
+    /// Read settings from FILE instead of the discovered .vault-config-updater.toml
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Apply the named profile from the config file on top of its defaults
+    #[arg(long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Do not run the hooks from the config file
+    #[arg(long, global = true)]
+    pub no_hooks: bool,
+
+    /// Hook commands from the config file
+    #[arg(skip)]
+    pub hooks: HookConfig,
+
+    /// A discovered project config, with the settings of it that were not taken
+    #[arg(skip)]
+    pub untrusted_settings: Option<(PathBuf, Vec<&'static str>)>,
+
+    /// Append an entry for every written file to this JSONL audit log; `history` reads it
+    #[arg(long, global = true, value_name = "FILE")]
+    pub audit_log: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Update options of a bare invocation, which keeps working as `update`
+    #[command(flatten)]
+    pub update: UpdateArgs,
+}
+
+/// Options for `update`, which replaces the tokens in every config file
+#[derive(Args, Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpdateArgs {
     /// HashiCorp Vault token (hvs.xxx format). If not provided, will prompt for input.
+    /// Arguments are visible to other users via `ps`; prefer --token-file, --token-stdin or --token-env.
     #[arg(value_name = "TOKEN")]
-    pub token: Option<String>,
+    pub token: Option<SecretToken>,
+
+    #[command(flatten)]
+    pub selection: SelectionArgs,
+
+    /// Dry run mode - show what files would be changed without modifying them (same as `scan`)
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// After every file was updated, revoke the old tokens found in them through the Vault API
+    #[arg(long, conflicts_with = "dry_run")]
+    pub revoke_old: bool,
+
+    #[command(flatten)]
+    pub token_args: TokenSourceArgs,
+
+    #[command(flatten)]
+    pub vault: VaultArgs,
+
+    #[command(flatten)]
+    pub validation: ValidationArgs,
+
+    #[command(flatten)]
+    pub login: LoginArgs,
+
+    #[command(flatten)]
+    pub git: GitArgs,
+
+    /// Pick the token for each file and field from the rules in a mapping file
+    #[arg(long, value_name = "FILE", conflicts_with_all = [
+        "token_source", "login", "unwrap", "revoke_old", "dry_run", "validate_token", "min_ttl"
+    ])]
+    pub mapping: Option<PathBuf>,
+
+    /// Token to write, once it has been read or issued
+    #[arg(skip)]
+    resolved_token: Option<SecretToken>,
+
+    /// Parent of the token issued by `--login token`, unless Vault made it an orphan
+    #[arg(skip)]
+    parent_token: Option<SecretToken>,
+}
+
+/// Subcommands; without one the tool updates the tokens
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Replace the vaultToken values in every config file (the default)
+    Update(Box<UpdateArgs>),
+    /// Show which files an update would change, without modifying anything
+    Scan(ScanArgs),
+    /// Group the tokens found in config files by fingerprint and optionally check them against Vault
+    Inspect(InspectArgs),
+    /// Check that every vaultToken field holds the expected token; exits with code 6 on drift
+    Verify(VerifyArgs),
+    /// Keep config files in sync: apply the token to new config files and again whenever it changes
+    Watch(WatchArgs),
+    /// Hold the discovered files in memory and take rotate, scan and status requests on a Unix socket
+    Serve(ServeArgs),
+    /// Show the file changes recorded in the audit log
+    History(HistoryArgs),
+}
 
-    /// Directory to search for config files (default: current directory)
+/// Options for `scan`
+#[derive(Args, Debug, Clone, PartialEq, Eq)]
+pub struct ScanArgs {
+    #[command(flatten)]
+    pub selection: SelectionArgs,
+}
+
+impl ScanArgs {
+    /// Get the first search path, defaulting to current directory
+    pub fn get_search_path(&self) -> &Path {
+        self.selection.get_search_path()
+    }
+
+    /// The roots to search, including those from the config file
+    pub fn search_paths(&self) -> Vec<PathBuf> {
+        self.selection.search_paths()
+    }
+}
+
+/// Options for `inspect`
+#[derive(Args, Debug, Clone, PartialEq, Eq)]
+pub struct InspectArgs {
+    #[command(flatten)]
+    pub selection: SelectionArgs,
+
+    /// Look each distinct token up in Vault and flag expired, expiring or unknown ones
+    #[arg(long)]
+    pub lookup: bool,
+
+    /// With --lookup, flag tokens that expire within this duration
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, default_value = "7d")]
+    pub expiring_within: Duration,
+
+    #[command(flatten)]
+    pub vault: VaultArgs,
+}
+
+impl InspectArgs {
+    /// Get the first search path, defaulting to current directory
+    pub fn get_search_path(&self) -> &Path {
+        self.selection.get_search_path()
+    }
+
+    /// The roots to search, including those from the config file
+    pub fn search_paths(&self) -> Vec<PathBuf> {
+        self.selection.search_paths()
+    }
+}
+
+/// Options for `verify`
+#[derive(Args, Debug, Clone, PartialEq, Eq)]
+pub struct VerifyArgs {
+    #[command(flatten)]
+    pub selection: SelectionArgs,
+
+    /// Fingerprint of the expected token, as shown by `inspect` and in JSON reports
+    #[arg(long, value_name = "FINGERPRINT", group = "token_source")]
+    pub token_fingerprint: Option<String>,
+
+    #[command(flatten)]
+    pub token_args: TokenSourceArgs,
+}
+
+/// Options for `history`
+#[derive(Args, Debug, Clone, PartialEq, Eq)]
+pub struct HistoryArgs {
+    /// Only show files at or below these paths
     #[arg(value_name = "PATH")]
-    pub path: Option<String>,
+    pub paths: Vec<PathBuf>,
 
-    /// Verbose output
-    #[arg(short, long)]
-    pub verbose: bool,
+    /// Only show changes from or to the token with this fingerprint
+    #[arg(long, value_name = "FINGERPRINT")]
+    pub fingerprint: Option<String>,
+
+    /// Only show the changes made by one run
+    #[arg(long, value_name = "RUN_ID")]
+    pub run: Option<String>,
+
+    /// Only show changes since a time (e.g. 2026-10-01T00:00:00Z) or within a duration (e.g. 7d)
+    #[arg(long, value_name = "WHEN", value_parser = parse_since)]
+    pub since: Option<SystemTime>,
 
-    /// Dry run mode - show what files would be changed without modifying them
+    /// Show only the N most recent matching changes
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+}
+
+impl HistoryArgs {
+    /// The entries to show; the paths are made absolute, like the paths in the log
+    pub fn query(&self) -> HistoryQuery {
+        HistoryQuery {
+            paths: self.paths.iter().map(|path| std::path::absolute(path).unwrap_or_else(|_| path.clone())).collect(),
+            fingerprint: self.fingerprint.as_ref().map(|fingerprint| fingerprint.trim().to_ascii_lowercase()),
+            run_id: self.run.clone(),
+            since: self.since,
+            limit: self.limit,
+        }
+    }
+}
+
+impl VerifyArgs {
+    /// Get the first search path, defaulting to current directory
+    pub fn get_search_path(&self) -> &Path {
+        self.selection.get_search_path()
+    }
+
+    /// The roots to search, including those from the config file
+    pub fn search_paths(&self) -> Vec<PathBuf> {
+        self.selection.search_paths()
+    }
+
+    /// Whether the token would be read from stdin while `--files-from -` also reads it
+    fn needs_stdin_for_token(&self) -> bool {
+        self.selection.reads_stdin() && self.token_fingerprint.is_none()
+            && (self.token_args.token_stdin || !self.token_args.has_source())
+    }
+
+    /// Fingerprint of the expected token, read from a token source when not given directly
+    pub fn expected_fingerprint(&self) -> Result<String> {
+        if let Some(fingerprint) = &self.token_fingerprint {
+            let fingerprint = fingerprint.trim().to_ascii_lowercase();
+            if fingerprint.len() != 12 || !fingerprint.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(ValidationError::new(format!(
+                    "{:?} is not a token fingerprint (expected 12 hex characters)", fingerprint
+                )).into());
+            }
+            return Ok(fingerprint);
+        }
+        Ok(self.token_args.source(None).read()?.fingerprint())
+    }
+}
+
+/// Options for `watch`
+#[derive(Args, Debug, Clone, PartialEq, Eq)]
+pub struct WatchArgs {
+    #[command(flatten)]
+    pub selection: SelectionArgs,
+
+    #[command(flatten)]
+    pub token_args: TokenSourceArgs,
+
+    /// Take the token from a Vault Agent file sink (plain or response-wrapped); repeat for several
+    /// sinks, the most recently written one wins
+    #[arg(long, value_name = "FILE", group = "token_source")]
+    pub sink: Vec<PathBuf>,
+
+    /// Wait until no change was seen for this long before applying a batch of changes
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, default_value = "500ms")]
+    pub debounce: Duration,
+
+    /// Apply a changed token at most once per DURATION; a newer token waits for the interval to pass
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, default_value = "10s")]
+    pub min_interval: Duration,
+
+    #[command(flatten)]
+    pub git: GitArgs,
+
+    /// Used to unwrap response-wrapped sinks
+    #[command(flatten)]
+    pub vault: VaultArgs,
+}
+
+impl WatchArgs {
+    /// The roots to watch, including those from the config file
+    pub fn search_paths(&self) -> Vec<PathBuf> {
+        self.selection.search_paths()
+    }
+
+    /// The files the token is read from, watched so a new token is applied as soon as it is written.
+    ///
+    /// Those are the --sink files, the --token-file, or ~/.vault-token with --from-vault-cli
+    /// when neither VAULT_TOKEN nor a token helper takes precedence.
+    pub fn token_files(&self) -> Vec<PathBuf> {
+        if !self.sink.is_empty() {
+            return self.sink.clone();
+        }
+        if let Some(path) = &self.token_args.token_file {
+            return vec![path.clone()];
+        }
+        if !self.token_args.from_vault_cli {
+            return Vec::new();
+        }
+        VaultCliConfig::from_env()
+            .ok()
+            .filter(|cli| cli.env_token.is_none() && cli.token_helper().is_ok_and(|helper| helper.is_none()))
+            .map(|cli| vec![cli.token_path])
+            .unwrap_or_default()
+    }
+
+    /// Whether the token would be read from stdin while `--files-from -` also reads it
+    fn needs_stdin_for_token(&self) -> bool {
+        self.selection.reads_stdin() && self.sink.is_empty()
+            && (self.token_args.token_stdin || !self.token_args.has_source())
+    }
+}
+
+/// Options for `serve`
+#[derive(Args, Debug, Clone, PartialEq, Eq)]
+pub struct ServeArgs {
+    #[command(flatten)]
+    pub selection: SelectionArgs,
+
+    /// Control socket path [default: $XDG_RUNTIME_DIR/vault-config-updater.sock]
+    #[arg(long, value_name = "PATH")]
+    pub socket: Option<PathBuf>,
+
+    /// Also accept signed rotation webhooks over HTTP on ADDR (e.g. 127.0.0.1:8470)
+    #[arg(long, value_name = "ADDR", requires = "webhook_secret")]
+    pub http: Option<SocketAddr>,
+
+    /// Read the secret that webhook requests are signed with from FILE
+    #[arg(long, value_name = "FILE", group = "webhook_secret")]
+    pub webhook_secret_file: Option<PathBuf>,
+
+    /// Read the secret that webhook requests are signed with from an environment variable
+    #[arg(long, value_name = "VAR", group = "webhook_secret")]
+    pub webhook_secret_env: Option<String>,
+
+    #[command(flatten)]
+    pub git: GitArgs,
+
+    /// Used to unwrap response-wrapped tokens
+    #[command(flatten)]
+    pub vault: VaultArgs,
+}
+
+impl ServeArgs {
+    /// The roots to serve, including those from the config file
+    pub fn search_paths(&self) -> Vec<PathBuf> {
+        self.selection.search_paths()
+    }
+
+    pub fn socket_path(&self) -> PathBuf {
+        self.socket.clone().unwrap_or_else(default_socket_path)
+    }
+
+    /// Where the webhook secret is read from, if --http is used
+    pub fn webhook_secret(&self) -> Option<TokenSource> {
+        self.webhook_secret_file.clone().map(TokenSource::File)
+            .or_else(|| self.webhook_secret_env.clone().map(TokenSource::Env))
+    }
+}
+
+/// Which files are processed and which JSON keys hold tokens
+#[derive(Args, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SelectionArgs {
+    /// Directories to search for config files (default: current directory)
+    #[arg(value_name = "PATH")]
+    pub paths: Vec<PathBuf>,
+
+    /// Also process the config files listed in FILE, one path per line ("-" reads stdin)
+    #[arg(long, value_name = "FILE")]
+    pub files_from: Option<PathBuf>,
+
+    /// Only process files whose path below the search root matches GLOB (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub include: Vec<String>,
+
+    /// Skip files whose path below the search root matches GLOB (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// JSON key whose value is a Vault token (repeatable; default: vaultToken)
+    #[arg(long = "key", value_name = "NAME")]
+    pub keys: Vec<String>,
+
+    /// Search roots from the config file, used when neither PATH nor --files-from is given
+    #[arg(skip)]
+    pub default_paths: Vec<PathBuf>,
+}
+
+impl SelectionArgs {
+    pub fn filter(&self) -> Result<FileFilter> {
+        FileFilter::new(&self.include, &self.exclude)
+    }
+
+    pub fn fields(&self) -> Result<TokenFields> {
+        TokenFields::new(&self.keys)
+    }
+
+    /// Get the first search path, defaulting to current directory
+    pub fn get_search_path(&self) -> &Path {
+        self.paths.first().map_or(Path::new("."), PathBuf::as_path)
+    }
+
+    /// The PATH arguments; with only --files-from none; otherwise the config file's paths
+    /// or the current directory
+    pub fn search_paths(&self) -> Vec<PathBuf> {
+        if !self.paths.is_empty() {
+            self.paths.clone()
+        } else if self.files_from.is_some() {
+            Vec::new()
+        } else if !self.default_paths.is_empty() {
+            self.default_paths.clone()
+        } else {
+            vec![PathBuf::from(".")]
+        }
+    }
+
+    /// Whether the file list is read from stdin (`--files-from -`)
+    pub fn reads_stdin(&self) -> bool {
+        self.files_from.as_deref() == Some(Path::new("-"))
+    }
+
+    /// Takes the settings that were not given on the command line from the config file
+    pub fn apply_settings(&mut self, settings: &Profile) {
+        let fill = |target: &mut Vec<String>, value: &Option<Vec<String>>| {
+            if target.is_empty() && let Some(value) = value {
+                target.clone_from(value);
+            }
+        };
+        fill(&mut self.include, &settings.include);
+        fill(&mut self.exclude, &settings.exclude);
+        fill(&mut self.keys, &settings.keys);
+        if let Some(paths) = &settings.paths {
+            self.default_paths.clone_from(paths);
+        }
+    }
+}
+
+/// How config files that a git repository could pick up are handled
+#[derive(Args, Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitArgs {
+    /// What to do with config files that git tracks or does not ignore [default: warn]
+    #[arg(long, value_enum, value_name = "POLICY")]
+    pub git_policy: Option<GitPolicy>,
+
+    /// Add config files that git does not ignore to the .gitignore of their repository
     #[arg(long)]
-    pub dry_run: bool,
+    pub gitignore: bool,
+}
+
+impl GitArgs {
+    /// The policy in effect; `warn` unless set on the command line or in the config file
+    pub fn policy(&self) -> GitPolicy {
+        self.git_policy.unwrap_or_default()
+    }
+
+    /// Takes the policy from the config file when it was not given on the command line
+    pub fn apply_settings(&mut self, settings: &Profile) {
+        if self.git_policy.is_none() {
+            self.git_policy = settings.git_policy;
+        }
+    }
+}
+
+/// Alternative ways to supply the token without putting it in argv
+#[derive(Args, Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenSourceArgs {
+    /// Read the token from the first line of FILE
+    #[arg(long, value_name = "FILE", group = "token_source")]
+    pub token_file: Option<PathBuf>,
+
+    /// Read the token from the first line of stdin
+    #[arg(long, group = "token_source")]
+    pub token_stdin: bool,
+
+    /// Read the token from the environment variable VAR
+    #[arg(long, value_name = "VAR", group = "token_source")]
+    pub token_env: Option<String>,
+
+    /// Use the token from `vault login`: VAULT_TOKEN, the configured token_helper, then ~/.vault-token
+    #[arg(long, group = "token_source")]
+    pub from_vault_cli: bool,
+
+    /// Ask for the token twice when prompting interactively
+    #[arg(long)]
+    pub confirm_token: bool,
+}
+
+impl TokenSourceArgs {
+    /// Whether a non-interactive source was selected
+    pub fn has_source(&self) -> bool {
+        self.token_file.is_some() || self.token_stdin || self.token_env.is_some() || self.from_vault_cli
+    }
+
+    /// The selected source; a token given as an argument wins, the prompt is the fallback
+    pub fn source(&self, argument: Option<&SecretToken>) -> TokenSource {
+        if let Some(token) = argument {
+            TokenSource::Argument(token.clone())
+        } else if let Some(path) = &self.token_file {
+            TokenSource::File(path.clone())
+        } else if self.token_stdin {
+            TokenSource::Stdin
+        } else if let Some(var) = &self.token_env {
+            TokenSource::Env(var.clone())
+        } else if self.from_vault_cli {
+            TokenSource::VaultCli
+        } else {
+            TokenSource::Prompt { confirm: self.confirm_token }
+        }
+    }
+}
+
+/// Vault server connection options
+#[derive(Args, Debug, Clone, Default, PartialEq, Eq)]
+pub struct VaultArgs {
+    /// Vault server address
+    #[arg(long, env = "VAULT_ADDR", value_name = "URL")]
+    pub vault_addr: Option<String>,
+
+    /// Vault Enterprise namespace
+    #[arg(long, env = "VAULT_NAMESPACE", value_name = "NAMESPACE")]
+    pub vault_namespace: Option<String>,
+
+    /// PEM bundle of CA certificates used to verify the Vault server
+    #[arg(long, env = "VAULT_CACERT", value_name = "FILE")]
+    pub vault_cacert: Option<PathBuf>,
+
+    /// Skip TLS certificate verification (insecure)
+    #[arg(long, env = "VAULT_SKIP_VERIFY", value_parser = BoolishValueParser::new())]
+    pub vault_skip_verify: bool,
+}
+
+impl VaultArgs {
+    /// Connection settings, failing when no Vault address is configured
+    pub fn config(&self) -> Result<VaultConfig> {
+        let address = self.vault_addr.clone()
+            .filter(|address| !address.is_empty())
+            .ok_or_else(|| ValidationError::new("No Vault address configured (set VAULT_ADDR or --vault-addr)"))?;
+
+        let mut config = VaultConfig::new(address);
+        config.namespace = self.vault_namespace.clone().filter(|namespace| !namespace.is_empty());
+        config.ca_cert = self.vault_cacert.clone();
+        config.skip_verify = self.vault_skip_verify;
+        Ok(config)
+    }
+}
+
+/// Checks applied to the new token before any file is touched
+#[derive(Args, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationArgs {
+    /// Look the new token up in Vault and refuse to rotate if it is invalid
+    #[arg(long)]
+    pub validate_token: bool,
+
+    /// Refuse to rotate if the new token expires sooner than this (e.g. 24h); implies --validate-token
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    pub min_ttl: Option<Duration>,
+}
+
+impl ValidationArgs {
+    /// Whether the new token should be checked against Vault before rotating
+    pub fn wants_validation(&self) -> bool {
+        self.validate_token || self.min_ttl.is_some()
+    }
+}
+
+/// Options for obtaining the new token by logging in to Vault
+#[derive(Args, Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoginArgs {
+    /// Log in to Vault with this auth method and rotate to the token it issues
+    #[arg(long, value_enum, value_name = "METHOD")]
+    pub login: Option<LoginMethod>,
+
+    /// Mount path of the auth method (default: approle, userpass or token)
+    #[arg(long, value_name = "PATH", requires = "login")]
+    pub auth_mount: Option<String>,
+
+    /// AppRole role_id
+    #[arg(long, env = "VAULT_ROLE_ID", value_name = "ID")]
+    pub role_id: Option<String>,
+
+    /// File holding the AppRole secret_id (default: the VAULT_SECRET_ID environment variable)
+    #[arg(long, value_name = "FILE")]
+    pub secret_id_file: Option<PathBuf>,
+
+    /// Userpass username
+    #[arg(long, env = "VAULT_USERNAME", value_name = "NAME")]
+    pub username: Option<String>,
+
+    /// File holding the userpass password (default: VAULT_PASSWORD, then a hidden prompt)
+    #[arg(long, value_name = "FILE")]
+    pub password_file: Option<PathBuf>,
+
+    /// Policy for a token created with --login token (repeatable)
+    #[arg(long = "token-policy", value_name = "POLICY")]
+    pub token_policies: Vec<String>,
+
+    /// TTL for a token created with --login token (e.g. 720h)
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    pub token_ttl: Option<Duration>,
+
+    /// Token role for a token created with --login token
+    #[arg(long, value_name = "ROLE")]
+    pub token_role: Option<String>,
+
+    /// Treat the token as a response-wrapping token and rotate to the token it wraps
+    #[arg(long, conflicts_with = "login")]
+    pub unwrap: bool,
+}
+
+impl LoginArgs {
+    /// Gathers the credentials for `method`; `parent` supplies the token for `--login token`
+    pub fn credentials(&self, method: LoginMethod, parent: impl FnOnce() -> Result<SecretToken>) -> Result<LoginCredentials> {
+        match method {
+            LoginMethod::AppRole => Ok(LoginCredentials::AppRole {
+                role_id: self.role_id.clone()
+                    .ok_or_else(|| ValidationError::new("--login approle requires --role-id or VAULT_ROLE_ID"))?,
+                secret_id: read_secret(self.secret_id_file.as_ref(), "VAULT_SECRET_ID", None)?
+                    .ok_or_else(|| ValidationError::new("--login approle requires --secret-id-file or VAULT_SECRET_ID"))?,
+            }),
+            LoginMethod::Userpass => Ok(LoginCredentials::Userpass {
+                username: self.username.clone()
+                    .ok_or_else(|| ValidationError::new("--login userpass requires --username or VAULT_USERNAME"))?,
+                password: read_secret(self.password_file.as_ref(), "VAULT_PASSWORD", Some("Vault password: "))?
+                    .ok_or_else(|| ValidationError::new("--login userpass requires a password"))?,
+            }),
+            LoginMethod::Token => Ok(LoginCredentials::Token {
+                parent: parent()?,
+                request: TokenCreateRequest {
+                    policies: self.token_policies.clone(),
+                    ttl: self.token_ttl,
+                    role: self.token_role.clone(),
+                },
+            }),
+        }
+    }
+}
+
+/// Reads a login secret from a file, then an environment variable, then an optional prompt
+fn read_secret(file: Option<&PathBuf>, env_var: &str, prompt: Option<&str>) -> Result<Option<SecretToken>> {
+    let secret = if let Some(path) = file {
+        let content = Zeroizing::new(fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?);
+        Some(SecretToken::new(content.trim()))
+    } else if let Ok(value) = env::var(env_var) {
+        let value = Zeroizing::new(value);
+        Some(SecretToken::new(value.trim()))
+    } else if let Some(prompt) = prompt {
+        Some(prompt_secret(prompt)?)
+    } else {
+        None
+    };
+    Ok(secret.filter(|secret| !secret.is_empty()))
 }
 
 impl CliArgs {
-    /// Get the search path, defaulting to current directory
-    pub fn get_search_path(&self) -> &Path {
-        match &self.path {
-            Some(p) => Path::new(p),
-            None => Path::new("."),
+    /// Presentation settings selected on the command line
+    pub fn output_options(&self) -> OutputOptions {
+        OutputOptions {
+            format: self.output,
+            verbose: self.verbose,
+            quiet: self.quiet,
+            color: self.color,
+            ascii: self.ascii,
+        }
+    }
+
+    /// The update options in effect: from `update`, or from a bare invocation
+    pub fn update_args(&mut self) -> Option<&mut UpdateArgs> {
+        match &mut self.command {
+            Some(Command::Update(args)) => Some(args),
+            None => Some(&mut self.update),
+            Some(_) => None,
+        }
+    }
+
+    /// The hooks to run around updates, matching files below the command's search roots; none with --no-hooks
+    pub fn hooks(&self) -> Result<Hooks> {
+        if self.no_hooks {
+            return Ok(Hooks::default());
+        }
+        let roots = match &self.command {
+            Some(Command::Update(args)) => args.search_paths(),
+            Some(Command::Watch(args)) => args.search_paths(),
+            Some(Command::Serve(args)) => args.search_paths(),
+            None => self.update.search_paths(),
+            Some(_) => Vec::new(),
+        };
+        Ok(Hooks::new(&self.hooks)?.with_search_roots(roots))
+    }
+
+    /// Opens the audit log for commands that write files; `None` without --audit-log or `audit-log`
+    pub fn open_audit_log(&self) -> Result<Option<AuditLog>> {
+        let command = match &self.command {
+            Some(Command::Update(args)) if !args.dry_run => "update",
+            None if !self.update.dry_run => "update",
+            Some(Command::Watch(_)) => "watch",
+            Some(Command::Serve(_)) => "serve",
+            _ => return Ok(None),
+        };
+        self.audit_log.as_ref().map(|path| AuditLog::open(path, command)).transpose()
+    }
+
+    /// Check if the selected command will prompt for a token
+    pub fn needs_interactive_input(&self) -> bool {
+        match &self.command {
+            Some(Command::Update(args)) => args.needs_interactive_input(),
+            None => self.update.needs_interactive_input(),
+            Some(_) => false,
+        }
+    }
+
+    /// Loads the config file named by --config, or else `discovered`, and applies its settings.
+    ///
+    /// Command-line flags win over the config file, which wins over environment variables.
+    pub fn apply_config(&mut self, matches: &ArgMatches, discovered: Option<PathBuf>) -> Result<()> {
+        let Some(path) = self.config.clone().or(discovered) else {
+            if let Some(profile) = &self.profile {
+                return Err(ValidationError::new(format!(
+                    "--profile {} needs a config file, but no {} was found", profile, CONFIG_FILE_NAME
+                )).into());
+            }
+            return Ok(());
+        };
+        let mut settings = ProjectConfig::load(&path)?.settings(self.profile.as_deref())?;
+        // A project file found by walking up may belong to an untrusted checkout, so the settings
+        // that run commands, receive tokens or pick the files to rewrite are only taken when the
+        // file is named with --config or is the user's own config
+        if self.config.is_none() && !ProjectConfig::is_user_config(&path) {
+            let mut ignored = Vec::new();
+            if settings.hooks.take().is_some() {
+                ignored.push("hooks");
+            }
+            if settings.vault_addr.take().is_some() {
+                ignored.push("vault-addr");
+            }
+            if settings.paths.take().is_some() {
+                ignored.push("paths");
+            }
+            if settings.audit_log.take().is_some() {
+                ignored.push("audit-log");
+            }
+            if !ignored.is_empty() {
+                self.untrusted_settings = Some((path, ignored));
+            }
+        }
+        self.apply_settings(&settings, matches);
+        Ok(())
+    }
+
+    fn apply_settings(&mut self, settings: &Profile, matches: &ArgMatches) {
+        let given = |matches: &ArgMatches, id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        if let Some(hooks) = &settings.hooks {
+            self.hooks = hooks.clone();
+        }
+        if self.audit_log.is_none() {
+            self.audit_log.clone_from(&settings.audit_log);
+        }
+        if let Some(output) = settings.output && !given(matches, "output") {
+            self.output = output;
+        }
+        let command_matches = matches.subcommand().map_or(matches, |(_, command_matches)| command_matches);
+        let (selection, vault, git) = match &mut self.command {
+            Some(Command::Update(args)) => (&mut args.selection, Some(&mut args.vault), Some(&mut args.git)),
+            Some(Command::Scan(args)) => (&mut args.selection, None, None),
+            Some(Command::Inspect(args)) => (&mut args.selection, Some(&mut args.vault), None),
+            Some(Command::Verify(args)) => (&mut args.selection, None, None),
+            Some(Command::Watch(args)) => (&mut args.selection, Some(&mut args.vault), Some(&mut args.git)),
+            Some(Command::Serve(args)) => (&mut args.selection, Some(&mut args.vault), Some(&mut args.git)),
+            Some(Command::History(_)) => return,
+            None => (&mut self.update.selection, Some(&mut self.update.vault), Some(&mut self.update.git)),
+        };
+        selection.apply_settings(settings);
+        if let Some(git) = git {
+            git.apply_settings(settings);
+        }
+        if let Some(vault) = vault
+            && let Some(address) = &settings.vault_addr
+            && !given(command_matches, "vault_addr")
+        {
+            vault.vault_addr = Some(address.clone());
+        }
+    }
+
+    fn from_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        if let Some(subcommand) = matches.subcommand_name() {
+            Self::reject_update_args_before(subcommand, matches)?;
+        }
+        let mut cli = Self::from_arg_matches(matches)?;
+        if let Some(args) = cli.update_args() {
+            args.resolve_positionals();
+        }
+        let stdin_taken = match &cli.command {
+            Some(Command::Update(args)) => args.needs_stdin_for_token(),
+            Some(Command::Verify(args)) => args.needs_stdin_for_token(),
+            Some(Command::Watch(args)) => args.needs_stdin_for_token(),
+            None => cli.update.needs_stdin_for_token(),
+            Some(_) => false,
+        };
+        if let Some(Command::Watch(args)) = &cli.command && args.selection.files_from.is_some() {
+            return Err(clap::Error::raw(
+                ErrorKind::ArgumentConflict,
+                "watch follows the search roots and cannot use --files-from\n",
+            ));
+        }
+        if let Some(Command::Serve(args)) = &cli.command && args.selection.reads_stdin() {
+            return Err(clap::Error::raw(
+                ErrorKind::ArgumentConflict,
+                "serve re-reads the file list on rescan, so --files-from cannot be stdin\n",
+            ));
+        }
+        if stdin_taken {
+            return Err(clap::Error::raw(
+                ErrorKind::ArgumentConflict,
+                "--files-from - reads the file list from stdin; supply the token with --token-file, --token-env or --from-vault-cli\n",
+            ));
+        }
+        Ok(cli)
+    }
+
+    /// Only global options may precede a subcommand; update options given there would be
+    /// silently ignored (and `TOKEN scan` most likely meant a directory called `scan`)
+    fn reject_update_args_before(subcommand: &str, matches: &ArgMatches) -> Result<(), clap::Error> {
+        let command = Self::command();
+        let misplaced = command.get_arguments()
+            .filter(|arg| !arg.is_global_set())
+            .find(|arg| matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine));
+        match misplaced {
+            Some(arg) => Err(clap::Error::raw(
+                ErrorKind::ArgumentConflict,
+                format!(
+                    "'{}' cannot be used before the '{}' subcommand; update options belong after `update`\n",
+                    arg.get_long().map(|long| format!("--{}", long))
+                        .or_else(|| arg.get_value_names().and_then(|names| names.first()).map(|name| name.to_string()))
+                        .unwrap_or_else(|| arg.get_id().to_string()),
+                    subcommand,
+                ),
+            )),
+            None => Ok(()),
         }
     }
+}
 
-    /// Check if interactive input is needed (no token provided and not in dry-run mode)
+impl UpdateArgs {
+    /// Get the first search path, defaulting to current directory
+    pub fn get_search_path(&self) -> &Path {
+        self.selection.get_search_path()
+    }
+
+    /// The roots to search, including those from the config file
+    pub fn search_paths(&self) -> Vec<PathBuf> {
+        self.selection.search_paths()
+    }
+
+    /// Check if interactive input is needed (no token source given and not in dry-run mode)
     pub fn needs_interactive_input(&self) -> bool {
-        self.token.is_none() && !self.dry_run
+        self.token.is_none() && !self.token_args.has_source() && self.login.login.is_none() && !self.dry_run
+            && self.mapping.is_none()
+    }
+
+    /// The source the token will be read from
+    pub fn token_source(&self) -> TokenSource {
+        self.token_args.source(self.token.as_ref())
     }
 
-    /// Get the token, prompting for input if not provided
-    pub fn get_token(&mut self) -> Result<String> {
+    /// Get the token, reading it from the selected source or prompting for input.
+    ///
+    /// With --login the token is issued by Vault; with --unwrap the token read is unwrapped first.
+    pub fn get_token(&mut self) -> Result<SecretToken> {
         if self.dry_run {
             return Err(anyhow::anyhow!("Token not required in dry-run mode"));
         }
 
-        match &self.token {
-            Some(token) => Ok(token.clone()),
-            None => {
-                print!("Enter Vault token: ");
-                io::stdout().flush()?;
+        if let Some(token) = &self.resolved_token {
+            return Ok(token.clone());
+        }
 
-                let mut input = String::new();
-                io::stdin().read_line(&mut input)?;
+        let token = if self.login.login.is_some() {
+            self.login_to_vault()?.token
+        } else if self.login.unwrap {
+            let client = VaultClient::new(self.vault.config()?)?;
+            self.token_source().read_unwrapped(&client)?
+        } else {
+            self.token_source().read()?
+        };
+        // Store the token for future use
+        self.resolved_token = Some(token.clone());
+        Ok(token)
+    }
 
-                let token = input.trim().to_string();
-                if token.is_empty() {
-                    return Err(anyhow::anyhow!("Token cannot be empty"));
-                }
+    /// Logs in with the method selected by --login and keeps the issued token
+    pub fn login_to_vault(&mut self) -> Result<LoginResult> {
+        let method = self.login.login
+            .ok_or_else(|| anyhow::anyhow!("No login method selected"))?;
+        let client = VaultClient::new(self.vault.config()?)?;
+        let credentials = self.login.credentials(method, || self.token_source().read())?;
+        let result = login(&client, &credentials, self.login.auth_mount.as_deref())?;
 
-                // Store the token for future use
-                self.token = Some(token.clone());
-                Ok(token)
-            }
+        // Later calls to get_token reuse the issued token instead of logging in again
+        self.resolved_token = Some(result.token.clone());
+        self.parent_token.clone_from(&result.parent);
+        Ok(result)
+    }
+
+    /// The token that `--login token` created the new token from, if revoking it would revoke the new token too
+    pub fn parent_token(&self) -> Option<&SecretToken> {
+        self.parent_token.as_ref()
+    }
+
+    /// With --token-file, --token-stdin, --token-env, --from-vault-cli, --mapping or a login that needs no
+    /// parent token, the token is not positional, so every positional argument is a search path
+    fn resolve_positionals(&mut self) {
+        let login_issues_token = self.login.login.is_some_and(|method| method != LoginMethod::Token);
+        if (self.token_args.has_source() || self.mapping.is_some() || login_issues_token)
+            && let Some(token) = self.token.take()
+        {
+            self.selection.paths.insert(0, PathBuf::from(token.expose()));
         }
     }
 
+    /// Whether the token would be read from stdin while `--files-from -` also reads it
+    fn needs_stdin_for_token(&self) -> bool {
+        self.selection.reads_stdin() && (self.token_args.token_stdin || self.needs_interactive_input())
+    }
+
     /// Get the token if available, returns None in dry-run mode
-    pub fn get_token_if_needed(&mut self) -> Result<Option<String>> {
+    pub fn get_token_if_needed(&mut self) -> Result<Option<SecretToken>> {
         if self.dry_run {
             Ok(None)
         } else {
@@ -78,18 +970,25 @@ impl CliArgs {
     }
 }
 
-/// Parse command line arguments
+/// Parse command line arguments.
+///
+/// Only a config file named by --config is read, so the result does not depend on the working directory.
 pub fn parse_args<I, T>(args: I) -> Result<CliArgs>
 where
     I: IntoIterator<Item = T>,
     T: Into<std::ffi::OsString> + Clone,
 {
-    let cli = CliArgs::try_parse_from(args)?;
+    let matches = CliArgs::command().try_get_matches_from(args)?;
+    let mut cli = CliArgs::from_matches(&matches)?;
+    cli.apply_config(&matches, None)?;
     Ok(cli)
 }
 
-/// Parse command line arguments from env::args()
+/// Parse command line arguments from env::args(), applying the discovered config file
 pub fn parse_env_args() -> Result<CliArgs> {
-    let cli = CliArgs::parse();
+    let matches = CliArgs::command().get_matches();
+    let mut cli = CliArgs::from_matches(&matches).unwrap_or_else(|e| e.exit());
+    let discovered = if cli.config.is_none() { ProjectConfig::discover(env::current_dir()?) } else { None };
+    cli.apply_config(&matches, discovered)?;
     Ok(cli)
 }
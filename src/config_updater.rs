@@ -1,48 +1,415 @@
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 use anyhow::Result;
+use rayon::prelude::*;
 use regex::Regex;
+use serde::Serialize;
+use zeroize::Zeroizing;
+use crate::exit_code::{ExitCode, ValidationError};
+use crate::file_scanner::GitFile;
+use crate::hooks::{HookOutcome, HookStage, Hooks, PreHooks};
+use crate::secret::SecretToken;
 
-/// Updates all vaultToken values in a JSON string with the new token
-pub fn update_vault_token(json_content: &str, new_token: &str) -> Result<String> {
-    let re = Regex::new(r#""vaultToken"\s*:\s*"[^"]*""#)?;
-    let replacement = format!(r#""vaultToken": "{}""#, new_token);
+/// Key holding the token when no other keys are configured
+pub const DEFAULT_TOKEN_KEY: &str = "vaultToken";
+
+/// The JSON keys whose string values are Vault tokens (`vaultToken` unless configured)
+#[derive(Debug, Clone)]
+pub struct TokenFields {
+    keys: Vec<String>,
+    /// Matches a `"<key>": "<value>"` field, capturing the key and the value
+    pattern: Regex,
+}
+
+impl TokenFields {
+    /// Fields named by `keys`; an empty list means `vaultToken`
+    pub fn new<S: AsRef<str>>(keys: &[S]) -> Result<Self> {
+        let mut keys: Vec<String> = keys.iter().map(|key| key.as_ref().to_string()).collect();
+        if keys.is_empty() {
+            keys.push(DEFAULT_TOKEN_KEY.to_string());
+        }
+        if let Some(key) = keys.iter().find(|key| key.is_empty() || key.contains(['"', '\\'])) {
+            return Err(ValidationError::new(format!("{:?} is not a valid token key", key)).into());
+        }
+        let alternatives: Vec<String> = keys.iter().map(|key| regex::escape(key)).collect();
+        let pattern = Regex::new(&format!(r#""({})"\s*:\s*"([^"]*)""#, alternatives.join("|")))?;
+        Ok(Self { keys, pattern })
+    }
+
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Replaces the value of every token field with `new_token`.
+    ///
+    /// The returned string contains the token; callers should wipe it when done.
+    pub fn replace(&self, json_content: &str, new_token: &str) -> String {
+        self.pattern
+            .replace_all(json_content, |captures: &regex::Captures| {
+                format!(r#""{}": "{}""#, &captures[1], new_token)
+            })
+            .into_owned()
+    }
+
+    /// Number of token fields in `json_content`
+    pub fn count(&self, json_content: &str) -> usize {
+        self.pattern.find_iter(json_content).count()
+    }
+
+    /// The values of all token fields in `json_content`
+    pub fn values(&self, json_content: &str) -> Vec<SecretToken> {
+        self.pattern
+            .captures_iter(json_content)
+            .map(|captures| SecretToken::new(&captures[2]))
+            .collect()
+    }
+
+    /// Replaces the token fields in a file atomically
+    pub fn update_file<P: AsRef<Path>>(&self, file_path: P, new_token: &str) -> Result<()> {
+        let path = file_path.as_ref();
+        // Both versions hold tokens, so they are wiped when dropped
+        let original_content = Zeroizing::new(fs::read_to_string(path)?);
+        let updated_content = Zeroizing::new(self.replace(&original_content, new_token));
+        if updated_content != original_content {
+            // Use a temporary file for atomic updates
+            let temp_path = path.with_extension("tmp");
+            fs::write(&temp_path, &updated_content)?;
+            fs::rename(&temp_path, path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Counts the token fields in a file without modifying it
+    pub fn scan_file<P: AsRef<Path>>(&self, file_path: P) -> Result<usize> {
+        let content = Zeroizing::new(fs::read_to_string(file_path.as_ref())?);
+        Ok(self.count(&content))
+    }
+
+    /// Returns the values of all token fields in a file
+    pub fn collect_file<P: AsRef<Path>>(&self, file_path: P) -> Result<Vec<SecretToken>> {
+        let content = Zeroizing::new(fs::read_to_string(file_path.as_ref())?);
+        Ok(self.values(&content))
+    }
+}
+
+impl TokenFields {
+    /// The JSON pointer of every token field, in document order (e.g. `/database/vaultToken`)
+    pub fn pointers(&self, json_content: &str) -> Vec<String> {
+        let matches: Vec<(usize, String)> = self.pattern
+            .captures_iter(json_content)
+            .map(|captures| (captures.get(0).map_or(0, |m| m.start()), captures[1].to_string()))
+            .collect();
+        let offsets: Vec<usize> = matches.iter().map(|(offset, _)| *offset).collect();
+        enclosing_pointers(json_content, &offsets)
+            .into_iter()
+            .zip(matches)
+            .map(|(parent, (_, key))| format!("{}/{}", parent, escape_pointer_token(&key)))
+            .collect()
+    }
+
+    /// Replaces each token field with the token `tokens` assigns to it; other fields are left alone.
+    ///
+    /// When `tokens` depends on the file's environment and does not know it, nothing is replaced.
+    pub fn assign<T: TokenAssignment + ?Sized>(&self, json_content: &str, file: &Path, tokens: &T) -> Assignment {
+        let environment = tokens.environment_pointer().and_then(|pointer| environment_of(json_content, pointer));
+        if tokens.environment_pointer().is_some()
+            && !environment.as_deref().is_some_and(|environment| tokens.knows_environment(environment))
+        {
+            return Assignment {
+                content: Zeroizing::new(json_content.to_string()),
+                assigned: 0,
+                unassigned: self.count(json_content),
+                environment,
+                unknown_environment: true,
+            };
+        }
+
+        let pointers = if tokens.uses_key_paths() { self.pointers(json_content) } else { Vec::new() };
+        let (mut assigned, mut unassigned, mut index) = (0, 0, 0);
+        let updated = self.pattern
+            .replace_all(json_content, |captures: &regex::Captures| {
+                let field = TokenField {
+                    file,
+                    pointer: pointers.get(index).map_or("", String::as_str),
+                    environment: environment.as_deref(),
+                };
+                index += 1;
+                match tokens.token_for(&field) {
+                    Some(token) => {
+                        assigned += 1;
+                        format!(r#""{}": "{}""#, &captures[1], token)
+                    }
+                    None => {
+                        unassigned += 1;
+                        captures[0].to_string()
+                    }
+                }
+            })
+            .into_owned();
+        Assignment { content: Zeroizing::new(updated), assigned, unassigned, environment, unknown_environment: false }
+    }
+}
+
+/// Result of [`TokenFields::assign`]
+pub struct Assignment {
+    /// The new content; it holds tokens, so it is wiped when dropped
+    pub content: Zeroizing<String>,
+    /// Fields that were given a token
+    pub assigned: usize,
+    /// Fields left alone
+    pub unassigned: usize,
+    /// The file's environment, when the assignment depends on it
+    pub environment: Option<String>,
+    /// The file was skipped because its environment is missing or has no token
+    pub unknown_environment: bool,
+}
+
+/// A token field being assigned, with what is known about it
+#[derive(Debug, Clone, Copy)]
+pub struct TokenField<'a> {
+    pub file: &'a Path,
+    /// JSON pointer of the field, or "" when the assignment does not use key paths
+    pub pointer: &'a str,
+    /// Value of the file's environment field, when the assignment reads one
+    pub environment: Option<&'a str>,
+}
+
+/// Decides which token each token field receives during an update
+pub trait TokenAssignment: Sync {
+    /// The token for `field`, or `None` to leave the field alone
+    fn token_for(&self, field: &TokenField<'_>) -> Option<&str>;
+
+    /// Whether `token_for` looks at the pointer; when not, pointers are not computed
+    fn uses_key_paths(&self) -> bool {
+        false
+    }
+
+    /// JSON pointer of the field naming a file's environment, when tokens depend on it
+    fn environment_pointer(&self) -> Option<&str> {
+        None
+    }
+
+    /// Whether files in `environment` can be given a token; other files are skipped
+    fn knows_environment(&self, _environment: &str) -> bool {
+        true
+    }
+}
+
+/// A single token written into every field
+impl TokenAssignment for str {
+    fn token_for(&self, _field: &TokenField<'_>) -> Option<&str> {
+        Some(self)
+    }
+}
+
+/// The string, number or boolean at `pointer` in a JSON document, such as `/global/environment`
+pub fn environment_of(json_content: &str, pointer: &str) -> Option<String> {
+    let document: serde_json::Value = serde_json::from_str(json_content).ok()?;
+    match document.pointer(pointer)? {
+        serde_json::Value::String(value) => Some(value.clone()),
+        value @ (serde_json::Value::Number(_) | serde_json::Value::Bool(_)) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Nesting level while walking a JSON document
+enum Frame {
+    Object { key: Option<String>, expecting_key: bool },
+    Array { index: usize },
+}
+
+/// The JSON pointer of the object enclosing each offset (which must be ascending).
+///
+/// A lightweight walk that tracks only nesting, so malformed documents still give a best-effort answer.
+fn enclosing_pointers(json_content: &str, offsets: &[usize]) -> Vec<String> {
+    let bytes = json_content.as_bytes();
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut pointers = Vec::with_capacity(offsets.len());
+    let mut pending = offsets.iter().peekable();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        while pending.next_if(|&&offset| offset <= i).is_some() {
+            pointers.push(frames_pointer(&stack[..stack.len().saturating_sub(1)]));
+        }
+        match bytes[i] {
+            b'"' => {
+                let mut end = i + 1;
+                while end < bytes.len() && bytes[end] != b'"' {
+                    end += if bytes[end] == b'\\' { 2 } else { 1 };
+                }
+                if let Some(Frame::Object { key, expecting_key: true }) = stack.last_mut() {
+                    let raw = &json_content[i + 1..end.min(bytes.len())];
+                    *key = Some(serde_json::from_str(&format!("\"{}\"", raw)).unwrap_or_else(|_| raw.to_string()));
+                }
+                i = end;
+            }
+            b':' => {
+                if let Some(Frame::Object { expecting_key, .. }) = stack.last_mut() {
+                    *expecting_key = false;
+                }
+            }
+            b',' => match stack.last_mut() {
+                Some(Frame::Object { expecting_key, .. }) => *expecting_key = true,
+                Some(Frame::Array { index }) => *index += 1,
+                None => {}
+            },
+            b'{' => stack.push(Frame::Object { key: None, expecting_key: true }),
+            b'[' => stack.push(Frame::Array { index: 0 }),
+            b'}' | b']' => {
+                stack.pop();
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    // Offsets past the end still get an answer
+    pointers.resize(offsets.len(), String::new());
+    pointers
+}
+
+fn frames_pointer(frames: &[Frame]) -> String {
+    frames.iter().map(|frame| match frame {
+        Frame::Object { key, .. } => format!("/{}", escape_pointer_token(key.as_deref().unwrap_or(""))),
+        Frame::Array { index } => format!("/{}", index),
+    }).collect()
+}
 
-    let updated_content = re.replace_all(json_content, replacement.as_str());
-    Ok(updated_content.to_string())
+/// Escapes `~` and `/` in a pointer segment (RFC 6901)
+fn escape_pointer_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+impl Default for TokenFields {
+    fn default() -> Self {
+        Self::new(&[DEFAULT_TOKEN_KEY]).expect("the default token key is valid")
+    }
+}
+
+/// Updates all vaultToken values in a JSON string with the new token.
+///
+/// The returned string contains the token; callers should wipe it when done.
+pub fn update_vault_token(json_content: &str, new_token: &str) -> Result<String> {
+    Ok(TokenFields::default().replace(json_content, new_token))
 }
 
 /// Updates vaultToken values in a file atomically
 pub fn update_vault_token_in_file<P: AsRef<Path>>(file_path: P, new_token: &str) -> Result<()> {
-    let path = file_path.as_ref();
-    let original_content = fs::read_to_string(path)?;
-    let updated_content = update_vault_token(&original_content, new_token)?;
-    if updated_content != original_content {
-        // Use a temporary file for atomic updates
-        let temp_path = path.with_extension("tmp");
-        fs::write(&temp_path, &updated_content)?;
-        fs::rename(&temp_path, path)?;
-    }
+    TokenFields::default().update_file(file_path, new_token)
+}
 
-    Ok(())
+/// Outcome of processing a single file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    /// The file now holds the new token
+    Updated,
+    /// The file had no vaultToken fields
+    Unchanged,
+    /// Dry run: the file has vaultToken fields that would be replaced
+    WouldUpdate,
+    /// Dry run or verify: the file has no vaultToken fields
+    NoTokens,
+    /// Verify: every vaultToken field holds the expected token
+    Verified,
+    /// Verify: at least one vaultToken field holds a different token
+    Drifted,
+    /// Mapped update: the file has token fields, but no mapping rule covers them
+    Unmatched,
+    /// Mapped update: the file's environment field is missing or has no token source
+    UnknownEnvironment,
+    /// The file could not be processed
+    Failed,
+}
+
+/// Per-file result recorded in the run statistics
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileOutcome {
+    pub path: PathBuf,
+    pub status: FileStatus,
+    pub tokens: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Value of the environment field, for files skipped because of it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+    pub duration_ms: f64,
+    /// The tokens the file held before and after, for files that were written
+    #[serde(skip)]
+    pub change: Option<TokenChange>,
+}
+
+/// Fingerprints of the tokens a file held before and after it was written
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TokenChange {
+    /// Distinct fingerprints, in the order their fields appear
+    pub old: Vec<String>,
+    pub new: Vec<String>,
+}
+
+impl TokenChange {
+    fn between(fields: &TokenFields, old_content: &str, new_content: &str) -> Self {
+        let fingerprints = |content: &str| {
+            let mut distinct: Vec<String> = Vec::new();
+            for token in fields.values(content).into_iter().filter(|token| !token.is_empty()) {
+                let fingerprint = token.fingerprint();
+                if !distinct.contains(&fingerprint) {
+                    distinct.push(fingerprint);
+                }
+            }
+            distinct
+        };
+        Self { old: fingerprints(old_content), new: fingerprints(new_content) }
+    }
 }
 
 /// Statistics about the update operation
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, PartialEq, Default, Serialize)]
 pub struct UpdateStats {
     pub files_processed: usize,
     pub files_updated: usize,
     pub tokens_replaced: usize,
+    /// Files with token fields that no mapping rule covered (`--mapping`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unmatched: Vec<PathBuf>,
+    /// Files skipped because their environment is missing or has no token source
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_environment: Vec<PathBuf>,
+    pub errors: Vec<String>,
+    pub files: Vec<FileOutcome>,
+    /// Hook commands that ran, in the order they started
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hooks: Vec<HookOutcome>,
+    /// Files that git tracks, does not ignore or could not check (`--git-policy`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub git_exposed: Vec<GitFile>,
+}
+
+/// Statistics about a verification run
+#[derive(Debug, PartialEq, Default, Serialize)]
+pub struct VerifyStats {
+    /// Fingerprint of the token every field is expected to hold
+    pub expected_fingerprint: String,
+    pub files_checked: usize,
+    pub files_verified: usize,
+    pub files_drifted: usize,
+    /// Files holding a different token
+    pub drifted: Vec<PathBuf>,
     pub errors: Vec<String>,
+    pub files: Vec<FileOutcome>,
 }
 
 /// Statistics about the scan operation (dry-run mode)
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, PartialEq, Default, Serialize)]
 pub struct ScanStats {
     pub files_scanned: usize,
     pub files_with_tokens: usize,
     pub total_tokens_found: usize,
     pub errors: Vec<String>,
+    pub files: Vec<FileOutcome>,
 }
 
 impl UpdateStats {
@@ -53,6 +420,52 @@ impl UpdateStats {
     pub fn add_error(&mut self, error: String) {
         self.errors.push(error);
     }
+
+    /// Adds a per-file outcome to the totals
+    pub fn record(&mut self, outcome: FileOutcome) {
+        self.files_processed += 1;
+        match outcome.status {
+            FileStatus::Updated => {
+                self.files_updated += 1;
+                self.tokens_replaced += outcome.tokens;
+            }
+            FileStatus::Unmatched => self.unmatched.push(outcome.path.clone()),
+            FileStatus::UnknownEnvironment => self.unknown_environment.push(outcome.path.clone()),
+            FileStatus::Failed => {
+                let error = outcome.error.as_deref().unwrap_or("unknown error");
+                self.add_error(format!("Failed to process {}: {}", outcome.path.display(), error));
+            }
+            _ => {}
+        }
+        self.files.push(outcome);
+    }
+
+    /// Adds a hook result; a failed hook counts as an error
+    pub fn record_hook(&mut self, outcome: HookOutcome) {
+        // A file whose own pre hook failed is reported as a failed file already
+        let reported = outcome.stage == HookStage::Pre && !outcome.files.is_empty();
+        if !outcome.succeeded() && !reported {
+            let stage = match outcome.stage {
+                HookStage::Pre => "Pre-update",
+                HookStage::Post => "Post-update",
+            };
+            self.add_error(format!("{} hook {}", stage, outcome.describe()));
+        }
+        self.hooks.push(outcome);
+    }
+
+    /// Exit code describing the outcome of the update.
+    ///
+    /// A mapped run that left files without a token is a partial failure, so CI can tell it from
+    /// a full rotation.
+    pub fn exit_code(&self) -> ExitCode {
+        let code = ExitCode::from_counts(self.files_updated, self.errors.len(), self.files_processed);
+        if code == ExitCode::Success && !(self.unmatched.is_empty() && self.unknown_environment.is_empty()) {
+            ExitCode::PartialFailure
+        } else {
+            code
+        }
+    }
 }
 
 impl ScanStats {
@@ -63,14 +476,212 @@ impl ScanStats {
     pub fn add_error(&mut self, error: String) {
         self.errors.push(error);
     }
+
+    /// Adds a per-file outcome to the totals
+    pub fn record(&mut self, outcome: FileOutcome) {
+        self.files_scanned += 1;
+        match outcome.status {
+            FileStatus::WouldUpdate => {
+                self.files_with_tokens += 1;
+                self.total_tokens_found += outcome.tokens;
+            }
+            FileStatus::Failed => {
+                let error = outcome.error.as_deref().unwrap_or("unknown error");
+                self.add_error(format!("Failed to scan {}: {}", outcome.path.display(), error));
+            }
+            _ => {}
+        }
+        self.files.push(outcome);
+    }
+
+    /// Exit code describing the outcome of the scan
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from_counts(self.files_with_tokens, self.errors.len(), self.files_scanned)
+    }
+}
+
+impl VerifyStats {
+    pub fn new<S: Into<String>>(expected_fingerprint: S) -> Self {
+        Self {
+            expected_fingerprint: expected_fingerprint.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Adds a per-file outcome to the totals
+    pub fn record(&mut self, outcome: FileOutcome) {
+        self.files_checked += 1;
+        match outcome.status {
+            FileStatus::Verified => self.files_verified += 1,
+            FileStatus::Drifted => {
+                self.files_drifted += 1;
+                self.drifted.push(outcome.path.clone());
+            }
+            FileStatus::Failed => {
+                let error = outcome.error.as_deref().unwrap_or("unknown error");
+                self.errors.push(format!("Failed to verify {}: {}", outcome.path.display(), error));
+            }
+            _ => {}
+        }
+        self.files.push(outcome);
+    }
+
+    /// Drift takes precedence over read errors, so CI always sees it as code 6
+    pub fn exit_code(&self) -> ExitCode {
+        if self.files_drifted > 0 {
+            ExitCode::DriftDetected
+        } else {
+            ExitCode::from_counts(self.files_verified, self.errors.len(), self.files_checked)
+        }
+    }
 }
 
 /// Scans a file for vaultToken fields without modifying it
 pub fn scan_vault_tokens_in_file<P: AsRef<Path>>(file_path: P) -> Result<usize> {
+    TokenFields::default().scan_file(file_path)
+}
+
+/// Returns the values of all vaultToken fields in a file
+pub fn collect_vault_tokens_in_file<P: AsRef<Path>>(file_path: P) -> Result<Vec<SecretToken>> {
+    TokenFields::default().collect_file(file_path)
+}
+
+/// Compares every vaultToken field in a file with the token identified by `expected_fingerprint`.
+///
+/// Returns the number of fields holding the expected token and the number holding anything else.
+pub fn verify_vault_tokens_in_file<P: AsRef<Path>>(file_path: P, expected_fingerprint: &str) -> Result<(usize, usize)> {
+    verify_tokens_in_file(file_path, expected_fingerprint, &TokenFields::default())
+}
+
+fn verify_tokens_in_file<P: AsRef<Path>>(file_path: P, expected_fingerprint: &str, fields: &TokenFields) -> Result<(usize, usize)> {
+    let tokens = fields.collect_file(file_path)?;
+    let matching = tokens.iter().filter(|token| token.fingerprint() == expected_fingerprint).count();
+    Ok((matching, tokens.len() - matching))
+}
+
+/// Returns the distinct token values across files, ordered by fingerprint.
+///
+/// Files that cannot be read are skipped; the update reports them separately.
+pub fn collect_distinct_vault_tokens(files: &[PathBuf], fields: &TokenFields) -> Vec<SecretToken> {
+    let tokens: BTreeMap<String, SecretToken> = files
+        .par_iter()
+        .filter_map(|file| fields.collect_file(file).ok())
+        .flatten()
+        .filter(|token| !token.is_empty())
+        .map(|token| (token.fingerprint(), token))
+        .collect();
+    tokens.into_values().collect()
+}
+
+/// Updates a single file and describes what happened to it; `tokens` picks the token of each field
+pub fn update_file_outcome<P, T>(file_path: P, tokens: &T, fields: &TokenFields) -> FileOutcome
+where
+    P: AsRef<Path>,
+    T: TokenAssignment + ?Sized,
+{
+    update_file_with(file_path.as_ref(), tokens, fields, |_| Ok(()))
+}
+
+/// Updates a single file, calling `before_write` only when its content is about to change
+fn update_file_with<T, H>(path: &Path, tokens: &T, fields: &TokenFields, before_write: H) -> FileOutcome
+where
+    T: TokenAssignment + ?Sized,
+    H: FnOnce(&Path) -> Result<()>,
+{
+    let start = Instant::now();
+
+    let mut environment = None;
+    let mut change = None;
+    let (status, tokens, error) = match assign_tokens_in_file(path, tokens, fields, before_write) {
+        Ok((assignment, _)) if assignment.unknown_environment => {
+            environment = assignment.environment;
+            (FileStatus::UnknownEnvironment, 0, None)
+        }
+        Ok((Assignment { assigned: 0, unassigned: 0, .. }, _)) => (FileStatus::Unchanged, 0, None),
+        Ok((Assignment { assigned: 0, .. }, _)) => (FileStatus::Unmatched, 0, None),
+        Ok((assignment, written)) => {
+            change = written;
+            (FileStatus::Updated, assignment.assigned, None)
+        }
+        Err(e) => (FileStatus::Failed, 0, Some(e.to_string())),
+    };
+
+    FileOutcome {
+        path: path.to_path_buf(),
+        status,
+        tokens,
+        error,
+        environment,
+        duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+        change,
+    }
+}
+
+/// Rewrites the token fields of a file atomically, returning what was assigned and, when the
+/// content changed, how; `before_write` can veto the write
+fn assign_tokens_in_file<T, H>(path: &Path, tokens: &T, fields: &TokenFields, before_write: H) -> Result<(Assignment, Option<TokenChange>)>
+where
+    T: TokenAssignment + ?Sized,
+    H: FnOnce(&Path) -> Result<()>,
+{
+    // Both versions hold tokens, so they are wiped when dropped
+    let original_content = Zeroizing::new(fs::read_to_string(path)?);
+    let assignment = fields.assign(&original_content, path, tokens);
+    if assignment.content == original_content {
+        return Ok((assignment, None));
+    }
+    before_write(path)?;
+    // Use a temporary file for atomic updates
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, &assignment.content)?;
+    fs::rename(&temp_path, path)?;
+    let change = TokenChange::between(fields, &original_content, &assignment.content);
+    Ok((assignment, Some(change)))
+}
+
+/// Scans a single file and describes what an update would do to it
+pub fn scan_file_outcome<P: AsRef<Path>>(file_path: P, fields: &TokenFields) -> FileOutcome {
     let path = file_path.as_ref();
-    let content = fs::read_to_string(path)?;
-    let re = Regex::new(r#""vaultToken"\s*:\s*"[^"]*""#)?;
-    Ok(re.find_iter(&content).count())
+    let start = Instant::now();
+
+    let (status, tokens, error) = match fields.scan_file(path) {
+        Ok(0) => (FileStatus::NoTokens, 0, None),
+        Ok(count) => (FileStatus::WouldUpdate, count, None),
+        Err(e) => (FileStatus::Failed, 0, Some(e.to_string())),
+    };
+
+    FileOutcome {
+        path: path.to_path_buf(),
+        status,
+        tokens,
+        error,
+        environment: None,
+        duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+        change: None,
+    }
+}
+
+/// Verifies a single file; `tokens` counts the fields holding a different token when it drifted
+pub fn verify_file_outcome<P: AsRef<Path>>(file_path: P, expected_fingerprint: &str, fields: &TokenFields) -> FileOutcome {
+    let path = file_path.as_ref();
+    let start = Instant::now();
+
+    let (status, tokens, error) = match verify_tokens_in_file(path, expected_fingerprint, fields) {
+        Ok((0, 0)) => (FileStatus::NoTokens, 0, None),
+        Ok((matching, 0)) => (FileStatus::Verified, matching, None),
+        Ok((_, mismatched)) => (FileStatus::Drifted, mismatched, None),
+        Err(e) => (FileStatus::Failed, 0, Some(e.to_string())),
+    };
+
+    FileOutcome {
+        path: path.to_path_buf(),
+        status,
+        tokens,
+        error,
+        environment: None,
+        duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+        change: None,
+    }
 }
 
 /// Scans vault tokens in multiple files and returns statistics
@@ -78,19 +689,7 @@ pub fn scan_vault_tokens_in_files<P: AsRef<Path>>(file_paths: &[P]) -> ScanStats
     let mut stats = ScanStats::new();
 
     for path in file_paths {
-        stats.files_scanned += 1;
-
-        match scan_vault_tokens_in_file(path) {
-            Ok(token_count) => {
-                if token_count > 0 {
-                    stats.files_with_tokens += 1;
-                    stats.total_tokens_found += token_count;
-                }
-            }
-            Err(e) => {
-                stats.add_error(format!("Error scanning {:?}: {}", path.as_ref(), e));
-            }
-        }
+        stats.record(scan_file_outcome(path, &TokenFields::default()));
     }
 
     stats
@@ -104,23 +703,128 @@ pub fn update_vault_tokens_in_files<P: AsRef<Path>>(
     let mut stats = UpdateStats::new();
 
     for path in file_paths {
-        stats.files_processed += 1;
-
-        match update_vault_token_in_file(path, new_token) {
-            Ok(_) => {
-                if let Ok(content) = fs::read_to_string(path) {
-                    let token_count = content.matches(&format!(r#""vaultToken": "{}""#, new_token)).count();
-                    if token_count > 0 {
-                        stats.files_updated += 1;
-                        stats.tokens_replaced += token_count;
-                    }
-                }
-            }
-            Err(e) => {
-                stats.add_error(format!("Error processing {:?}: {}", path.as_ref(), e));
+        stats.record(update_file_outcome(path, new_token, &TokenFields::default()));
+    }
+
+    stats
+}
+
+/// Updates vault tokens in multiple files concurrently.
+///
+/// `tokens` is either a single token (`&str`) or an assignment such as a [`TokenMapping`](crate::TokenMapping).
+/// `on_file` is called from the worker threads as soon as each file is done,
+/// which lets callers stream progress; the returned statistics keep the input order.
+///
+/// `hooks` run around the writes: the global `pre` command first (when it fails, no file is
+/// touched), a file's `pre` commands just before it is written, and the `post` commands once all
+/// writes are done. Per-file hooks only run for files whose content changes.
+pub fn update_files_parallel<T, F>(files: &[PathBuf], tokens: &T, fields: &TokenFields, hooks: &Hooks, on_file: F) -> UpdateStats
+where
+    T: TokenAssignment + ?Sized,
+    F: Fn(&FileOutcome) + Sync,
+{
+    let mut stats = UpdateStats::new();
+    if let Some(command) = hooks.global(HookStage::Pre) {
+        let outcome = hooks.run(HookStage::Pre, command, Vec::new());
+        let succeeded = outcome.succeeded();
+        stats.record_hook(outcome);
+        if !succeeded {
+            return stats;
+        }
+    }
+
+    let pre_hooks = PreHooks::new(hooks);
+    let results: Vec<_> = files
+        .par_iter()
+        .map(|file| {
+            let outcome = update_file_with(file, tokens, fields, |path| pre_hooks.run_for(path));
+            on_file(&outcome);
+            outcome
+        })
+        .collect();
+    for outcome in pre_hooks.into_outcomes() {
+        stats.record_hook(outcome);
+    }
+
+    let written: Vec<PathBuf> = results
+        .iter()
+        .filter(|outcome| outcome.change.is_some())
+        .map(|outcome| outcome.path.clone())
+        .collect();
+    for outcome in results {
+        stats.record(outcome);
+    }
+    run_post_hooks(&written, hooks, &mut stats);
+
+    stats
+}
+
+/// Runs the `post` commands for the written files, each distinct command once, then the global one
+fn run_post_hooks(written: &[PathBuf], hooks: &Hooks, stats: &mut UpdateStats) {
+    if written.is_empty() {
+        return;
+    }
+    let mut commands: Vec<(String, Vec<PathBuf>)> = Vec::new();
+    for file in written {
+        for command in hooks.commands_for(HookStage::Post, file) {
+            match commands.iter_mut().find(|(known, _)| *known == command) {
+                Some((_, files)) => files.push(file.clone()),
+                None => commands.push((command, vec![file.clone()])),
             }
         }
     }
+    let outcomes: Vec<_> = commands
+        .into_par_iter()
+        .map(|(command, files)| hooks.run(HookStage::Post, &command, files))
+        .collect();
+    for outcome in outcomes {
+        stats.record_hook(outcome);
+    }
+    if let Some(command) = hooks.global(HookStage::Post) {
+        stats.record_hook(hooks.run(HookStage::Post, command, Vec::new()));
+    }
+}
+
+/// Scans vault tokens in multiple files concurrently (dry-run mode)
+pub fn scan_files_parallel<F>(files: &[PathBuf], fields: &TokenFields, on_file: F) -> ScanStats
+where
+    F: Fn(&FileOutcome) + Sync,
+{
+    let outcomes: Vec<_> = files
+        .par_iter()
+        .map(|file| {
+            let outcome = scan_file_outcome(file, fields);
+            on_file(&outcome);
+            outcome
+        })
+        .collect();
+
+    let mut stats = ScanStats::new();
+    for outcome in outcomes {
+        stats.record(outcome);
+    }
+
+    stats
+}
+
+/// Verifies multiple files concurrently against the expected token fingerprint
+pub fn verify_files_parallel<F>(files: &[PathBuf], expected_fingerprint: &str, fields: &TokenFields, on_file: F) -> VerifyStats
+where
+    F: Fn(&FileOutcome) + Sync,
+{
+    let outcomes: Vec<_> = files
+        .par_iter()
+        .map(|file| {
+            let outcome = verify_file_outcome(file, expected_fingerprint, fields);
+            on_file(&outcome);
+            outcome
+        })
+        .collect();
+
+    let mut stats = VerifyStats::new(expected_fingerprint);
+    for outcome in outcomes {
+        stats.record(outcome);
+    }
 
     stats
 }
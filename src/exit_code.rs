@@ -0,0 +1,81 @@
+use std::fmt;
+use std::process;
+
+/// Process exit codes returned by the CLI.
+///
+/// These values are part of the public interface: scripts rely on them to tell
+/// outcomes apart, so existing codes must never be renumbered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// Every file was processed and at least one token was found or replaced
+    Success = 0,
+    /// Fatal error, or every file that was processed failed
+    Failure = 1,
+    /// Invalid command-line usage (reported by the argument parser)
+    Usage = 2,
+    /// Nothing to do: no config files or no vaultToken fields were found
+    NothingToDo = 3,
+    /// Some files were processed successfully, others failed
+    PartialFailure = 4,
+    /// Input was rejected before any file was touched (e.g. an empty token)
+    ValidationFailed = 5,
+    /// Verification found files holding an unexpected token
+    DriftDetected = 6,
+}
+
+impl ExitCode {
+    /// Numeric value passed to the operating system
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Classify a fatal error into an exit code
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        if error.downcast_ref::<ValidationError>().is_some() {
+            ExitCode::ValidationFailed
+        } else if error.downcast_ref::<clap::Error>().is_some() {
+            ExitCode::Usage
+        } else {
+            ExitCode::Failure
+        }
+    }
+
+    /// Classify a finished run from its success and error counts
+    pub fn from_counts(succeeded: usize, failed: usize, total: usize) -> Self {
+        if failed == 0 {
+            if succeeded > 0 {
+                ExitCode::Success
+            } else {
+                ExitCode::NothingToDo
+            }
+        } else if failed >= total {
+            ExitCode::Failure
+        } else {
+            ExitCode::PartialFailure
+        }
+    }
+}
+
+impl From<ExitCode> for process::ExitCode {
+    fn from(code: ExitCode) -> Self {
+        process::ExitCode::from(code.code())
+    }
+}
+
+/// Error raised when user input is rejected before any file is modified
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError(pub String);
+
+impl ValidationError {
+    pub fn new<S: Into<String>>(message: S) -> Self {
+        Self(message.into())
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ValidationError {}
@@ -1,9 +1,140 @@
-use std::path::{Path, PathBuf};
-use anyhow::Result;
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use globset::{Glob, GlobBuilder, GlobMatcher, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
 use walkdir::WalkDir;
+use crate::exit_code::ValidationError;
+
+/// Include and exclude globs, matched against paths relative to the search root.
+///
+/// `*` stays within one directory, `**` crosses directories. With no include
+/// globs every file is included; an exclude glob always wins.
+#[derive(Debug, Clone, Default)]
+pub struct FileFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl FileFilter {
+    pub fn new<S: AsRef<str>>(include: &[S], exclude: &[S]) -> Result<Self> {
+        Ok(Self {
+            include: build_glob_set(include)?,
+            exclude: build_glob_set(exclude)?,
+        })
+    }
+
+    /// Whether a file at `relative_path` (below the search root) should be processed
+    pub fn matches(&self, relative_path: &Path) -> bool {
+        self.include.as_ref().is_none_or(|include| include.is_match(relative_path))
+            && !self.exclude.as_ref().is_some_and(|exclude| exclude.is_match(relative_path))
+    }
+}
+
+fn build_glob_set<S: AsRef<str>>(patterns: &[S]) -> Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(parse_glob(pattern.as_ref())?);
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// Compiles a single glob with the same rules as [`FileFilter`]
+pub(crate) fn compile_glob(pattern: &str) -> Result<GlobMatcher> {
+    Ok(parse_glob(pattern)?.compile_matcher())
+}
+
+fn parse_glob(pattern: &str) -> Result<Glob> {
+    Ok(GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .map_err(|e| ValidationError::new(format!("Invalid glob {:?}: {}", pattern, e.kind())))?)
+}
+
+/// The config files found under one search root, or listed by `--files-from`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchRoot {
+    pub path: PathBuf,
+    pub files: Vec<PathBuf>,
+}
+
+/// Whether a file is named config.json or globalConfig.json
+pub fn is_config_file<P: AsRef<Path>>(path: P) -> bool {
+    matches!(path.as_ref().file_name().and_then(|name| name.to_str()), Some("config.json" | "globalConfig.json"))
+}
+
+/// Reads a newline-separated file list, such as `git ls-files` or `fd` output.
+///
+/// Only config files accepted by `filter` are kept; the globs are matched against the paths as listed.
+pub fn read_file_list<R: BufRead>(reader: R, filter: &FileFilter) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let path = PathBuf::from(line);
+        if is_config_file(&path) && filter.matches(path.strip_prefix(".").unwrap_or(&path)) {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Reads the file list named by `--files-from`; `-` reads stdin
+pub fn read_file_list_from<P: AsRef<Path>>(source: P, filter: &FileFilter) -> Result<Vec<PathBuf>> {
+    let source = source.as_ref();
+    if source == Path::new("-") {
+        return read_file_list(io::stdin().lock(), filter);
+    }
+    let file = File::open(source).map_err(|e| anyhow!("Failed to read file list {}: {}", source.display(), e))?;
+    read_file_list(BufReader::new(file), filter)
+}
+
+/// Finds the config files under every root, then those listed in `files_from`; each file is kept once
+pub fn find_config_files_in_roots(paths: &[PathBuf], files_from: Option<&Path>, filter: &FileFilter) -> Result<Vec<SearchRoot>> {
+    let mut roots = Vec::new();
+    for path in paths {
+        roots.push(SearchRoot { path: path.clone(), files: find_config_files_filtered(path, filter)? });
+    }
+    if let Some(list) = files_from {
+        roots.push(SearchRoot { path: list.to_path_buf(), files: read_file_list_from(list, filter)? });
+    }
+    dedupe_roots(&mut roots);
+    Ok(roots)
+}
+
+/// Removes files already claimed by an earlier root (or listed twice), so each file is processed once
+pub fn dedupe_roots(roots: &mut [SearchRoot]) {
+    let mut seen = HashSet::new();
+    for root in roots {
+        root.files.retain(|file| seen.insert(dedupe_key(file)));
+    }
+}
+
+/// `./a/config.json` and `a/config.json` are the same file; symlinks and `..` are resolved when possible
+fn dedupe_key(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .unwrap_or_else(|_| path.components().filter(|component| *component != Component::CurDir).collect())
+}
 
 /// Finds all config.json and globalConfig.json files recursively in the given directory
 pub fn find_config_files<P: AsRef<Path>>(search_path: P) -> Result<Vec<PathBuf>> {
+    find_config_files_filtered(search_path, &FileFilter::default())
+}
+
+/// Like [`find_config_files`], keeping only the files accepted by `filter`
+pub fn find_config_files_filtered<P: AsRef<Path>>(search_path: P, filter: &FileFilter) -> Result<Vec<PathBuf>> {
     let mut config_files = Vec::new();
 
     let walker = WalkDir::new(search_path.as_ref())
@@ -15,9 +146,8 @@ pub fn find_config_files<P: AsRef<Path>>(search_path: P) -> Result<Vec<PathBuf>>
         let path = entry.path();
 
         if path.is_file()
-            && let Some(file_name) = path.file_name()
-            && let Some(name_str) = file_name.to_str()
-            && (name_str == "config.json" || name_str == "globalConfig.json")
+            && is_config_file(path)
+            && filter.matches(path.strip_prefix(search_path.as_ref()).unwrap_or(path))
         {
             config_files.push(path.to_path_buf());
         }
@@ -29,3 +159,287 @@ pub fn find_config_files<P: AsRef<Path>>(search_path: P) -> Result<Vec<PathBuf>>
 
     Ok(config_files)
 }
+
+/// What an update does with config files that a git repository could pick up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GitPolicy {
+    /// Do not look at git
+    Off,
+    /// Write the token and report the exposed files
+    #[default]
+    Warn,
+    /// Leave the exposed files alone
+    Skip,
+    /// Write nothing while any file is exposed
+    Fail,
+}
+
+/// Where a config file stands in the git work tree holding it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GitStatus {
+    /// Committed or staged, so a new token shows up in `git diff`
+    Tracked,
+    /// Neither tracked nor ignored, so `git add .` picks it up
+    Untracked,
+    /// Covered by a `.gitignore` rule
+    Ignored,
+    /// git could not be asked, e.g. because it is not installed
+    Unknown,
+}
+
+/// A config file inside a git work tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GitFile {
+    /// The path as found
+    pub path: PathBuf,
+    /// Top directory of the work tree
+    pub repo: PathBuf,
+    pub status: GitStatus,
+}
+
+impl GitFile {
+    /// Whether a token written to the file could end up in the repository
+    pub fn is_exposed(&self) -> bool {
+        self.status != GitStatus::Ignored
+    }
+
+    /// Describes the exposure, e.g. "./api/config.json is tracked by git in /srv/app"
+    pub fn describe(&self) -> String {
+        let how = match self.status {
+            GitStatus::Tracked => "is tracked by git",
+            GitStatus::Untracked => "is not ignored by git",
+            GitStatus::Ignored => "is ignored by git",
+            GitStatus::Unknown => "could not be checked against git",
+        };
+        format!("{} {} in {}", self.path.display(), how, self.repo.display())
+    }
+
+    /// The path below the work tree, with `/` separators
+    fn relative(&self) -> PathBuf {
+        let absolute = std::path::absolute(&self.path).unwrap_or_else(|_| self.path.clone());
+        absolute.strip_prefix(&self.repo).unwrap_or(&absolute)
+            .components()
+            .filter(|component| *component != Component::CurDir)
+            .collect()
+    }
+}
+
+/// Finds the git work tree holding each file and asks git whether it tracks or ignores the file.
+///
+/// Files outside any work tree are left out. git runs at most twice per work tree; when it
+/// fails, the files of that work tree get [`GitStatus::Unknown`] and the error is returned alongside.
+pub fn git_status_of(files: &[PathBuf]) -> (Vec<GitFile>, Vec<String>) {
+    let mut by_repo: BTreeMap<PathBuf, Vec<GitFile>> = BTreeMap::new();
+    for file in files {
+        let Ok(absolute) = std::path::absolute(file) else {
+            continue;
+        };
+        let repo = absolute.parent().and_then(|dir| dir.ancestors().find(|dir| dir.join(".git").exists()));
+        if let Some(repo) = repo {
+            by_repo.entry(repo.to_path_buf()).or_default().push(GitFile {
+                path: file.clone(),
+                repo: repo.to_path_buf(),
+                status: GitStatus::Unknown,
+            });
+        }
+    }
+
+    let (mut checked, mut errors) = (Vec::new(), Vec::new());
+    for (repo, mut files) in by_repo {
+        match classify(&repo, &mut files) {
+            Ok(()) => {}
+            Err(e) => errors.push(format!("Cannot check the git status of the files in {}: {}", repo.display(), e)),
+        }
+        checked.extend(files);
+    }
+    // Report in the order the files were given
+    let order: BTreeMap<&PathBuf, usize> = files.iter().enumerate().map(|(index, file)| (file, index)).collect();
+    checked.sort_by_key(|file| order.get(&file.path).copied());
+    (checked, errors)
+}
+
+/// Sets the status of `files`, all in the work tree `repo`
+fn classify(repo: &Path, files: &mut [GitFile]) -> Result<()> {
+    let relative: Vec<PathBuf> = files.iter().map(GitFile::relative).collect();
+    let mut ls_files = vec!["--literal-pathspecs".into(), "ls-files".into(), "-z".into(), "--".into()];
+    ls_files.extend(relative.iter().map(|path| path.clone().into_os_string()));
+    let tracked: HashSet<PathBuf> = run_git(repo, &ls_files, None)?.into_iter().collect();
+
+    let untracked: Vec<&PathBuf> = relative.iter().filter(|path| !tracked.contains(*path)).collect();
+    let mut input = Vec::new();
+    for path in &untracked {
+        input.extend_from_slice(path.as_os_str().as_encoded_bytes());
+        input.push(0);
+    }
+    let check_ignore = ["check-ignore".into(), "-z".into(), "--stdin".into()];
+    let ignored: HashSet<PathBuf> = if untracked.is_empty() {
+        HashSet::new()
+    } else {
+        run_git(repo, &check_ignore, Some(&input))?.into_iter().collect()
+    };
+
+    for (file, path) in files.iter_mut().zip(&relative) {
+        file.status = if tracked.contains(path) {
+            GitStatus::Tracked
+        } else if ignored.contains(path) {
+            GitStatus::Ignored
+        } else {
+            GitStatus::Untracked
+        };
+    }
+    Ok(())
+}
+
+/// Runs git in `repo` and splits its NUL-separated output into paths.
+///
+/// Exit code 1 is not an error: `check-ignore` uses it when nothing is ignored.
+fn run_git(repo: &Path, args: &[OsString], input: Option<&[u8]>) -> Result<Vec<PathBuf>> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("git could not be run: {}", e))?;
+    let mut stdin = child.stdin.take();
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    // Fed from a thread, so git never blocks on a full stdout pipe while the input is written
+    let (output, errors) = thread::scope(|scope| {
+        if let (Some(mut stdin), Some(input)) = (stdin.take(), input) {
+            scope.spawn(move || stdin.write_all(input));
+        }
+        let errors = scope.spawn(move || {
+            let mut errors = String::new();
+            let _ = stderr.read_to_string(&mut errors);
+            errors
+        });
+        let mut output = Vec::new();
+        let _ = stdout.read_to_end(&mut output);
+        (output, errors.join().unwrap_or_default())
+    });
+    let status = child.wait()?;
+    if !matches!(status.code(), Some(0 | 1)) {
+        let subcommand = args.iter().map(|arg| arg.to_string_lossy()).find(|arg| !arg.starts_with('-')).unwrap_or_default();
+        return Err(anyhow!("git {} failed: {}", subcommand, errors.lines().next().unwrap_or("").trim()));
+    }
+    Ok(output
+        .split(|byte| *byte == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| PathBuf::from(String::from_utf8_lossy(path).into_owned()))
+        .collect())
+}
+
+/// Adds an entry for the file to the `.gitignore` at the top of its work tree.
+///
+/// Returns false when the entry was already there.
+pub fn add_to_gitignore(file: &GitFile) -> Result<bool> {
+    let entry = gitignore_entry(&file.relative());
+    let gitignore = file.repo.join(".gitignore");
+    let existing = match fs::read_to_string(&gitignore) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(anyhow!("Cannot read {}: {}", gitignore.display(), e)),
+    };
+    if existing.lines().any(|line| line.trim_end() == entry) {
+        return Ok(false);
+    }
+    let separator = if existing.is_empty() || existing.ends_with('\n') { "" } else { "\n" };
+    OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&gitignore)
+        .and_then(|mut out| writeln!(out, "{}{}", separator, entry))
+        .map_err(|e| anyhow!("Cannot write {}: {}", gitignore.display(), e))?;
+    Ok(true)
+}
+
+/// An anchored `.gitignore` pattern matching exactly `relative`
+fn gitignore_entry(relative: &Path) -> String {
+    let mut entry = String::from("/");
+    for (index, component) in relative.components().enumerate() {
+        if index > 0 {
+            entry.push('/');
+        }
+        for c in component.as_os_str().to_string_lossy().chars() {
+            if matches!(c, '\\' | '*' | '?' | '[') {
+                entry.push('\\');
+            }
+            entry.push(c);
+        }
+    }
+    entry
+}
+
+/// The files an update may write, after checking them against a [`GitPolicy`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitCheck {
+    /// The files to write
+    pub files: Vec<PathBuf>,
+    /// Files that git tracks, does not ignore or could not check
+    pub exposed: Vec<GitFile>,
+    /// Files this check added to `.gitignore`, with the status git reports afterwards
+    pub ignored: Vec<GitFile>,
+    /// Why git could not be asked about some files
+    pub errors: Vec<String>,
+}
+
+/// Checks the files an update is about to write against `policy`.
+///
+/// With `gitignore`, untracked files are first added to their work tree's `.gitignore` and
+/// checked again, so a file that another rule re-includes stays exposed; a tracked file stays
+/// exposed until it is removed from the index. `Skip`
+/// drops the exposed files, and `Fail` rejects the whole update when any file is exposed.
+pub fn check_git(files: Vec<PathBuf>, policy: GitPolicy, gitignore: bool) -> Result<GitCheck> {
+    if policy == GitPolicy::Off {
+        return Ok(GitCheck { files, ..GitCheck::default() });
+    }
+    let (mut statuses, mut errors) = git_status_of(&files);
+    let mut added = HashSet::new();
+    if gitignore {
+        for file in statuses.iter().filter(|file| file.status == GitStatus::Untracked) {
+            match add_to_gitignore(file) {
+                Ok(true) => {
+                    added.insert(file.path.clone());
+                }
+                Ok(false) => {}
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+        // Ask git again: a later `!` rule or a deeper .gitignore can still re-include a file
+        if !added.is_empty() {
+            let (rechecked, recheck_errors) = git_status_of(&files);
+            statuses = rechecked;
+            errors.extend(recheck_errors);
+        }
+    }
+    let mut check = GitCheck::default();
+    for file in statuses {
+        if added.contains(&file.path) {
+            check.ignored.push(file.clone());
+        }
+        if file.is_exposed() {
+            check.exposed.push(file);
+        }
+    }
+    check.errors = errors;
+
+    if policy == GitPolicy::Fail && !check.exposed.is_empty() {
+        let exposed: Vec<String> = check.exposed.iter().map(GitFile::describe).collect();
+        return Err(ValidationError::new(format!(
+            "Refusing to write the token while {} file{} could end up in git (--git-policy fail): {}",
+            exposed.len(), if exposed.len() == 1 { "" } else { "s" }, exposed.join("; ")
+        )).into());
+    }
+    check.files = if policy == GitPolicy::Skip {
+        let exposed: HashSet<&PathBuf> = check.exposed.iter().map(|file| &file.path).collect();
+        files.into_iter().filter(|file| !exposed.contains(file)).collect()
+    } else {
+        files
+    };
+    Ok(check)
+}
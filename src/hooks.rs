@@ -0,0 +1,349 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use globset::GlobMatcher;
+use serde::{Deserialize, Serialize};
+use crate::exit_code::ValidationError;
+use crate::file_scanner::compile_glob;
+
+/// How long a hook may run when the config does not say
+pub const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Captured output beyond this many bytes is cut, keeping the end
+const MAX_CAPTURED_BYTES: usize = 16 * 1024;
+
+/// How often a running hook is checked for completion
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long the output is still read once a hook has ended, since the processes it left behind
+/// may keep the pipes open
+const CAPTURE_GRACE: Duration = Duration::from_millis(500);
+
+/// Commands for the files matching a `path` or `dir` glob, from a `[[hooks.files]]` entry
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileHookConfig {
+    /// Glob matched against the file path below the search root it was found under
+    pub path: Option<String>,
+    /// Glob matched against the directory holding the file, below the search root
+    pub dir: Option<String>,
+    pub pre: Option<String>,
+    pub post: Option<String>,
+}
+
+/// The `hooks` table of a config profile
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookConfig {
+    /// Runs once before any file is written; when it fails, no file is touched
+    pub pre: Option<String>,
+    /// Runs once after the files were written, if any was
+    pub post: Option<String>,
+    /// Limit for each command, e.g. "30s"
+    pub timeout: Option<String>,
+    #[serde(default)]
+    pub files: Vec<FileHookConfig>,
+}
+
+#[derive(Debug, Clone)]
+struct FileHook {
+    path: Option<GlobMatcher>,
+    dir: Option<GlobMatcher>,
+    pre: Option<String>,
+    post: Option<String>,
+}
+
+impl FileHook {
+    /// `file` is relative to its search root
+    fn matches(&self, file: &Path) -> bool {
+        let dir = file.parent().unwrap_or(Path::new(""));
+        self.path.as_ref().is_none_or(|glob| glob.is_match(file))
+            && self.dir.as_ref().is_none_or(|glob| glob.is_match(dir))
+    }
+}
+
+/// When a hook runs relative to the file writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookStage {
+    Pre,
+    Post,
+}
+
+/// How a hook command ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookStatus {
+    Succeeded,
+    /// Exited with a non-zero status, or could not be started
+    Failed,
+    /// Killed after running longer than the timeout
+    TimedOut,
+}
+
+/// The result of running one hook command
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HookOutcome {
+    pub stage: HookStage,
+    /// The command as run, with its variables filled in
+    pub command: String,
+    /// The files that triggered it; empty for the global hooks
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<PathBuf>,
+    pub status: HookStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// Why the command failed, e.g. "exited with status 1"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: f64,
+}
+
+impl HookOutcome {
+    pub fn succeeded(&self) -> bool {
+        self.status == HookStatus::Succeeded
+    }
+
+    /// Describes the outcome for messages, e.g. "`systemctl restart api` exited with status 1"
+    pub fn describe(&self) -> String {
+        format!("`{}` {}", self.command, self.error.as_deref().unwrap_or("succeeded"))
+    }
+}
+
+/// Commands run around an update: global `pre`/`post` commands and per-file ones.
+///
+/// Per-file commands may use `{path}` (the config file) and `{dir}` (its directory); the values
+/// are shell-quoted. A command that expands to the same text for several files runs once, so a
+/// `{dir}` command runs once per directory. Commands run through `sh -c` in the current directory.
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    pre: Option<String>,
+    post: Option<String>,
+    files: Vec<FileHook>,
+    timeout: Option<Duration>,
+    search_roots: Vec<PathBuf>,
+}
+
+impl Hooks {
+    pub fn new(config: &HookConfig) -> Result<Self> {
+        let timeout = config.timeout.as_deref()
+            .map(|timeout| humantime::parse_duration(timeout)
+                .map_err(|e| ValidationError::new(format!("Invalid hook timeout {:?}: {}", timeout, e))))
+            .transpose()?;
+        let mut files = Vec::new();
+        for entry in &config.files {
+            if entry.path.is_none() && entry.dir.is_none() {
+                return Err(ValidationError::new("Each [[hooks.files]] entry needs a path or dir glob").into());
+            }
+            if entry.pre.is_none() && entry.post.is_none() {
+                return Err(ValidationError::new("Each [[hooks.files]] entry needs a pre or post command").into());
+            }
+            files.push(FileHook {
+                path: entry.path.as_deref().map(compile_glob).transpose()?,
+                dir: entry.dir.as_deref().map(compile_glob).transpose()?,
+                pre: entry.pre.clone(),
+                post: entry.post.clone(),
+            });
+        }
+        Ok(Self { pre: config.pre.clone(), post: config.post.clone(), files, timeout, search_roots: Vec::new() })
+    }
+
+    /// Matches `path` and `dir` globs against the path below the root each file was found under,
+    /// as `--include` and `--exclude` do; other files are matched as given, without a leading `./`
+    pub fn with_search_roots(mut self, roots: Vec<PathBuf>) -> Self {
+        self.search_roots = roots;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pre.is_none() && self.post.is_none() && self.files.is_empty()
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout.unwrap_or(DEFAULT_HOOK_TIMEOUT)
+    }
+
+    /// The global command for `stage`
+    pub fn global(&self, stage: HookStage) -> Option<&str> {
+        match stage {
+            HookStage::Pre => self.pre.as_deref(),
+            HookStage::Post => self.post.as_deref(),
+        }
+    }
+
+    /// The per-file commands for `stage` that apply to `file`, with their variables filled in
+    pub fn commands_for(&self, stage: HookStage, file: &Path) -> Vec<String> {
+        let relative = self.search_roots
+            .iter()
+            .find_map(|root| file.strip_prefix(root).ok())
+            .unwrap_or_else(|| file.strip_prefix(".").unwrap_or(file));
+        self.files
+            .iter()
+            .filter(|hook| hook.matches(relative))
+            .filter_map(|hook| match stage {
+                HookStage::Pre => hook.pre.as_deref(),
+                HookStage::Post => hook.post.as_deref(),
+            })
+            .map(|command| expand(command, file))
+            .collect()
+    }
+
+    /// Runs a command through the shell, killing it once the timeout has passed
+    pub fn run(&self, stage: HookStage, command: &str, files: Vec<PathBuf>) -> HookOutcome {
+        let start = Instant::now();
+        let mut outcome = HookOutcome {
+            stage,
+            command: command.to_string(),
+            files,
+            status: HookStatus::Failed,
+            exit_code: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            error: None,
+            duration_ms: 0.0,
+        };
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+        // Its own process group, so a timeout also stops the commands it started
+        #[cfg(unix)]
+        shell.process_group(0);
+        let spawned = shell.spawn();
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(e) => {
+                outcome.error = Some(format!("could not be started: {}", e));
+                return outcome;
+            }
+        };
+        // Both pipes are drained while waiting, so a chatty command cannot block on a full pipe
+        let stdout = child.stdout.take().map(capture);
+        let stderr = child.stderr.take().map(capture);
+        let deadline = start + self.timeout();
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break Ok(status),
+                Ok(None) if Instant::now() >= deadline => {
+                    // Elsewhere only the shell itself can be killed
+                    #[cfg(unix)]
+                    // SAFETY: killpg only sends a signal; the group is the one the shell leads
+                    unsafe {
+                        libc::killpg(child.id() as libc::pid_t, libc::SIGKILL);
+                    }
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    break Err(None);
+                }
+                Ok(None) => thread::sleep(POLL_INTERVAL),
+                Err(e) => break Err(Some(e)),
+            }
+        };
+        let output_deadline = Instant::now() + CAPTURE_GRACE;
+        let collect = |output: Option<mpsc::Receiver<String>>| output
+            .and_then(|output| output.recv_timeout(output_deadline.saturating_duration_since(Instant::now())).ok())
+            .unwrap_or_default();
+        outcome.stdout = collect(stdout);
+        outcome.stderr = collect(stderr);
+        match status {
+            Ok(status) => {
+                outcome.exit_code = status.code();
+                if status.success() {
+                    outcome.status = HookStatus::Succeeded;
+                } else {
+                    outcome.error = Some(match status.code() {
+                        Some(code) => format!("exited with status {}", code),
+                        None => "was killed by a signal".to_string(),
+                    });
+                }
+            }
+            Err(None) => {
+                outcome.status = HookStatus::TimedOut;
+                outcome.error = Some(format!("timed out after {}", humantime::format_duration(self.timeout())));
+            }
+            Err(Some(e)) => outcome.error = Some(format!("could not be waited for: {}", e)),
+        }
+        outcome.duration_ms = start.elapsed().as_secs_f64() * 1000.0;
+        outcome
+    }
+}
+
+/// Reads a pipe to the end on its own thread, keeping the last [`MAX_CAPTURED_BYTES`]
+fn capture<R: Read + Send + 'static>(mut pipe: R) -> mpsc::Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = pipe.read_to_end(&mut bytes);
+        let start = bytes.len().saturating_sub(MAX_CAPTURED_BYTES);
+        let _ = sender.send(String::from_utf8_lossy(&bytes[start..]).into_owned());
+    });
+    receiver
+}
+
+/// Fills in `{path}` and `{dir}`, quoted for the shell
+fn expand(command: &str, file: &Path) -> String {
+    let dir = file.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    command
+        .replace("{path}", &shell_quote(&file.display().to_string()))
+        .replace("{dir}", &shell_quote(&dir.display().to_string()))
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Per-file `pre` commands for one update: each distinct command runs once, and files that
+/// share it wait for that run
+pub(crate) struct PreHooks<'a> {
+    hooks: &'a Hooks,
+    runs: Mutex<BTreeMap<String, PreRun>>,
+}
+
+/// A `pre` command shared by some files; the first file to need it runs it
+#[derive(Default)]
+struct PreRun {
+    outcome: Arc<OnceLock<HookOutcome>>,
+    files: Vec<PathBuf>,
+}
+
+impl<'a> PreHooks<'a> {
+    pub(crate) fn new(hooks: &'a Hooks) -> Self {
+        Self { hooks, runs: Mutex::new(BTreeMap::new()) }
+    }
+
+    /// Runs the `pre` commands for `file`, failing with the first one that did not succeed
+    pub(crate) fn run_for(&self, file: &Path) -> Result<()> {
+        for command in self.hooks.commands_for(HookStage::Pre, file) {
+            let run = {
+                let mut runs = self.runs.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                let run = runs.entry(command.clone()).or_default();
+                run.files.push(file.to_path_buf());
+                Arc::clone(&run.outcome)
+            };
+            let outcome = run.get_or_init(|| self.hooks.run(HookStage::Pre, &command, Vec::new()));
+            if !outcome.succeeded() {
+                return Err(anyhow!("Pre-update hook {}", outcome.describe()));
+            }
+        }
+        Ok(())
+    }
+
+    /// The outcomes of the commands that ran, each with the files that needed it
+    pub(crate) fn into_outcomes(self) -> Vec<HookOutcome> {
+        let runs = self.runs.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner());
+        runs.into_values()
+            .filter_map(|run| {
+                let mut outcome = Arc::try_unwrap(run.outcome).ok()?.into_inner()?;
+                outcome.files = run.files;
+                Some(outcome)
+            })
+            .collect()
+    }
+}
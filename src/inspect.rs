@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use rayon::prelude::*;
+use serde::Serialize;
+use crate::config_updater::TokenFields;
+use crate::exit_code::ExitCode;
+use crate::secret::SecretToken;
+use crate::vault::{TokenInfo, VaultClient, VaultError};
+
+/// Vault's verdict on a token found during inspection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenHealth {
+    Valid,
+    /// Valid, but expires within the configured warning window
+    Expiring,
+    /// Rejected by Vault: expired, revoked or never issued
+    Invalid,
+    /// Vault could not be asked (network or server error)
+    LookupFailed,
+}
+
+/// One distinct token and the files that hold it
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenGroup {
+    pub fingerprint: String,
+    pub masked: String,
+    /// Number of token fields holding this token
+    pub occurrences: usize,
+    pub files: Vec<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health: Option<TokenHealth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lookup: Option<TokenInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip)]
+    token: SecretToken,
+}
+
+/// Results of an inspection, with tokens grouped by fingerprint
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InspectStats {
+    pub files_scanned: usize,
+    pub files_with_tokens: usize,
+    pub distinct_tokens: usize,
+    pub errors: Vec<String>,
+    pub tokens: Vec<TokenGroup>,
+}
+
+impl InspectStats {
+    /// Tokens that Vault flagged as expiring, invalid or unverifiable
+    pub fn flagged(&self) -> impl Iterator<Item = &TokenGroup> {
+        self.tokens.iter().filter(|group| matches!(group.health, Some(health) if health != TokenHealth::Valid))
+    }
+
+    /// Read errors take precedence; otherwise flagged tokens fail the run with `ValidationFailed`
+    pub fn exit_code(&self) -> ExitCode {
+        let code = ExitCode::from_counts(self.files_with_tokens, self.errors.len(), self.files_scanned);
+        if code == ExitCode::Success && self.flagged().next().is_some() {
+            ExitCode::ValidationFailed
+        } else {
+            code
+        }
+    }
+}
+
+/// Reads every file in parallel and groups the token values by fingerprint
+pub fn inspect_files(files: &[PathBuf], fields: &TokenFields) -> InspectStats {
+    let results: Vec<_> = files
+        .par_iter()
+        .map(|file| (file, fields.collect_file(file)))
+        .collect();
+
+    let mut stats = InspectStats::default();
+    let mut groups: BTreeMap<String, TokenGroup> = BTreeMap::new();
+    for (file, result) in results {
+        stats.files_scanned += 1;
+        let tokens = match result {
+            // An empty field holds no token, so there is nothing to group or look up
+            Ok(tokens) => tokens.into_iter().filter(|token| !token.is_empty()).collect::<Vec<_>>(),
+            Err(e) => {
+                stats.errors.push(format!("Failed to read {}: {}", file.display(), e));
+                continue;
+            }
+        };
+        if !tokens.is_empty() {
+            stats.files_with_tokens += 1;
+        }
+        for token in tokens {
+            let group = groups.entry(token.fingerprint()).or_insert_with(|| TokenGroup {
+                fingerprint: token.fingerprint(),
+                masked: token.masked(),
+                occurrences: 0,
+                files: Vec::new(),
+                health: None,
+                lookup: None,
+                error: None,
+                token: token.clone(),
+            });
+            group.occurrences += 1;
+            if group.files.last() != Some(file) {
+                group.files.push(file.clone());
+            }
+        }
+    }
+
+    stats.tokens = groups.into_values().collect();
+    stats.distinct_tokens = stats.tokens.len();
+    stats
+}
+
+/// Looks up each distinct token in Vault and records its health.
+///
+/// Tokens with less than `expiring_within` left are flagged as expiring.
+pub fn lookup_token_groups(stats: &mut InspectStats, client: &VaultClient, expiring_within: Duration) {
+    for group in &mut stats.tokens {
+        match client.lookup_self(&group.token) {
+            Ok(info) => {
+                let expiring = !info.never_expires() && Duration::from_secs(info.ttl) < expiring_within;
+                group.health = Some(if expiring { TokenHealth::Expiring } else { TokenHealth::Valid });
+                group.lookup = Some(info);
+            }
+            Err(e) => match e.downcast_ref::<VaultError>() {
+                Some(vault_error) if vault_error.is_permission_denied() => {
+                    group.health = Some(TokenHealth::Invalid);
+                }
+                _ => {
+                    group.health = Some(TokenHealth::LookupFailed);
+                    group.error = Some(format!("{:#}", e));
+                }
+            },
+        }
+    }
+}
@@ -1,7 +1,43 @@
 pub mod file_scanner;
 pub mod config_updater;
 pub mod cli;
+pub mod exit_code;
+pub mod report;
+pub mod output;
+pub mod secret;
+pub mod token_source;
+pub mod vault_cli;
+pub mod vault;
+pub mod auth;
+pub mod revoke;
+pub mod inspect;
+pub mod project_config;
+pub mod mapping;
+pub mod watch;
+pub mod sink;
+pub mod serve;
+pub mod webhook;
+pub mod hooks;
+pub mod audit;
 
 pub use file_scanner::*;
 pub use config_updater::*;
-pub use cli::*;
\ No newline at end of file
+pub use cli::*;
+pub use exit_code::*;
+pub use report::*;
+pub use output::*;
+pub use secret::*;
+pub use token_source::*;
+pub use vault_cli::*;
+pub use vault::*;
+pub use auth::*;
+pub use revoke::*;
+pub use inspect::*;
+pub use project_config::*;
+pub use mapping::*;
+pub use watch::*;
+pub use sink::*;
+pub use serve::*;
+pub use webhook::*;
+pub use hooks::*;
+pub use audit::*;
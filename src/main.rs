@@ -1,214 +1,488 @@
+use std::path::{Path, PathBuf};
 use std::process;
-use std::time::Instant;
-use anyhow::Result;
-use rayon::prelude::*;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Instant, SystemTime};
+use anyhow::{anyhow, Result};
+use signal_hook::consts::{SIGINT, SIGTERM};
 use vault_config_updater::{
-    parse_env_args, find_config_files, update_vault_token_in_file, UpdateStats,
-    scan_vault_tokens_in_file, ScanStats
+    parse_env_args, find_config_files_in_roots, check_git, update_files_parallel, scan_files_parallel,
+    collect_distinct_vault_tokens, inspect_files, lookup_token_groups, revoke_old_tokens, validate_token,
+    verify_files_parallel, watch_changes, latest_sink, read_sink, serve_unix, read_history,
+    AuditLog, CliArgs, Command, ExitCode, Hooks, FileStatus, InspectArgs, Icon, Output, RevocationStatus, RootSummary, RunMode, RunReport,
+    SearchRoot, SecretToken, SelectionArgs, TokenFields, TokenMapping, UpdateArgs, VaultClient, VerifyArgs,
+    ServeArgs, ServeSelection, Server, WatchArgs, WatchTargets, WebhookListener, HistoryArgs, ValidationError,
+    GitArgs, GitCheck, GitStatus
 };
 
-fn main() {
-    if let Err(e) = run() {
-        eprintln!("Error: {}", e);
-        process::exit(1);
+fn main() -> process::ExitCode {
+    let mut cli = match parse_env_args() {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::from_error(&e).into();
+        }
+    };
+    let out = Output::new(cli.output_options());
+
+    match run(&mut cli, &out) {
+        Ok(code) => code.into(),
+        Err(e) => {
+            out.error(&e.to_string());
+            ExitCode::from_error(&e).into()
+        }
     }
 }
 
-fn run() -> Result<()> {
-    let start_time = Instant::now();
-    let mut cli = parse_env_args()?;
-
-    if cli.verbose {
-        if cli.dry_run {
-            println!("🔍 Vault Config Updater v0.1.0 - DRY RUN MODE");
-        } else {
-            println!("🔍 Vault Config Updater v0.1.0");
+fn run(cli: &mut CliArgs, out: &Output) -> Result<ExitCode> {
+    let runs_hooks = matches!(cli.command, None | Some(Command::Update(_) | Command::Watch(_) | Command::Serve(_)));
+    if let Some((path, ignored)) = &cli.untrusted_settings {
+        let ignored: Vec<&str> = ignored.iter()
+            .copied()
+            .filter(|&name| name != "hooks" || (runs_hooks && !cli.no_hooks))
+            .collect();
+        if !ignored.is_empty() {
+            out.warn(&format!(
+                "Ignoring {} from {}; name it with --config to use them", ignored.join(", "), path.display()
+            ));
         }
-        println!("📁 Searching in: {:?}", cli.get_search_path());
     }
+    let hooks = cli.hooks()?;
+    // Opened up front, so an unwritable log fails the run before any file is touched
+    let audit = cli.open_audit_log()?;
+    match &mut cli.command {
+        Some(Command::Update(args)) => update(args, &hooks, audit.as_ref(), out),
+        Some(Command::Scan(args)) => scan(&args.selection, out),
+        Some(Command::Verify(args)) => verify(args, out),
+        Some(Command::Inspect(args)) => inspect(args, out),
+        Some(Command::Watch(args)) => watch(args, &hooks, audit.as_ref(), out),
+        Some(Command::Serve(args)) => serve(args, hooks, audit, out),
+        Some(Command::History(args)) => history(args, cli.audit_log.as_deref(), out),
+        None => update(&mut cli.update, &hooks, audit.as_ref(), out),
+    }
+}
 
-    let search_path = cli.get_search_path();
-    let config_files = find_config_files(search_path)?;
+/// The config files to work on, and the roots they were found in
+struct Discovery {
+    roots: Vec<SearchRoot>,
+    files: Vec<PathBuf>,
+}
 
-    if config_files.is_empty() {
-        println!("⚠️  No config.json or globalConfig.json files found in {:?}", search_path);
-        return Ok(());
+impl Discovery {
+    /// The root named in reports; the first one when there are several
+    fn report_path(&self) -> &Path {
+        self.roots.first().map_or(Path::new("."), |root| root.path.as_path())
     }
+}
 
-    if cli.verbose {
-        println!("📋 Found {} config files:", config_files.len());
-        for file in &config_files {
-            println!("   • {}", file.display());
-        }
+/// Finds the config files under every root and in the --files-from list; `None` when there is nothing to do
+fn discover(selection: &SelectionArgs, out: &Output) -> Result<Option<Discovery>> {
+    let search_paths = selection.search_paths();
+    for search_path in &search_paths {
+        out.detail(Icon::Folder, &format!("Searching in: {:?}", search_path));
     }
+    if let Some(list) = &selection.files_from {
+        out.detail(Icon::Folder, &format!("Reading file list: {:?}", list));
+    }
+    let roots = find_config_files_in_roots(&search_paths, selection.files_from.as_deref(), &selection.filter()?)?;
+    let files: Vec<PathBuf> = roots.iter().flat_map(|root| root.files.iter().cloned()).collect();
+
+    if files.is_empty() && !out.format().is_machine_readable() {
+        let searched: Vec<&Path> = roots.iter().map(|root| root.path.as_path()).collect();
+        out.warn(&format!("No config.json or globalConfig.json files found in {:?}", searched));
+        return Ok(None);
+    }
+
+    out.detail(Icon::List, &format!("Found {} config files:", files.len()));
+    for file in &files {
+        out.detail_item(&file.display().to_string());
+    }
+    Ok(Some(Discovery { roots, files }))
+}
 
-    if cli.dry_run {
-        if cli.verbose {
-            println!("🔍 DRY RUN MODE - No files will be modified");
+/// Checks the files about to be written against the git policy, reporting what the check changed or could not tell
+fn check_git_exposure(files: &[PathBuf], git: &GitArgs, out: &Output) -> Result<GitCheck> {
+    let check = check_git(files.to_vec(), git.policy(), git.gitignore)?;
+    for file in &check.ignored {
+        let gitignore = file.repo.join(".gitignore");
+        if file.is_exposed() {
+            out.warn(&format!("Added {} to {}, but another rule still re-includes it", file.path.display(), gitignore.display()));
+        } else {
+            out.info(Icon::Lock, &format!("Added {} to {}", file.path.display(), gitignore.display()));
         }
-        let stats = scan_files_parallel(&config_files, cli.verbose)?;
-        print_scan_results(&stats, start_time.elapsed());
-        if !stats.errors.is_empty() {
-            process::exit(1);
+    }
+    for file in check.exposed.iter().filter(|file| git.gitignore && file.status == GitStatus::Tracked) {
+        out.warn(&format!("{} stays tracked by git; remove it from the index with `git rm --cached`", file.path.display()));
+    }
+    for error in &check.errors {
+        out.warn(error);
+    }
+    Ok(check)
+}
+
+fn scan(selection: &SelectionArgs, out: &Output) -> Result<ExitCode> {
+    let start_time = Instant::now();
+    out.detail(Icon::Search, "Vault Config Updater v0.1.0 - DRY RUN MODE");
+    let fields = selection.fields()?;
+    let Some(discovery) = discover(selection, out)? else {
+        return Ok(ExitCode::NothingToDo);
+    };
+
+    out.detail(Icon::Search, "DRY RUN MODE - No files will be modified");
+    out.detail(Icon::Search, &format!("Scanning {} files in parallel...", discovery.files.len()));
+    let stats = scan_files_parallel(&discovery.files, &fields, |outcome| out.file_event(outcome));
+    let exit_code = stats.exit_code();
+    let report = RunReport::new(RunMode::DryRun, discovery.report_path(), &stats, start_time.elapsed(), exit_code.code())
+        .with_roots(RootSummary::from_outcomes(&discovery.roots, &stats.files));
+    out.scan_summary(&report)?;
+    Ok(exit_code)
+}
+
+fn update(args: &mut UpdateArgs, hooks: &Hooks, audit: Option<&AuditLog>, out: &Output) -> Result<ExitCode> {
+    if args.dry_run {
+        return scan(&args.selection, out);
+    }
+    if let Some(mapping) = &args.mapping {
+        return update_mapped(args, mapping, hooks, audit, out);
+    }
+
+    let start_time = Instant::now();
+    out.detail(Icon::Search, "Vault Config Updater v0.1.0");
+    let fields = args.selection.fields()?;
+    let Some(discovery) = discover(&args.selection, out)? else {
+        return Ok(ExitCode::NothingToDo);
+    };
+    // Refuse before a token is issued or any file is touched
+    let git = check_git_exposure(&discovery.files, &args.git, out)?;
+
+    if let Some(method) = args.login.login {
+        out.detail(Icon::Key, &format!("Logging in to Vault using {}", method));
+    } else if args.login.unwrap {
+        out.detail(Icon::Key, "Unwrapping the response-wrapped token via sys/wrapping/unwrap");
+    }
+    let token = args.get_token()?;
+    out.detail(Icon::Key, &format!("Token obtained: {} (length: {} chars)", token, token.len()));
+    // Fail on a missing Vault address before any file is touched
+    let client = if args.validation.wants_validation() || args.revoke_old {
+        Some(VaultClient::new(args.vault.config()?)?)
+    } else {
+        None
+    };
+    let token_info = if let Some(client) = client.as_ref().filter(|_| args.validation.wants_validation()) {
+        let info = validate_token(client, &token, args.validation.min_ttl.unwrap_or_default())?;
+        out.info(Icon::Key, &format!("Token {} is valid: {}", token.fingerprint(), info.summary()));
+        Some(info)
+    } else {
+        None
+    };
+    let old_tokens = if args.revoke_old {
+        // Files left alone by the git policy keep their tokens, which must stay valid
+        let skipped: Vec<PathBuf> = discovery.files.iter().filter(|file| !git.files.contains(file)).cloned().collect();
+        let kept = collect_distinct_vault_tokens(&skipped, &fields);
+        if !kept.is_empty() {
+            out.warn(&format!("Not revoking {} old token(s) still held by files skipped under --git-policy", kept.len()));
         }
+        collect_distinct_vault_tokens(&discovery.files, &fields).into_iter().filter(|token| !kept.contains(token)).collect()
     } else {
-        let token = cli.get_token()?;
-        if cli.verbose {
-            println!("🎯 Token obtained (length: {} chars)", token.len());
+        Vec::new()
+    };
+    // Revoking a token also revokes its children, which would take the new token down with it
+    if let Some(parent) = args.parent_token().filter(|parent| old_tokens.contains(parent)) {
+        return Err(ValidationError::new(format!(
+            "--revoke-old would also revoke the new token, which was created as a child of the old token [{}]; \
+             create it from another token or with a role that issues orphan tokens",
+            parent.fingerprint()
+        )).into());
+    }
+    out.detail(Icon::Working, &format!("Processing {} files in parallel...", git.files.len()));
+    let mut stats = update_files_parallel(&git.files, token.expose(), &fields, hooks, |outcome| out.file_event(outcome));
+    stats.git_exposed = git.exposed;
+    if let Some(audit) = audit {
+        audit.record_stats(&mut stats);
+    }
+    let mut exit_code = stats.exit_code();
+    let revocations = match &client {
+        Some(client) if args.revoke_old && stats.errors.is_empty() => {
+            out.detail(Icon::Lock, &format!("Revoking {} old token(s)...", old_tokens.len()));
+            revoke_old_tokens(client, &old_tokens, &token)
         }
-        let stats = update_files_parallel(&config_files, &token, cli.verbose)?;
-        print_results(&stats, start_time.elapsed());
-        if !stats.errors.is_empty() {
-            process::exit(1);
+        _ => {
+            if args.revoke_old {
+                out.warn("Old tokens were not revoked because some files failed to update");
+            }
+            Vec::new()
         }
+    };
+    if exit_code == ExitCode::Success
+        && revocations.iter().any(|r| r.status == RevocationStatus::Failed)
+    {
+        exit_code = ExitCode::PartialFailure;
     }
-
-    Ok(())
+    let report = RunReport::new(RunMode::Update, discovery.report_path(), &stats, start_time.elapsed(), exit_code.code())
+        .with_roots(RootSummary::from_outcomes(&discovery.roots, &stats.files))
+        .with_token(&token)
+        .with_token_info(token_info)
+        .with_revocations(revocations);
+    out.update_summary(&report)?;
+    Ok(exit_code)
 }
 
-fn update_files_parallel(
-    files: &[std::path::PathBuf],
-    token: &str,
-    verbose: bool
-) -> Result<UpdateStats> {
-    if verbose {
-        println!("⚡ Processing {} files in parallel...", files.len());
+/// Writes the token chosen by the mapping rules into each file
+fn update_mapped(args: &UpdateArgs, mapping_path: &Path, hooks: &Hooks, audit: Option<&AuditLog>, out: &Output) -> Result<ExitCode> {
+    let start_time = Instant::now();
+    out.detail(Icon::Search, "Vault Config Updater v0.1.0");
+    let fields = args.selection.fields()?;
+    let mut mapping = TokenMapping::load(mapping_path)?;
+    let Some(discovery) = discover(&args.selection, out)? else {
+        return Ok(ExitCode::NothingToDo);
+    };
+    let git = check_git_exposure(&discovery.files, &args.git, out)?;
+
+    mapping.set_search_roots(&discovery.roots);
+    out.detail(Icon::Key, &format!("Reading the tokens referenced by {:?}", mapping_path));
+    mapping.resolve(args.vault.config().ok().as_ref())?;
+    let tokens = mapping.tokens();
+    for token in &tokens {
+        out.detail(Icon::Key, &format!("Token from {}: {} [{}]", token.source, token.masked, token.fingerprint));
+    }
+    out.detail(Icon::Working, &format!("Processing {} files in parallel...", git.files.len()));
+    let mut stats = update_files_parallel(&git.files, &mapping, &fields, hooks, |outcome| out.file_event(outcome));
+    stats.git_exposed = git.exposed;
+    if let Some(audit) = audit {
+        audit.record_stats(&mut stats);
     }
+    let exit_code = stats.exit_code();
+    let report = RunReport::new(RunMode::Update, discovery.report_path(), &stats, start_time.elapsed(), exit_code.code())
+        .with_roots(RootSummary::from_outcomes(&discovery.roots, &stats.files))
+        .with_mapped_tokens(tokens);
+    out.update_summary(&report)?;
+    Ok(exit_code)
+}
 
-    let results: Vec<_> = files
-        .par_iter()
-        .map(|file| {
-            let result = update_vault_token_in_file(file, token);
-            (file.clone(), result)
-        })
-        .collect();
+fn inspect(args: &InspectArgs, out: &Output) -> Result<ExitCode> {
+    let start_time = Instant::now();
+    let fields = args.selection.fields()?;
 
-    let mut stats = UpdateStats::new();
-    stats.files_processed = files.len();
-
-    for (file, result) in results {
-        match result {
-            Ok(_) => {
-                // Check if file was actually updated by reading it
-                if let Ok(content) = std::fs::read_to_string(&file) {
-                    let token_count = content.matches(&format!(r#""vaultToken": "{}""#, token)).count();
-                    if token_count > 0 {
-                        stats.files_updated += 1;
-                        stats.tokens_replaced += token_count;
-                        if verbose {
-                            println!("   ✅ Updated {} (replaced {} tokens)", file.display(), token_count);
-                        }
-                    } else if verbose {
-                        println!("   ⏭️  Skipped {} (no vaultToken fields)", file.display());
-                    }
-                } else if verbose {
-                    println!("   ⚠️  Could not verify updates in {}", file.display());
-                }
-            }
-            Err(e) => {
-                let error_msg = format!("Failed to process {}: {}", file.display(), e);
-                stats.add_error(error_msg.clone());
-                if verbose {
-                    println!("   ❌ {}", error_msg);
-                }
-            }
-        }
+    // Connect first so a missing Vault address fails before the tree is walked
+    let client = if args.lookup { Some(VaultClient::new(args.vault.config()?)?) } else { None };
+    let Some(discovery) = discover(&args.selection, out)? else {
+        return Ok(ExitCode::NothingToDo);
+    };
+
+    let mut stats = inspect_files(&discovery.files, &fields);
+    if let Some(client) = &client {
+        out.detail(Icon::Key, &format!("Looking up {} token(s) in Vault...", stats.distinct_tokens));
+        lookup_token_groups(&mut stats, client, args.expiring_within);
     }
+    let exit_code = stats.exit_code();
+    let report = RunReport::new(RunMode::Inspect, discovery.report_path(), &stats, start_time.elapsed(), exit_code.code())
+        .with_roots(RootSummary::from_outcomes(&discovery.roots, &[]));
+    out.inspect_summary(&report)?;
+    Ok(exit_code)
+}
 
-    Ok(stats)
+fn verify(args: &VerifyArgs, out: &Output) -> Result<ExitCode> {
+    let start_time = Instant::now();
+    let fields = args.selection.fields()?;
+    let expected = args.expected_fingerprint()?;
+    out.detail(Icon::Key, &format!("Expected token: [{}]", expected));
+    let Some(discovery) = discover(&args.selection, out)? else {
+        return Ok(ExitCode::NothingToDo);
+    };
+
+    let stats = verify_files_parallel(&discovery.files, &expected, &fields, |outcome| out.file_event(outcome));
+    let exit_code = stats.exit_code();
+    let report = RunReport::new(RunMode::Verify, discovery.report_path(), &stats, start_time.elapsed(), exit_code.code())
+        .with_roots(RootSummary::from_outcomes(&discovery.roots, &stats.files));
+    out.verify_summary(&report)?;
+    Ok(exit_code)
 }
 
-fn scan_files_parallel(
-    files: &[std::path::PathBuf],
-    verbose: bool
-) -> Result<ScanStats> {
-    if verbose {
-        println!("🔍 Scanning {} files in parallel...", files.len());
+fn watch(args: &WatchArgs, hooks: &Hooks, audit: Option<&AuditLog>, out: &Output) -> Result<ExitCode> {
+    let fields = args.selection.fields()?;
+    // Only wrapped sinks need Vault, so a missing address is reported when one is met
+    let client = if args.sink.is_empty() { None } else { args.vault.config().ok().map(VaultClient::new).transpose()? };
+    let targets = WatchTargets {
+        roots: args.search_paths(),
+        filter: args.selection.filter()?,
+        token_files: args.token_files(),
+        min_token_interval: args.min_interval,
+    };
+    let mut token = watch_token(args, &args.sink, client.as_ref())?;
+
+    // Bring every file up to date before waiting for changes
+    if let Some(discovery) = discover(&args.selection, out)? {
+        sync_files(&discovery.files, &token, &fields, &args.git, hooks, audit, out);
     }
 
-    let results: Vec<_> = files
-        .par_iter()
-        .map(|file| {
-            let result = scan_vault_tokens_in_file(file);
-            (file.clone(), result)
-        })
-        .collect();
+    let shutdown = Arc::new(AtomicBool::new(false));
+    for signal in [SIGTERM, SIGINT] {
+        signal_hook::flag::register(signal, Arc::clone(&shutdown))?;
+    }
+    out.info(Icon::Search, &format!("Watching {:?} with token [{}] (stop with Ctrl-C or SIGTERM)",
+        targets.roots, token.fingerprint()));
+    for token_file in &targets.token_files {
+        out.info(Icon::Key, &format!("Re-reading the token when {} changes", token_file.display()));
+    }
 
-    let mut stats = ScanStats::new();
-    stats.files_scanned = files.len();
-
-    for (file, result) in results {
-        match result {
-            Ok(token_count) => {
-                if token_count > 0 {
-                    stats.files_with_tokens += 1;
-                    stats.total_tokens_found += token_count;
-                    if verbose {
-                        println!("   🎯 {} ({} vaultToken field{})",
-                            file.display(),
-                            token_count,
-                            if token_count == 1 { "" } else { "s" });
+    watch_changes(&targets, args.debounce, &shutdown, |batch| {
+        let mut files: Vec<PathBuf> = batch.configs.into_iter().collect();
+        if !batch.token_files.is_empty() {
+            let changed: Vec<PathBuf> = batch.token_files.into_iter().collect();
+            match watch_token(args, &changed, client.as_ref()) {
+                Ok(new_token) if new_token != token => {
+                    out.info(Icon::Key, &timestamped(&format!(
+                        "Token changed from [{}] to [{}]", token.fingerprint(), new_token.fingerprint()
+                    )));
+                    token = new_token;
+                    match find_config_files_in_roots(&targets.roots, None, &targets.filter) {
+                        Ok(roots) => files = roots.into_iter().flat_map(|root| root.files).collect(),
+                        Err(e) => out.warn(&format!("Cannot list the config files: {}", e)),
                     }
-                } else if verbose {
-                    println!("   ⏭️  {} (no vaultToken fields)", file.display());
-                }
-            }
-            Err(e) => {
-                let error_msg = format!("Failed to scan {}: {}", file.display(), e);
-                stats.add_error(error_msg.clone());
-                if verbose {
-                    println!("   ❌ {}", error_msg);
                 }
+                Ok(_) => {}
+                Err(e) => out.warn(&timestamped(&format!("Keeping the current token; the new one cannot be read: {}", e))),
             }
         }
-    }
+        sync_files(&files, &token, &fields, &args.git, hooks, audit, out);
+        Ok(())
+    })?;
 
-    Ok(stats)
+    out.info(Icon::Done, "Stopped watching");
+    Ok(ExitCode::Success)
 }
 
-fn print_results(stats: &UpdateStats, duration: std::time::Duration) {
-    println!("\n🎉 Update completed in {:.2}s", duration.as_secs_f64());
-    println!("📊 Results:");
-    println!("   • Files processed: {}", stats.files_processed);
-    println!("   • Files updated: {}", stats.files_updated);
-    println!("   • Tokens replaced: {}", stats.tokens_replaced);
+/// The token for `watch`: from the most recently written of `sinks` when sinks are used, else from the token source
+fn watch_token(args: &WatchArgs, sinks: &[PathBuf], client: Option<&VaultClient>) -> Result<SecretToken> {
+    if args.sink.is_empty() {
+        return args.token_args.source(None).read();
+    }
+    let sink = latest_sink(sinks).ok_or_else(|| anyhow!("None of the sinks {:?} exist yet", sinks))?;
+    read_sink(sink)?.resolve(client)
+}
+
+/// Prefixes a log line with the current UTC time
+fn timestamped(message: &str) -> String {
+    format!("{} {}", humantime::format_rfc3339_seconds(SystemTime::now()), message)
+}
 
-    if !stats.errors.is_empty() {
-        println!("   • Errors: {}", stats.errors.len());
-        println!("\n❌ Errors encountered:");
-        for error in &stats.errors {
-            println!("   • {}", error);
+/// Writes `token` into the files that do not already hold it everywhere and pass the git policy
+fn sync_files(
+    files: &[PathBuf], token: &SecretToken, fields: &TokenFields, git: &GitArgs, hooks: &Hooks, audit: Option<&AuditLog>, out: &Output,
+) {
+    let stale: Vec<PathBuf> = files
+        .iter()
+        .filter(|file| fields.collect_file(file).is_ok_and(|values| values.iter().any(|value| value != token)))
+        .cloned()
+        .collect();
+    if stale.is_empty() {
+        return;
+    }
+    let check = match check_git_exposure(&stale, git, out) {
+        Ok(check) => check,
+        Err(e) => {
+            out.warn(&timestamped(&e.to_string()));
+            return;
         }
+    };
+    for file in &check.exposed {
+        let action = if check.files.contains(&file.path) { "Writing" } else { "Skipping" };
+        out.warn(&timestamped(&format!("{} {}", action, file.describe())));
     }
-
-    if stats.files_updated > 0 {
-        println!("\n✨ Successfully updated vault tokens in {} files!", stats.files_updated);
-    } else if stats.errors.is_empty() {
-        println!("\nℹ️  No files needed updating (no vaultToken fields found).");
+    let stale = check.files;
+    if stale.is_empty() {
+        return;
+    }
+    // Text output names each updated file below; machine-readable output gets the usual events
+    let mut stats = update_files_parallel(&stale, token.expose(), fields, hooks, |outcome| {
+        if out.format().is_machine_readable() {
+            out.file_event(outcome);
+        }
+    });
+    if let Some(audit) = audit {
+        audit.record_stats(&mut stats);
+    }
+    for outcome in &stats.files {
+        if outcome.status == FileStatus::Updated {
+            out.info(Icon::Success, &timestamped(&format!(
+                "Updated {} with token [{}]", outcome.path.display(), token.fingerprint()
+            )));
+        }
+    }
+    for hook in stats.hooks.iter().filter(|hook| hook.succeeded()) {
+        out.info(Icon::Success, &timestamped(&format!("Ran hook {}", hook.describe())));
+    }
+    for error in &stats.errors {
+        out.warn(error);
     }
 }
 
-fn print_scan_results(stats: &ScanStats, duration: std::time::Duration) {
-    println!("\n🔍 DRY RUN scan completed in {:.2}s", duration.as_secs_f64());
-    println!("📊 Results:");
-    println!("   • Files scanned: {}", stats.files_scanned);
-    println!("   • Files that would be updated: {}", stats.files_with_tokens);
-    println!("   • Total tokens that would be replaced: {}", stats.total_tokens_found);
+fn serve(args: &ServeArgs, hooks: Hooks, audit: Option<AuditLog>, out: &Output) -> Result<ExitCode> {
+    let mut server = Server::new(ServeSelection {
+        paths: args.search_paths(),
+        files_from: args.selection.files_from.clone(),
+        filter: args.selection.filter()?,
+        fields: args.selection.fields()?,
+        git_policy: args.git.policy(),
+        gitignore: args.git.gitignore,
+    })?.with_hooks(hooks);
+    // Only wrapped tokens need Vault, so a missing address is reported when one arrives
+    if let Ok(config) = args.vault.config() {
+        server = server.with_vault(VaultClient::new(config)?);
+    }
+    if let Some(audit) = audit {
+        server = server.with_audit(audit);
+    }
+    let socket = args.socket_path();
+    let webhook = match (args.http, args.webhook_secret()) {
+        (Some(address), Some(secret)) => Some((WebhookListener::bind(address)?, secret.read()?)),
+        _ => None,
+    };
 
-    if !stats.errors.is_empty() {
-        println!("   • Errors: {}", stats.errors.len());
-        println!("\n❌ Errors encountered:");
-        for error in &stats.errors {
-            println!("   • {}", error);
+    let shutdown = Arc::new(AtomicBool::new(false));
+    for signal in [SIGTERM, SIGINT] {
+        signal_hook::flag::register(signal, Arc::clone(&shutdown))?;
+    }
+    out.info(Icon::Rocket, &format!("Serving {} config files on {} (stop with Ctrl-C or SIGTERM)",
+        server.file_count(), socket.display()));
+    if let Some((listener, _)) = &webhook {
+        let address = listener.local_addr().ok_or_else(|| anyhow!("The webhook listener has no IP address"))?;
+        out.info(Icon::Rocket, &format!("Accepting signed webhooks on http://{}/rotate", address));
+        if !address.ip().is_loopback() {
+            out.warn(&format!("{} is not a loopback address; tokens in webhook bodies travel unencrypted", address));
         }
     }
 
-    if stats.files_with_tokens > 0 {
-        println!("\n💡 {} file{} would be updated with new vault tokens!",
-            stats.files_with_tokens,
-            if stats.files_with_tokens == 1 { "" } else { "s" });
-        println!("🚀 Run without --dry-run to apply these changes.");
-    } else if stats.errors.is_empty() {
-        println!("\nℹ️  No files would need updating (no vaultToken fields found).");
-    }
-}
\ No newline at end of file
+    let log = |message: &str| out.info(Icon::Info, &timestamped(message));
+    thread::scope(|scope| {
+        let webhook = webhook.as_ref().map(|(listener, secret)| {
+            scope.spawn(|| {
+                let result = listener.serve(&server, secret, &shutdown, log);
+                // Either listener failing stops the other
+                shutdown.store(true, Ordering::SeqCst);
+                result
+            })
+        });
+        let result = serve_unix(&server, &socket, &shutdown, log);
+        shutdown.store(true, Ordering::SeqCst);
+        let webhook_result = webhook.map_or(Ok(()), |handle| {
+            handle.join().unwrap_or_else(|_| Err(anyhow!("The webhook listener panicked")))
+        });
+        result.and(webhook_result)
+    })?;
+    out.info(Icon::Done, "Stopped serving");
+    Ok(ExitCode::Success)
+}
+
+fn history(args: &HistoryArgs, audit_log: Option<&Path>, out: &Output) -> Result<ExitCode> {
+    let start_time = Instant::now();
+    let log = audit_log.ok_or_else(|| ValidationError::new(
+        "history reads the audit log; pass --audit-log FILE or set audit-log in the config file"
+    ))?;
+    let stats = read_history(log, &args.query())?;
+    let exit_code = stats.exit_code();
+    let report = RunReport::new(RunMode::History, log, &stats, start_time.elapsed(), exit_code.code());
+    out.history_summary(&report)?;
+    Ok(exit_code)
+}
@@ -0,0 +1,310 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+use globset::GlobMatcher;
+use serde::{Deserialize, Serialize};
+use crate::auth::{login, LoginCredentials, LoginMethod, TokenCreateRequest};
+use crate::config_updater::{TokenAssignment, TokenField};
+use crate::exit_code::ValidationError;
+use crate::file_scanner::{compile_glob, SearchRoot};
+use crate::secret::SecretToken;
+use crate::token_source::TokenSource;
+use crate::vault::{VaultClient, VaultConfig};
+
+/// Where a mapping rule takes its token from
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MappedSource {
+    /// First line of an environment variable
+    Env(String),
+    /// First line of a file, relative to the mapping file
+    File(PathBuf),
+    /// Token issued by the named `[login.<name>]` profile
+    Login(String),
+}
+
+impl fmt::Display for MappedSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MappedSource::Env(var) => write!(f, "env {}", var),
+            MappedSource::File(path) => write!(f, "file {}", path.display()),
+            MappedSource::Login(name) => write!(f, "login {}", name),
+        }
+    }
+}
+
+/// A Vault login used by `login` sources
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LoginProfile {
+    pub method: LoginMethod,
+    /// Vault address (default: --vault-addr or VAULT_ADDR)
+    pub vault_addr: Option<String>,
+    pub auth_mount: Option<String>,
+    pub role_id: Option<String>,
+    pub secret_id_file: Option<PathBuf>,
+    pub secret_id_env: Option<String>,
+    pub username: Option<String>,
+    pub password_file: Option<PathBuf>,
+    pub password_env: Option<String>,
+}
+
+impl LoginProfile {
+    fn credentials(&self, name: &str) -> Result<LoginCredentials> {
+        let missing = |what: &str| ValidationError::new(format!("Login profile {:?} needs {}", name, what));
+        match self.method {
+            LoginMethod::AppRole => Ok(LoginCredentials::AppRole {
+                role_id: self.role_id.clone().ok_or_else(|| missing("role-id"))?,
+                secret_id: secret(&self.secret_id_file, &self.secret_id_env)
+                    .ok_or_else(|| missing("secret-id-file or secret-id-env"))?
+                    .read()?,
+            }),
+            LoginMethod::Userpass => Ok(LoginCredentials::Userpass {
+                username: self.username.clone().ok_or_else(|| missing("username"))?,
+                password: secret(&self.password_file, &self.password_env)
+                    .ok_or_else(|| missing("password-file or password-env"))?
+                    .read()?,
+            }),
+            LoginMethod::Token => Ok(LoginCredentials::Token {
+                parent: TokenSource::VaultCli.read()?,
+                request: TokenCreateRequest::default(),
+            }),
+        }
+    }
+}
+
+fn secret(file: &Option<PathBuf>, env: &Option<String>) -> Option<TokenSource> {
+    file.clone().map(TokenSource::File).or_else(|| env.clone().map(TokenSource::Env))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MappingRule {
+    path: Option<String>,
+    key: Option<String>,
+    environment: Option<String>,
+    source: MappedSource,
+}
+
+/// The `[environment]` table: where a file names its environment, and the source for each one
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EnvironmentSection {
+    pointer: String,
+    #[serde(default)]
+    sources: BTreeMap<String, MappedSource>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MappingFile {
+    #[serde(default)]
+    rule: Vec<MappingRule>,
+    environment: Option<EnvironmentSection>,
+    #[serde(default)]
+    login: BTreeMap<String, LoginProfile>,
+}
+
+#[derive(Debug)]
+struct Rule {
+    path: Option<GlobMatcher>,
+    key: Option<GlobMatcher>,
+    environment: Option<String>,
+    /// Index into `TokenMapping::sources`
+    source: usize,
+}
+
+/// A token written by a mapped update, identified without revealing it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MappedToken {
+    pub source: String,
+    pub masked: String,
+    pub fingerprint: String,
+}
+
+/// Rules that pick a token per file and field, loaded from a `--mapping` file.
+///
+/// The first rule whose `path` glob matches the file, whose `key` glob matches the field's
+/// JSON pointer and whose `environment` equals the file's environment decides the token;
+/// a rule without any of them matches everything. `[environment.sources]` entries are
+/// tried after the `[[rule]]` entries.
+#[derive(Debug)]
+pub struct TokenMapping {
+    rules: Vec<Rule>,
+    /// Where each file names its environment, from `[environment] pointer`
+    environment_pointer: Option<String>,
+    sources: Vec<MappedSource>,
+    logins: BTreeMap<String, LoginProfile>,
+    /// Tokens read from `sources`, in the same order, once resolved
+    tokens: Vec<SecretToken>,
+    /// Roots the files were found under; `path` globs match below them
+    search_roots: Vec<PathBuf>,
+}
+
+impl TokenMapping {
+    /// Parses a mapping file; relative file paths are resolved against `base_dir`
+    pub fn parse(content: &str, base_dir: &Path) -> Result<Self> {
+        let file: MappingFile = toml::from_str(content)
+            .map_err(|e| ValidationError::new(e.to_string().trim_end()))?;
+        let environment_pointer = file.environment.as_ref().map(|section| section.pointer.clone());
+        if let Some(pointer) = &environment_pointer && !pointer.starts_with('/') {
+            return Err(ValidationError::new(format!(
+                "The environment pointer {:?} must start with '/' (e.g. \"/global/environment\")", pointer
+            )).into());
+        }
+        let mut rule_entries = file.rule;
+        if let Some(section) = file.environment {
+            rule_entries.extend(section.sources.into_iter().map(|(environment, source)| MappingRule {
+                path: None,
+                key: None,
+                environment: Some(environment),
+                source,
+            }));
+        }
+        if rule_entries.is_empty() {
+            return Err(ValidationError::new("The mapping has no [[rule]] or [environment.sources] entries").into());
+        }
+        if environment_pointer.is_none() && rule_entries.iter().any(|rule| rule.environment.is_some()) {
+            return Err(ValidationError::new("Rules with an environment need an [environment] pointer").into());
+        }
+
+        let mut logins = file.login;
+        for profile in logins.values_mut() {
+            for path in [&mut profile.secret_id_file, &mut profile.password_file].into_iter().flatten() {
+                *path = base_dir.join(&*path);
+            }
+        }
+
+        let mut sources: Vec<MappedSource> = Vec::new();
+        let mut rules = Vec::new();
+        for rule in rule_entries {
+            let source = match rule.source {
+                MappedSource::File(path) => MappedSource::File(base_dir.join(path)),
+                MappedSource::Login(name) if !logins.contains_key(&name) => {
+                    return Err(ValidationError::new(format!("Unknown login profile {:?}", name)).into());
+                }
+                source => source,
+            };
+            let index = sources.iter().position(|known| *known == source).unwrap_or_else(|| {
+                sources.push(source);
+                sources.len() - 1
+            });
+            rules.push(Rule {
+                path: rule.path.as_deref().map(compile_glob).transpose()?,
+                key: rule.key.as_deref().map(compile_glob).transpose()?,
+                environment: rule.environment,
+                source: index,
+            });
+        }
+        Ok(Self { rules, environment_pointer, sources, logins, tokens: Vec::new(), search_roots: Vec::new() })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read mapping file {}: {}", path.display(), e))?;
+        Self::parse(&content, path.parent().unwrap_or(Path::new(".")))
+            .map_err(|e| ValidationError::new(format!("Invalid mapping file {}: {}", path.display(), e)).into())
+    }
+
+    /// Matches `path` globs against the path below the root each file was found under, as
+    /// `--include` and `--exclude` do; other files are matched as given, without a leading `./`
+    pub fn set_search_roots(&mut self, roots: &[SearchRoot]) {
+        self.search_roots = roots.iter().map(|root| root.path.clone()).collect();
+    }
+
+    /// The source of the first rule covering `field`
+    pub fn source_for(&self, field: &TokenField<'_>) -> Option<&MappedSource> {
+        self.source_index_for(field).map(|index| &self.sources[index])
+    }
+
+    /// Index into `sources` of the first rule covering `field`
+    fn source_index_for(&self, field: &TokenField<'_>) -> Option<usize> {
+        let file = self.search_roots
+            .iter()
+            .find_map(|root| field.file.strip_prefix(root).ok())
+            .unwrap_or_else(|| field.file.strip_prefix(".").unwrap_or(field.file));
+        self.rules
+            .iter()
+            .find(|rule| {
+                rule.path.as_ref().is_none_or(|glob| glob.is_match(file))
+                    && rule.key.as_ref().is_none_or(|glob| glob.is_match(field.pointer))
+                    && rule.environment.as_deref().is_none_or(|environment| field.environment == Some(environment))
+            })
+            .map(|rule| rule.source)
+    }
+
+    /// Reads the token of every source, so a missing one fails the run before any file is touched.
+    ///
+    /// `vault` is used by login profiles that do not set their own address.
+    pub fn resolve(&mut self, vault: Option<&VaultConfig>) -> Result<()> {
+        let mut tokens = Vec::with_capacity(self.sources.len());
+        for source in &self.sources {
+            let token = self.read_source(source, vault).map_err(|e| {
+                let message = format!("Cannot read the token from {}: {}", source, e);
+                if e.downcast_ref::<ValidationError>().is_some() {
+                    ValidationError::new(message).into()
+                } else {
+                    anyhow!(message)
+                }
+            })?;
+            tokens.push(token);
+        }
+        self.tokens = tokens;
+        Ok(())
+    }
+
+    fn read_source(&self, source: &MappedSource, vault: Option<&VaultConfig>) -> Result<SecretToken> {
+        match source {
+            MappedSource::Env(var) => TokenSource::Env(var.clone()).read(),
+            MappedSource::File(path) => TokenSource::File(path.clone()).read(),
+            MappedSource::Login(name) => {
+                let profile = &self.logins[name];
+                let config = match (&profile.vault_addr, vault) {
+                    (Some(address), Some(vault)) => VaultConfig { address: address.clone(), ..vault.clone() },
+                    (Some(address), None) => VaultConfig::new(address.clone()),
+                    (None, Some(vault)) => vault.clone(),
+                    (None, None) => return Err(ValidationError::new(
+                        "No Vault address configured (set vault-addr in the profile, VAULT_ADDR or --vault-addr)"
+                    ).into()),
+                };
+                let client = VaultClient::new(config)?;
+                Ok(login(&client, &profile.credentials(name)?, profile.auth_mount.as_deref())?.token)
+            }
+        }
+    }
+
+    /// The resolved tokens, masked, with the source each came from
+    pub fn tokens(&self) -> Vec<MappedToken> {
+        self.sources
+            .iter()
+            .zip(&self.tokens)
+            .map(|(source, token)| MappedToken {
+                source: source.to_string(),
+                masked: token.masked(),
+                fingerprint: token.fingerprint(),
+            })
+            .collect()
+    }
+}
+
+impl TokenAssignment for TokenMapping {
+    fn token_for(&self, field: &TokenField<'_>) -> Option<&str> {
+        self.tokens.get(self.source_index_for(field)?).map(SecretToken::expose)
+    }
+
+    fn uses_key_paths(&self) -> bool {
+        self.rules.iter().any(|rule| rule.key.is_some())
+    }
+
+    fn environment_pointer(&self) -> Option<&str> {
+        self.environment_pointer.as_deref()
+    }
+
+    /// An environment is known when a rule names it, or when some rule applies to every environment
+    fn knows_environment(&self, environment: &str) -> bool {
+        self.rules.iter().any(|rule| rule.environment.as_deref().is_none_or(|known| known == environment))
+    }
+}
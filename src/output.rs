@@ -0,0 +1,747 @@
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use anyhow::Result;
+use clap::ValueEnum;
+use crate::audit::{AuditEntry, HistoryStats};
+use crate::config_updater::{FileOutcome, FileStatus, ScanStats, UpdateStats, VerifyStats};
+use crate::hooks::{HookOutcome, HookStage};
+use crate::inspect::{InspectStats, TokenGroup, TokenHealth};
+use crate::mapping::MappedToken;
+use crate::report::{ndjson_entry_event, ndjson_file_event, OutputFormat, RootSummary, RunReport};
+use crate::revoke::{RevocationOutcome, RevocationStatus};
+
+/// How many lines of a failed hook's stderr the text summary shows
+const HOOK_STDERR_LINES: usize = 5;
+
+/// When to use ANSI colors in text output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when writing to a terminal and `NO_COLOR` is not set
+    #[default]
+    Auto,
+    /// Always color, even when piped or when `NO_COLOR` is set
+    Always,
+    /// Never color
+    Never,
+}
+
+impl ColorChoice {
+    /// Decides whether a stream should be colored
+    pub fn enabled(self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => is_terminal && !no_color_requested(),
+        }
+    }
+}
+
+/// Whether the `NO_COLOR` convention (https://no-color.org) asks for plain output
+fn no_color_requested() -> bool {
+    env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// Symbols used to decorate text output, rendered as emoji or ASCII
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Icon {
+    Search,
+    Folder,
+    List,
+    Key,
+    Working,
+    Success,
+    Skip,
+    Warning,
+    Error,
+    Target,
+    Done,
+    Stats,
+    Sparkles,
+    Info,
+    Hint,
+    Rocket,
+    Lock,
+    Bullet,
+}
+
+impl Icon {
+    fn emoji(self) -> &'static str {
+        match self {
+            Icon::Search => "🔍",
+            Icon::Folder => "📁",
+            Icon::List => "📋",
+            Icon::Key => "🎯",
+            Icon::Working => "⚡",
+            Icon::Success => "✅",
+            Icon::Skip => "⏭️ ",
+            Icon::Warning => "⚠️ ",
+            Icon::Error => "❌",
+            Icon::Target => "🎯",
+            Icon::Done => "🎉",
+            Icon::Stats => "📊",
+            Icon::Sparkles => "✨",
+            Icon::Info => "ℹ️ ",
+            Icon::Hint => "💡",
+            Icon::Rocket => "🚀",
+            Icon::Lock => "🔒",
+            Icon::Bullet => "•",
+        }
+    }
+
+    fn ascii(self) -> &'static str {
+        match self {
+            Icon::Search | Icon::Folder | Icon::List | Icon::Key | Icon::Working
+            | Icon::Stats | Icon::Rocket | Icon::Lock => "*",
+            Icon::Success | Icon::Done | Icon::Sparkles => "[ok]",
+            Icon::Skip => "[skip]",
+            Icon::Warning => "[warn]",
+            Icon::Error => "[error]",
+            Icon::Target => "[found]",
+            Icon::Info | Icon::Hint => "[info]",
+            Icon::Bullet => "-",
+        }
+    }
+}
+
+/// Color applied to a line of text output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Plain,
+    Bold,
+    Green,
+    Yellow,
+    Red,
+}
+
+impl Style {
+    fn code(self) -> Option<&'static str> {
+        match self {
+            Style::Plain => None,
+            Style::Bold => Some("\x1b[1m"),
+            Style::Green => Some("\x1b[32m"),
+            Style::Yellow => Some("\x1b[33m"),
+            Style::Red => Some("\x1b[31m"),
+        }
+    }
+}
+
+/// Presentation settings for an [`Output`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OutputOptions {
+    pub format: OutputFormat,
+    pub verbose: bool,
+    pub quiet: bool,
+    pub color: ColorChoice,
+    pub ascii: bool,
+}
+
+type Sink = Mutex<Box<dyn Write + Send>>;
+
+/// The single place where the CLI writes to stdout and stderr.
+///
+/// Safe to share between worker threads; each call writes whole lines.
+pub struct Output {
+    options: OutputOptions,
+    stdout: Sink,
+    stderr: Sink,
+    color_stdout: bool,
+    color_stderr: bool,
+}
+
+impl Output {
+    /// Creates an output layer writing to the process's stdout and stderr
+    pub fn new(options: OutputOptions) -> Self {
+        let color_stdout = options.color.enabled(io::stdout().is_terminal());
+        let color_stderr = options.color.enabled(io::stderr().is_terminal());
+        Self {
+            options,
+            stdout: Mutex::new(Box::new(io::stdout())),
+            stderr: Mutex::new(Box::new(io::stderr())),
+            color_stdout,
+            color_stderr,
+        }
+    }
+
+    /// Creates an output layer writing to custom streams, treated as non-terminals
+    pub fn with_writers(
+        options: OutputOptions,
+        stdout: Box<dyn Write + Send>,
+        stderr: Box<dyn Write + Send>,
+    ) -> Self {
+        Self {
+            options,
+            stdout: Mutex::new(stdout),
+            stderr: Mutex::new(stderr),
+            color_stdout: options.color.enabled(false),
+            color_stderr: options.color.enabled(false),
+        }
+    }
+
+    pub fn format(&self) -> OutputFormat {
+        self.options.format
+    }
+
+    /// Whether human-readable prose goes to stdout
+    fn shows_text(&self) -> bool {
+        self.options.format == OutputFormat::Text && !self.options.quiet
+    }
+
+    /// Whether verbose progress lines are shown
+    pub fn is_verbose(&self) -> bool {
+        self.shows_text() && self.options.verbose
+    }
+
+    /// Prints a regular progress or status line
+    pub fn info(&self, icon: Icon, message: &str) {
+        if self.shows_text() {
+            self.line_out(Style::Plain, Some(icon), message);
+        }
+    }
+
+    /// Prints a line only in verbose mode
+    pub fn detail(&self, icon: Icon, message: &str) {
+        if self.is_verbose() {
+            self.line_out(Style::Plain, Some(icon), message);
+        }
+    }
+
+    /// Prints an indented list item only in verbose mode
+    pub fn detail_item(&self, message: &str) {
+        if self.is_verbose() {
+            self.item_out(Style::Plain, Icon::Bullet, message);
+        }
+    }
+
+    /// Prints a warning; goes to stderr when stdout carries machine-readable output
+    pub fn warn(&self, message: &str) {
+        if self.shows_text() {
+            self.line_out(Style::Yellow, Some(Icon::Warning), message);
+        } else if !self.options.quiet {
+            self.line_err(Style::Yellow, &format!("Warning: {}", message));
+        }
+    }
+
+    /// Prints a fatal error to stderr; never suppressed
+    pub fn error(&self, message: &str) {
+        self.line_err(Style::Red, &format!("Error: {}", message));
+    }
+
+    /// Prints raw machine-readable output to stdout; never suppressed
+    pub fn machine(&self, line: &str) {
+        self.write_out(&format!("{}\n", line));
+    }
+
+    /// Reports a single finished file, as prose in verbose mode or as an NDJSON event
+    pub fn file_event(&self, outcome: &FileOutcome) {
+        if self.options.format == OutputFormat::Ndjson {
+            if let Ok(line) = ndjson_file_event(outcome) {
+                self.machine(&line);
+            }
+            return;
+        }
+        if !self.is_verbose() {
+            return;
+        }
+
+        let path = outcome.path.display();
+        match outcome.status {
+            FileStatus::Updated => self.item_out(Style::Green, Icon::Success,
+                &format!("Updated {} (replaced {} tokens)", path, outcome.tokens)),
+            FileStatus::Unchanged => self.item_out(Style::Plain, Icon::Skip,
+                &format!("Skipped {} (no vaultToken fields)", path)),
+            FileStatus::WouldUpdate => self.item_out(Style::Plain, Icon::Target,
+                &format!("{} ({} vaultToken field{})", path, outcome.tokens, plural(outcome.tokens))),
+            FileStatus::NoTokens => self.item_out(Style::Plain, Icon::Skip,
+                &format!("{} (no vaultToken fields)", path)),
+            FileStatus::Verified => self.item_out(Style::Green, Icon::Success,
+                &format!("{} ({} vaultToken field{} verified)", path, outcome.tokens, plural(outcome.tokens))),
+            FileStatus::Drifted => self.item_out(Style::Red, Icon::Error,
+                &format!("{} ({} vaultToken field{} hold{} a different token)",
+                    path, outcome.tokens, plural(outcome.tokens), if outcome.tokens == 1 { "s" } else { "" })),
+            FileStatus::Unmatched => self.item_out(Style::Yellow, Icon::Skip,
+                &format!("Skipped {} (no mapping rule matched)", path)),
+            FileStatus::UnknownEnvironment => self.item_out(Style::Yellow, Icon::Skip,
+                &format!("Skipped {} ({})", path, environment_reason(outcome.environment.as_deref()))),
+            FileStatus::Failed => self.item_out(Style::Red, Icon::Error,
+                &format!("{}: {}", path, outcome.error.as_deref().unwrap_or("unknown error"))),
+        }
+    }
+
+    /// Prints the final result of an update run in the configured format
+    pub fn update_summary(&self, report: &RunReport<'_, UpdateStats>) -> Result<()> {
+        match self.options.format {
+            OutputFormat::Json => self.machine(&report.to_json()?),
+            OutputFormat::Ndjson => self.machine(&report.to_ndjson_summary()?),
+            OutputFormat::Text => {
+                self.update_text(report.stats, report.duration_ms);
+                self.hooks_text(&report.stats.hooks);
+                self.mapped_tokens_text(&report.mapped_tokens);
+                self.roots_text(&report.roots);
+                self.revocations_text(&report.revocations);
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints the final result of a dry-run scan in the configured format
+    pub fn scan_summary(&self, report: &RunReport<'_, ScanStats>) -> Result<()> {
+        match self.options.format {
+            OutputFormat::Json => self.machine(&report.to_json()?),
+            OutputFormat::Ndjson => self.machine(&report.to_ndjson_summary()?),
+            OutputFormat::Text => {
+                self.scan_text(report.stats, report.duration_ms);
+                self.roots_text(&report.roots);
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints the tokens found by `inspect` in the configured format
+    pub fn inspect_summary(&self, report: &RunReport<'_, InspectStats>) -> Result<()> {
+        match self.options.format {
+            OutputFormat::Json => self.machine(&report.to_json()?),
+            OutputFormat::Ndjson => self.machine(&report.to_ndjson_summary()?),
+            OutputFormat::Text => {
+                self.inspect_text(report.stats, report.duration_ms);
+                self.roots_text(&report.roots);
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints the result of `verify` in the configured format
+    pub fn verify_summary(&self, report: &RunReport<'_, VerifyStats>) -> Result<()> {
+        match self.options.format {
+            OutputFormat::Json => self.machine(&report.to_json()?),
+            OutputFormat::Ndjson => self.machine(&report.to_ndjson_summary()?),
+            OutputFormat::Text => {
+                self.verify_text(report.stats, report.duration_ms);
+                self.roots_text(&report.roots);
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints the audit log entries selected by `history` in the configured format
+    pub fn history_summary(&self, report: &RunReport<'_, HistoryStats>) -> Result<()> {
+        match self.options.format {
+            OutputFormat::Json => self.machine(&report.to_json()?),
+            OutputFormat::Ndjson => {
+                for entry in &report.stats.entries {
+                    self.machine(&ndjson_entry_event(entry)?);
+                }
+                self.machine(&report.to_ndjson_summary()?);
+            }
+            OutputFormat::Text => self.history_text(report.stats, report.search_path),
+        }
+        Ok(())
+    }
+
+    fn update_text(&self, stats: &UpdateStats, duration_ms: f64) {
+        if !self.shows_text() {
+            self.errors_to_stderr(&stats.errors);
+            return;
+        }
+
+        self.blank();
+        self.line_out(Style::Bold, Some(Icon::Done),
+            &format!("Update completed in {:.2}s", duration_ms / 1000.0));
+        self.line_out(Style::Plain, Some(Icon::Stats), "Results:");
+        self.item_out(Style::Plain, Icon::Bullet, &format!("Files processed: {}", stats.files_processed));
+        self.item_out(Style::Plain, Icon::Bullet, &format!("Files updated: {}", stats.files_updated));
+        self.item_out(Style::Plain, Icon::Bullet, &format!("Tokens replaced: {}", stats.tokens_replaced));
+        self.errors_text(&stats.errors);
+
+        if !stats.unmatched.is_empty() {
+            self.blank();
+            self.line_out(Style::Yellow, Some(Icon::Warning), "Files not covered by any mapping rule:");
+            for path in &stats.unmatched {
+                self.item_out(Style::Yellow, Icon::Bullet, &path.display().to_string());
+            }
+        }
+
+        if !stats.unknown_environment.is_empty() {
+            self.blank();
+            self.line_out(Style::Yellow, Some(Icon::Warning), "Files skipped because of their environment:");
+            for outcome in stats.files.iter().filter(|outcome| outcome.status == FileStatus::UnknownEnvironment) {
+                self.item_out(Style::Yellow, Icon::Bullet, &format!("{} ({})",
+                    outcome.path.display(), environment_reason(outcome.environment.as_deref())));
+            }
+        }
+
+        if !stats.git_exposed.is_empty() {
+            self.blank();
+            self.line_out(Style::Yellow, Some(Icon::Warning), "Files that could end up in git:");
+            for file in &stats.git_exposed {
+                let written = stats.files.iter().any(|outcome| outcome.path == file.path);
+                self.item_out(Style::Yellow, Icon::Bullet, &format!("{}{}",
+                    file.describe(), if written { "" } else { " (skipped)" }));
+            }
+        }
+
+        if stats.files_updated > 0 {
+            self.blank();
+            self.line_out(Style::Green, Some(Icon::Sparkles),
+                &format!("Successfully updated vault tokens in {} files!", stats.files_updated));
+        } else if stats.errors.is_empty() && stats.unmatched.is_empty() && stats.unknown_environment.is_empty()
+            && stats.git_exposed.is_empty()
+        {
+            self.blank();
+            self.line_out(Style::Plain, Some(Icon::Info),
+                "No files needed updating (no vaultToken fields found).");
+        }
+    }
+
+    fn scan_text(&self, stats: &ScanStats, duration_ms: f64) {
+        if !self.shows_text() {
+            self.errors_to_stderr(&stats.errors);
+            return;
+        }
+
+        self.blank();
+        self.line_out(Style::Bold, Some(Icon::Search),
+            &format!("DRY RUN scan completed in {:.2}s", duration_ms / 1000.0));
+        self.line_out(Style::Plain, Some(Icon::Stats), "Results:");
+        self.item_out(Style::Plain, Icon::Bullet, &format!("Files scanned: {}", stats.files_scanned));
+        self.item_out(Style::Plain, Icon::Bullet,
+            &format!("Files that would be updated: {}", stats.files_with_tokens));
+        self.item_out(Style::Plain, Icon::Bullet,
+            &format!("Total tokens that would be replaced: {}", stats.total_tokens_found));
+        self.errors_text(&stats.errors);
+
+        if stats.files_with_tokens > 0 {
+            self.blank();
+            self.line_out(Style::Green, Some(Icon::Hint), &format!(
+                "{} file{} would be updated with new vault tokens!",
+                stats.files_with_tokens,
+                plural(stats.files_with_tokens)));
+            self.line_out(Style::Plain, Some(Icon::Rocket), "Run without --dry-run to apply these changes.");
+        } else if stats.errors.is_empty() {
+            self.blank();
+            self.line_out(Style::Plain, Some(Icon::Info),
+                "No files would need updating (no vaultToken fields found).");
+        }
+    }
+
+    fn verify_text(&self, stats: &VerifyStats, duration_ms: f64) {
+        if !self.shows_text() {
+            // Offending files are the point of verify, so they are reported even when quiet
+            if self.options.format == OutputFormat::Text {
+                for path in &stats.drifted {
+                    self.line_err(Style::Red, &format!("Error: {} holds a different token", path.display()));
+                }
+            }
+            self.errors_to_stderr(&stats.errors);
+            return;
+        }
+
+        self.blank();
+        self.line_out(Style::Bold, Some(Icon::Search),
+            &format!("Verification completed in {:.2}s", duration_ms / 1000.0));
+        self.line_out(Style::Plain, Some(Icon::Stats), "Results:");
+        self.item_out(Style::Plain, Icon::Bullet, &format!("Expected token: [{}]", stats.expected_fingerprint));
+        self.item_out(Style::Plain, Icon::Bullet, &format!("Files checked: {}", stats.files_checked));
+        self.item_out(Style::Plain, Icon::Bullet, &format!("Files verified: {}", stats.files_verified));
+        self.item_out(Style::Plain, Icon::Bullet, &format!("Files with a different token: {}", stats.files_drifted));
+        self.errors_text(&stats.errors);
+
+        self.blank();
+        if !stats.drifted.is_empty() {
+            self.line_out(Style::Red, Some(Icon::Error), "Files holding a different token:");
+            for path in &stats.drifted {
+                self.item_out(Style::Red, Icon::Bullet, &path.display().to_string());
+            }
+        } else if stats.files_verified > 0 && stats.errors.is_empty() {
+            self.line_out(Style::Green, Some(Icon::Sparkles),
+                &format!("All {} file{} hold the expected token.", stats.files_verified, plural(stats.files_verified)));
+        } else if stats.errors.is_empty() {
+            self.line_out(Style::Plain, Some(Icon::Info), "No vaultToken fields found.");
+        }
+    }
+
+    fn inspect_text(&self, stats: &InspectStats, duration_ms: f64) {
+        if !self.shows_text() {
+            self.errors_to_stderr(&stats.errors);
+            return;
+        }
+
+        self.blank();
+        self.line_out(Style::Bold, Some(Icon::Search),
+            &format!("Inspection completed in {:.2}s", duration_ms / 1000.0));
+        self.line_out(Style::Plain, Some(Icon::Stats), "Results:");
+        self.item_out(Style::Plain, Icon::Bullet, &format!("Files scanned: {}", stats.files_scanned));
+        self.item_out(Style::Plain, Icon::Bullet, &format!("Files with tokens: {}", stats.files_with_tokens));
+        self.item_out(Style::Plain, Icon::Bullet, &format!("Distinct tokens: {}", stats.distinct_tokens));
+        self.errors_text(&stats.errors);
+
+        for group in &stats.tokens {
+            self.blank();
+            self.token_group_text(group);
+        }
+
+        let flagged = stats.flagged().count();
+        if flagged > 0 {
+            self.blank();
+            self.line_out(Style::Yellow, Some(Icon::Warning),
+                &format!("{} token{} need{} attention", flagged, plural(flagged), if flagged == 1 { "s" } else { "" }));
+        } else if stats.distinct_tokens > 1 {
+            self.blank();
+            self.line_out(Style::Plain, Some(Icon::Hint),
+                &format!("{} different tokens are in use across the tree", stats.distinct_tokens));
+        }
+    }
+
+    fn history_text(&self, stats: &HistoryStats, log: &Path) {
+        if !self.shows_text() {
+            self.errors_to_stderr(&stats.errors);
+            return;
+        }
+
+        if stats.entries.is_empty() {
+            self.line_out(Style::Plain, Some(Icon::Info),
+                &format!("No matching entries among the {} in {}", stats.entries_read, log.display()));
+        } else {
+            let shown = if stats.entries.len() < stats.entries_matched {
+                format!("last {} of {}", stats.entries.len(), stats.entries_matched)
+            } else {
+                stats.entries_matched.to_string()
+            };
+            self.line_out(Style::Plain, Some(Icon::List), &format!(
+                "{} change{} recorded in {}:", shown, plural(stats.entries_matched), log.display()));
+            for entry in &stats.entries {
+                self.item_out(Style::Plain, Icon::Bullet, &history_line(entry));
+            }
+        }
+        if !stats.errors.is_empty() {
+            self.blank();
+            self.line_out(Style::Red, Some(Icon::Error), "Unreadable lines:");
+            for error in &stats.errors {
+                self.item_out(Style::Red, Icon::Bullet, error);
+            }
+        }
+    }
+
+    fn token_group_text(&self, group: &TokenGroup) {
+        let (style, icon, verdict) = match group.health {
+            None => (Style::Plain, Icon::Lock, String::new()),
+            Some(TokenHealth::Valid) => (Style::Green, Icon::Success,
+                format!(": valid, {}", group.lookup.as_ref().map(|info| info.summary()).unwrap_or_default())),
+            Some(TokenHealth::Expiring) => (Style::Yellow, Icon::Warning,
+                format!(": expiring soon, {}", group.lookup.as_ref().map(|info| info.summary()).unwrap_or_default())),
+            Some(TokenHealth::Invalid) => (Style::Red, Icon::Error,
+                ": rejected by Vault (expired, revoked or unknown)".to_string()),
+            Some(TokenHealth::LookupFailed) => (Style::Red, Icon::Error,
+                format!(": lookup failed: {}", group.error.as_deref().unwrap_or("unknown error"))),
+        };
+        self.line_out(style, Some(icon), &format!(
+            "{} [{}] in {} file{}{}",
+            group.masked, group.fingerprint, group.files.len(), plural(group.files.len()), verdict));
+        for file in &group.files {
+            self.item_out(Style::Plain, Icon::Bullet, &file.display().to_string());
+        }
+    }
+
+    fn roots_text(&self, roots: &[RootSummary]) {
+        if roots.is_empty() || !self.shows_text() {
+            return;
+        }
+        self.blank();
+        self.line_out(Style::Plain, Some(Icon::Folder), "Results by root:");
+        for root in roots {
+            let statuses: Vec<String> = root.statuses
+                .iter()
+                .map(|(status, count)| format!("{} {}", count, status_label(*status)))
+                .collect();
+            let breakdown = if statuses.is_empty() { String::new() } else { format!(" ({})", statuses.join(", ")) };
+            self.item_out(Style::Plain, Icon::Bullet, &format!(
+                "{}: {} file{}{}", root.path.display(), root.files, plural(root.files), breakdown));
+        }
+    }
+
+    fn hooks_text(&self, hooks: &[HookOutcome]) {
+        if hooks.is_empty() || !self.shows_text() {
+            return;
+        }
+        self.blank();
+        self.line_out(Style::Plain, Some(Icon::Rocket), "Hooks:");
+        for hook in hooks {
+            let stage = match hook.stage {
+                HookStage::Pre => "pre",
+                HookStage::Post => "post",
+            };
+            if hook.succeeded() {
+                self.item_out(Style::Green, Icon::Success, &format!("{}: `{}`", stage, hook.command));
+                continue;
+            }
+            self.item_out(Style::Red, Icon::Error, &format!("{}: {}", stage, hook.describe()));
+            // The end of stderr usually says what went wrong
+            let lines: Vec<&str> = hook.stderr.lines().filter(|line| !line.trim().is_empty()).collect();
+            for line in &lines[lines.len().saturating_sub(HOOK_STDERR_LINES)..] {
+                self.write_out(&format!("      {}\n", line));
+            }
+        }
+    }
+
+    fn mapped_tokens_text(&self, tokens: &[MappedToken]) {
+        if tokens.is_empty() || !self.shows_text() {
+            return;
+        }
+        self.blank();
+        self.line_out(Style::Plain, Some(Icon::Key), "Tokens by source:");
+        for token in tokens {
+            self.item_out(Style::Plain, Icon::Bullet,
+                &format!("{}: {} [{}]", token.source, token.masked, token.fingerprint));
+        }
+    }
+
+    fn revocations_text(&self, revocations: &[RevocationOutcome]) {
+        if revocations.is_empty() {
+            return;
+        }
+        if self.shows_text() {
+            self.blank();
+            self.line_out(Style::Plain, Some(Icon::Lock), "Old tokens:");
+        }
+        for revocation in revocations {
+            let token = format!("{} [{}]", revocation.masked, revocation.fingerprint);
+            match revocation.status {
+                RevocationStatus::Revoked if self.shows_text() => {
+                    self.item_out(Style::Green, Icon::Success, &format!("Revoked {}", token));
+                }
+                RevocationStatus::AlreadyInvalid if self.shows_text() => {
+                    self.item_out(Style::Plain, Icon::Skip, &format!("{} was already revoked or expired", token));
+                }
+                RevocationStatus::Failed => {
+                    let message = format!("Failed to revoke {}: {}",
+                        token, revocation.error.as_deref().unwrap_or("unknown error"));
+                    if self.shows_text() {
+                        self.item_out(Style::Red, Icon::Error, &message);
+                    } else {
+                        self.line_err(Style::Red, &format!("Error: {}", message));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn errors_text(&self, errors: &[String]) {
+        if errors.is_empty() {
+            return;
+        }
+        self.item_out(Style::Plain, Icon::Bullet, &format!("Errors: {}", errors.len()));
+        self.blank();
+        self.line_out(Style::Red, Some(Icon::Error), "Errors encountered:");
+        for error in errors {
+            self.item_out(Style::Red, Icon::Bullet, error);
+        }
+    }
+
+    /// In quiet mode errors are still reported, on stderr
+    fn errors_to_stderr(&self, errors: &[String]) {
+        if self.options.format == OutputFormat::Text {
+            for error in errors {
+                self.line_err(Style::Red, &format!("Error: {}", error));
+            }
+        }
+    }
+
+    fn icon(&self, icon: Icon) -> &'static str {
+        if self.options.ascii {
+            icon.ascii()
+        } else {
+            icon.emoji()
+        }
+    }
+
+    fn paint(text: &str, style: Style, enabled: bool) -> String {
+        match style.code() {
+            Some(code) if enabled => format!("{}{}\x1b[0m", code, text),
+            _ => text.to_string(),
+        }
+    }
+
+    fn render(&self, icon: Option<Icon>, message: &str) -> String {
+        let message = if self.options.ascii { to_ascii(message) } else { message.to_string() };
+        match icon {
+            Some(icon) => format!("{} {}", self.icon(icon), message),
+            None => message,
+        }
+    }
+
+    fn line_out(&self, style: Style, icon: Option<Icon>, message: &str) {
+        let line = Self::paint(&self.render(icon, message), style, self.color_stdout);
+        self.write_out(&format!("{}\n", line));
+    }
+
+    fn item_out(&self, style: Style, icon: Icon, message: &str) {
+        let line = Self::paint(&self.render(Some(icon), message), style, self.color_stdout);
+        self.write_out(&format!("   {}\n", line));
+    }
+
+    fn line_err(&self, style: Style, message: &str) {
+        let message = if self.options.ascii { to_ascii(message) } else { message.to_string() };
+        let line = Self::paint(&message, style, self.color_stderr);
+        if let Ok(mut stderr) = self.stderr.lock() {
+            let _ = writeln!(stderr, "{}", line);
+            let _ = stderr.flush();
+        }
+    }
+
+    fn blank(&self) {
+        self.write_out("\n");
+    }
+
+    fn write_out(&self, text: &str) {
+        if let Ok(mut stdout) = self.stdout.lock() {
+            let _ = stdout.write_all(text.as_bytes());
+            let _ = stdout.flush();
+        }
+    }
+}
+
+/// Replaces non-ASCII characters so output survives legacy log collectors
+fn to_ascii(text: &str) -> String {
+    text.chars().map(|c| if c.is_ascii() { c } else { '?' }).collect()
+}
+
+/// One audit entry: when, by whom, which file and which tokens
+fn history_line(entry: &AuditEntry) -> String {
+    let fingerprints = |fingerprints: &[String]| match fingerprints {
+        [] => "[none]".to_string(),
+        _ => fingerprints.iter().map(|fingerprint| format!("[{}]", fingerprint)).collect::<Vec<_>>().join(" "),
+    };
+    format!(
+        "{} {} {}@{} {}: {} -> {} (run {})",
+        entry.timestamp, entry.command, entry.user, entry.host, entry.path.display(),
+        fingerprints(&entry.old_fingerprints), fingerprints(&entry.new_fingerprints), entry.run_id,
+    )
+}
+
+/// Why a file was skipped by an environment-based mapping
+fn environment_reason(environment: Option<&str>) -> String {
+    match environment {
+        Some(environment) => format!("no token source for environment {:?}", environment),
+        None => "no environment field".to_string(),
+    }
+}
+
+fn status_label(status: FileStatus) -> &'static str {
+    match status {
+        FileStatus::Updated => "updated",
+        FileStatus::Unchanged => "unchanged",
+        FileStatus::WouldUpdate => "to update",
+        FileStatus::NoTokens => "without tokens",
+        FileStatus::Verified => "verified",
+        FileStatus::Drifted => "drifted",
+        FileStatus::Unmatched => "unmatched",
+        FileStatus::UnknownEnvironment => "unknown environment",
+        FileStatus::Failed => "failed",
+    }
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 { "" } else { "s" }
+}
@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use crate::exit_code::ValidationError;
+use crate::file_scanner::GitPolicy;
+use crate::hooks::{HookConfig, Hooks};
+use crate::report::OutputFormat;
+
+/// Name of the project config file, looked up from the working directory upward
+pub const CONFIG_FILE_NAME: &str = ".vault-config-updater.toml";
+
+/// Settings from the `[defaults]` table or a `[profiles.<name>]` table
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Profile {
+    /// Search roots, relative to the directory holding the config file
+    pub paths: Option<Vec<PathBuf>>,
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    pub keys: Option<Vec<String>>,
+    pub output: Option<OutputFormat>,
+    pub vault_addr: Option<String>,
+    /// Commands run around updates
+    pub hooks: Option<HookConfig>,
+    /// JSONL audit log of written files, relative to the directory holding the config file
+    pub audit_log: Option<PathBuf>,
+    /// What updates do with config files that git could pick up
+    pub git_policy: Option<GitPolicy>,
+}
+
+impl Profile {
+    /// Settings from `self`, with every value that `other` sets taking precedence
+    pub fn overlay(self, other: &Profile) -> Profile {
+        Profile {
+            paths: other.paths.clone().or(self.paths),
+            include: other.include.clone().or(self.include),
+            exclude: other.exclude.clone().or(self.exclude),
+            keys: other.keys.clone().or(self.keys),
+            output: other.output.or(self.output),
+            vault_addr: other.vault_addr.clone().or(self.vault_addr),
+            hooks: other.hooks.clone().or(self.hooks),
+            audit_log: other.audit_log.clone().or(self.audit_log),
+            git_policy: other.git_policy.or(self.git_policy),
+        }
+    }
+}
+
+/// Contents of a `.vault-config-updater.toml` file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Settings used with or without a profile
+    #[serde(default)]
+    pub defaults: Profile,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl ProjectConfig {
+    /// Parses a config file; relative `paths` are resolved against `base_dir`
+    pub fn parse(content: &str, base_dir: &Path) -> Result<Self> {
+        let mut config: ProjectConfig = toml::from_str(content)
+            .map_err(|e| ValidationError::new(e.to_string().trim_end()))?;
+        for profile in std::iter::once(&mut config.defaults).chain(config.profiles.values_mut()) {
+            for path in profile.paths.iter_mut().flatten().chain(profile.audit_log.as_mut()) {
+                *path = base_dir.join(&*path);
+            }
+            // Catch bad globs and timeouts when the file is read, not when an update runs
+            if let Some(hooks) = &profile.hooks {
+                Hooks::new(hooks)?;
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read config file {}: {}", path.display(), e))?;
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+        Self::parse(&content, base_dir)
+            .map_err(|e| ValidationError::new(format!("Invalid config file {}: {}", path.display(), e)).into())
+    }
+
+    /// Finds the nearest `.vault-config-updater.toml` in `start_dir` or its parents, falling back
+    /// to `vault-config-updater/config.toml` in `$XDG_CONFIG_HOME` (default: `~/.config`)
+    pub fn discover<P: AsRef<Path>>(start_dir: P) -> Option<PathBuf> {
+        start_dir.as_ref()
+            .ancestors()
+            .map(|dir| dir.join(CONFIG_FILE_NAME))
+            .chain(user_config_path())
+            .find(|path| path.is_file())
+    }
+
+    /// Whether `path` is the user's own config file under `$XDG_CONFIG_HOME`
+    pub fn is_user_config(path: &Path) -> bool {
+        user_config_path().is_some_and(|user| user == path)
+    }
+
+    /// The settings for `profile`: the defaults, overlaid with the profile's own values
+    pub fn settings(&self, profile: Option<&str>) -> Result<Profile> {
+        let Some(name) = profile else {
+            return Ok(self.defaults.clone());
+        };
+        let selected = self.profiles.get(name).ok_or_else(|| {
+            let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            ValidationError::new(format!(
+                "Unknown profile {:?} (available: {})",
+                name,
+                if known.is_empty() { "none".to_string() } else { known.join(", ") }
+            ))
+        })?;
+        Ok(self.defaults.clone().overlay(selected))
+    }
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("vault-config-updater").join("config.toml"))
+}
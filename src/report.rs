@@ -0,0 +1,208 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::audit::AuditEntry;
+use crate::config_updater::{FileOutcome, FileStatus};
+use crate::file_scanner::SearchRoot;
+use crate::mapping::MappedToken;
+use crate::revoke::RevocationOutcome;
+use crate::secret::SecretToken;
+use crate::vault::TokenInfo;
+
+/// Version of the machine-readable output schema.
+///
+/// Bumped only for incompatible changes; new fields may be added at any time.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// How results are written to stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// A single JSON document printed when the run finishes
+    Json,
+    /// One JSON object per line: an event per file, then a summary
+    Ndjson,
+}
+
+impl OutputFormat {
+    /// Whether the output is meant for machines rather than people
+    pub fn is_machine_readable(self) -> bool {
+        self != OutputFormat::Text
+    }
+}
+
+/// Kind of run a report describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunMode {
+    Update,
+    DryRun,
+    Inspect,
+    Verify,
+    History,
+}
+
+/// Masked description of the token used for a run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TokenSummary {
+    pub masked: String,
+    pub fingerprint: String,
+    pub length: usize,
+    /// Vault's view of the token, when it was validated before the run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lookup: Option<TokenInfo>,
+}
+
+impl TokenSummary {
+    pub fn new(token: &SecretToken) -> Self {
+        Self {
+            masked: token.masked(),
+            fingerprint: token.fingerprint(),
+            length: token.len(),
+            lookup: None,
+        }
+    }
+}
+
+/// Results for one search root of a run that covers several
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RootSummary {
+    pub path: PathBuf,
+    pub files: usize,
+    /// Number of files per outcome, e.g. `{"updated": 3, "unchanged": 1}`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub statuses: BTreeMap<FileStatus, usize>,
+}
+
+impl RootSummary {
+    /// Breaks the per-file outcomes of a run down by the root each file was found in
+    pub fn from_outcomes(roots: &[SearchRoot], outcomes: &[FileOutcome]) -> Vec<RootSummary> {
+        let statuses: HashMap<&Path, FileStatus> = outcomes
+            .iter()
+            .map(|outcome| (outcome.path.as_path(), outcome.status))
+            .collect();
+        roots
+            .iter()
+            .map(|root| {
+                let mut counts = BTreeMap::new();
+                for status in root.files.iter().filter_map(|file| statuses.get(file.as_path())) {
+                    *counts.entry(*status).or_insert(0) += 1;
+                }
+                RootSummary { path: root.path.clone(), files: root.files.len(), statuses: counts }
+            })
+            .collect()
+    }
+}
+
+/// Complete, machine-readable result of a run
+#[derive(Debug, Serialize)]
+pub struct RunReport<'a, S: Serialize> {
+    pub schema_version: u32,
+    pub mode: RunMode,
+    pub search_path: &'a Path,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<TokenSummary>,
+    pub duration_ms: f64,
+    pub exit_code: u8,
+    pub stats: &'a S,
+    /// Per-root breakdown, present when the run covered more than one root
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub roots: Vec<RootSummary>,
+    /// Tokens written by a `--mapping` update, one per source
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mapped_tokens: Vec<MappedToken>,
+    /// Old tokens revoked after the update (`--revoke-old`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub revocations: Vec<RevocationOutcome>,
+}
+
+impl<'a, S: Serialize> RunReport<'a, S> {
+    pub fn new(mode: RunMode, search_path: &'a Path, stats: &'a S, duration: Duration, exit_code: u8) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            mode,
+            search_path,
+            token: None,
+            duration_ms: duration.as_secs_f64() * 1000.0,
+            exit_code,
+            stats,
+            roots: Vec::new(),
+            mapped_tokens: Vec::new(),
+            revocations: Vec::new(),
+        }
+    }
+
+    pub fn with_token(mut self, token: &SecretToken) -> Self {
+        self.token = Some(TokenSummary::new(token));
+        self
+    }
+
+    /// Attaches the result of a pre-flight token lookup
+    pub fn with_token_info(mut self, info: Option<TokenInfo>) -> Self {
+        if let Some(token) = &mut self.token {
+            token.lookup = info;
+        }
+        self
+    }
+
+    /// Attaches the per-root breakdown; a single root is already described by the totals
+    pub fn with_roots(mut self, roots: Vec<RootSummary>) -> Self {
+        if roots.len() > 1 {
+            self.roots = roots;
+        }
+        self
+    }
+
+    pub fn with_mapped_tokens(mut self, tokens: Vec<MappedToken>) -> Self {
+        self.mapped_tokens = tokens;
+        self
+    }
+
+    pub fn with_revocations(mut self, revocations: Vec<RevocationOutcome>) -> Self {
+        self.revocations = revocations;
+        self
+    }
+
+    /// Renders the report as a pretty-printed JSON document
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Renders the closing `summary` event of an NDJSON stream.
+    ///
+    /// Per-file outcomes and audit entries were already streamed as `file` and `entry` events,
+    /// so they are left out here.
+    pub fn to_ndjson_summary(&self) -> Result<String> {
+        let mut value = serde_json::to_value(self)?;
+        if let Some(stats) = value.get_mut("stats").and_then(Value::as_object_mut) {
+            stats.remove("files");
+            stats.remove("entries");
+        }
+        Ok(serde_json::to_string(&Event { event: "summary", body: &value })?)
+    }
+}
+
+/// A line of NDJSON output, tagged with its event type
+#[derive(Serialize)]
+struct Event<'a, T: Serialize> {
+    event: &'static str,
+    #[serde(flatten)]
+    body: &'a T,
+}
+
+/// Renders a per-file NDJSON event
+pub fn ndjson_file_event(outcome: &FileOutcome) -> Result<String> {
+    Ok(serde_json::to_string(&Event { event: "file", body: outcome })?)
+}
+
+/// Renders an NDJSON event for an audit log entry shown by `history`
+pub fn ndjson_entry_event(entry: &AuditEntry) -> Result<String> {
+    Ok(serde_json::to_string(&Event { event: "entry", body: entry })?)
+}
@@ -0,0 +1,49 @@
+use serde::Serialize;
+use crate::secret::SecretToken;
+use crate::vault::{VaultClient, VaultError};
+
+/// What happened when revoking one old token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevocationStatus {
+    Revoked,
+    /// Vault no longer accepts the token (already revoked or expired)
+    AlreadyInvalid,
+    Failed,
+}
+
+/// Per-token revocation result, identified by fingerprint only
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RevocationOutcome {
+    pub fingerprint: String,
+    pub masked: String,
+    pub status: RevocationStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Revokes every old token except `new_token`.
+///
+/// Only call this after every file has been updated: a revoked token still
+/// referenced by a config would break that service.
+pub fn revoke_old_tokens(client: &VaultClient, old_tokens: &[SecretToken], new_token: &SecretToken) -> Vec<RevocationOutcome> {
+    old_tokens
+        .iter()
+        .filter(|token| *token != new_token)
+        .map(|token| {
+            let (status, error) = match client.revoke_self(token) {
+                Ok(()) => (RevocationStatus::Revoked, None),
+                Err(e) => match e.downcast_ref::<VaultError>() {
+                    Some(vault_error) if vault_error.is_permission_denied() => (RevocationStatus::AlreadyInvalid, None),
+                    _ => (RevocationStatus::Failed, Some(e.to_string())),
+                },
+            };
+            RevocationOutcome {
+                fingerprint: token.fingerprint(),
+                masked: token.masked(),
+                status,
+                error,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,108 @@
+use std::fmt;
+use std::str::FromStr;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Deserializer};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+/// Key for token fingerprints.
+///
+/// The key is fixed so fingerprints are stable across machines and runs, which lets
+/// CI jobs and audit logs compare tokens without ever storing them.
+const FINGERPRINT_KEY: &[u8] = b"vault-config-updater/token-fingerprint/v1";
+
+/// Number of hex characters kept from the keyed hash
+const FINGERPRINT_LEN: usize = 12;
+
+/// A Vault token that never reveals its value through `Debug` or `Display`.
+///
+/// Code that needs the raw value must call [`SecretToken::expose`] explicitly;
+/// everything else should identify the token by [`SecretToken::fingerprint`].
+/// The backing memory is overwritten with zeros when the token is dropped.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretToken(Zeroizing<String>);
+
+impl SecretToken {
+    pub fn new<S: Into<String>>(token: S) -> Self {
+        Self(Zeroizing::new(token.into()))
+    }
+
+    /// The raw token value, for writing into files and Vault requests only
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Stable short identifier derived from the token with a keyed hash
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.0)
+    }
+
+    /// The token type prefix followed by asterisks, e.g. `hvs.****`
+    pub fn masked(&self) -> String {
+        mask_token(&self.0)
+    }
+}
+
+impl fmt::Debug for SecretToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretToken({})", self)
+    }
+}
+
+impl fmt::Display for SecretToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]", self.masked(), self.fingerprint())
+    }
+}
+
+impl FromStr for SecretToken {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s))
+    }
+}
+
+impl From<String> for SecretToken {
+    fn from(token: String) -> Self {
+        Self::new(token)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretToken {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+/// Computes the fingerprint of a raw token value, e.g. one read from a config file
+pub fn fingerprint(token: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(FINGERPRINT_KEY)
+        .expect("HMAC accepts keys of any length");
+    mac.update(token.as_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    digest
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>()[..FINGERPRINT_LEN]
+        .to_string()
+}
+
+/// Masks a token for display, keeping only its well-known type prefix
+pub fn mask_token(token: &str) -> String {
+    const PREFIXES: [&str; 5] = ["hvs.", "hvb.", "hvr.", "s.", "b."];
+
+    match PREFIXES.iter().find(|prefix| token.starts_with(*prefix)) {
+        Some(prefix) => format!("{}****", prefix),
+        None => "****".to_string(),
+    }
+}
@@ -0,0 +1,372 @@
+#[cfg(unix)]
+use std::fs;
+#[cfg(unix)]
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+#[cfg(unix)]
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+#[cfg(unix)]
+use std::thread;
+#[cfg(unix)]
+use std::time::Duration;
+use std::time::{Instant, SystemTime};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+#[cfg(unix)]
+use zeroize::Zeroizing;
+use crate::audit::AuditLog;
+use crate::auth::unwrap_token;
+use crate::config_updater::{scan_files_parallel, update_files_parallel, TokenFields};
+use crate::hooks::Hooks;
+use crate::file_scanner::{check_git, find_config_files_in_roots, FileFilter, GitPolicy, SearchRoot};
+use crate::report::{RootSummary, RunMode, RunReport};
+use crate::exit_code::ValidationError;
+use crate::secret::SecretToken;
+use crate::vault::VaultClient;
+
+/// Socket file name under `$XDG_RUNTIME_DIR` (or the temp directory)
+const SOCKET_NAME: &str = "vault-config-updater.sock";
+
+/// How often the accept loop checks the shutdown flag
+#[cfg(unix)]
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The token a `rotate` request carries
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RotateRequest {
+    pub token: SecretToken,
+    /// `token` is a response-wrapping token, unwrapped through Vault before the rotation
+    #[serde(default)]
+    pub wrapped: bool,
+}
+
+/// A request on the control socket, one JSON object per line
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+pub enum Request {
+    /// Write the token into every known file
+    Rotate(RotateRequest),
+    /// Report what a rotation would change
+    Scan,
+    /// Describe the server and the last rotation
+    Status,
+    /// Walk the roots again to pick up added or removed files
+    Rescan,
+}
+
+impl Request {
+    /// The `command` name, as used in log lines
+    pub fn name(&self) -> &'static str {
+        match self {
+            Request::Rotate(_) => "rotate",
+            Request::Scan => "scan",
+            Request::Status => "status",
+            Request::Rescan => "rescan",
+        }
+    }
+}
+
+/// The last rotation performed by the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LastRotation {
+    pub at: String,
+    pub fingerprint: String,
+    pub files_updated: usize,
+    pub exit_code: u8,
+}
+
+/// Answer to a `status` request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ServerStatus {
+    pub roots: Vec<PathBuf>,
+    pub files: usize,
+    pub started_at: String,
+    pub uptime_secs: u64,
+    pub rotations: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_rotation: Option<LastRotation>,
+}
+
+/// Where the server finds its files
+#[derive(Debug, Clone, Default)]
+pub struct ServeSelection {
+    pub paths: Vec<PathBuf>,
+    pub files_from: Option<PathBuf>,
+    pub filter: FileFilter,
+    pub fields: TokenFields,
+    /// What rotations do with files that git could pick up
+    pub git_policy: GitPolicy,
+    /// Add files that git does not ignore to `.gitignore` before each rotation
+    pub gitignore: bool,
+}
+
+struct ServerState {
+    roots: Vec<SearchRoot>,
+    rotations: usize,
+    last_rotation: Option<LastRotation>,
+}
+
+/// Holds the discovered files in memory and answers control requests.
+///
+/// Requests are handled one at a time, so two rotations never interleave.
+pub struct Server {
+    selection: ServeSelection,
+    /// Unwraps response-wrapped tokens
+    vault: Option<VaultClient>,
+    hooks: Hooks,
+    /// Records the files each rotation writes
+    audit: Option<AuditLog>,
+    started_at: SystemTime,
+    started: Instant,
+    state: Mutex<ServerState>,
+}
+
+impl Server {
+    /// Discovers the files once; later requests reuse the list until a `rescan`
+    pub fn new(selection: ServeSelection) -> Result<Self> {
+        let roots = find_config_files_in_roots(&selection.paths, selection.files_from.as_deref(), &selection.filter)?;
+        Ok(Self {
+            selection,
+            vault: None,
+            hooks: Hooks::default(),
+            audit: None,
+            started_at: SystemTime::now(),
+            started: Instant::now(),
+            state: Mutex::new(ServerState { roots, rotations: 0, last_rotation: None }),
+        })
+    }
+
+    /// Lets `rotate` requests carry response-wrapped tokens
+    pub fn with_vault(mut self, client: VaultClient) -> Self {
+        self.vault = Some(client);
+        self
+    }
+
+    /// Runs `hooks` around every rotation
+    pub fn with_hooks(mut self, hooks: Hooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Records every rotation in `audit`
+    pub fn with_audit(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Number of files currently known
+    pub fn file_count(&self) -> usize {
+        self.lock().roots.iter().map(|root| root.files.len()).sum()
+    }
+
+    /// Answers one request line with one response line: `{"ok": true, "result": ...}` or `{"ok": false, "error": ...}`
+    pub fn handle_line(&self, line: &str) -> String {
+        let (_, result) = self.answer(line);
+        response_line(&result)
+    }
+
+    /// Parses and runs a request line, returning the command name alongside the result
+    fn answer(&self, line: &str) -> (&'static str, Result<Value>) {
+        match serde_json::from_str::<Request>(line) {
+            Ok(request) => (request.name(), self.handle(request)),
+            Err(e) => ("invalid", Err(anyhow!("Invalid request: {}", e))),
+        }
+    }
+
+    /// Runs a request and returns its result
+    pub fn handle(&self, request: Request) -> Result<Value> {
+        let mut state = self.lock();
+        let start = Instant::now();
+        match request {
+            Request::Rotate(RotateRequest { token, wrapped }) => {
+                if token.is_empty() {
+                    return Err(ValidationError::new("The token is empty").into());
+                }
+                let token = if wrapped {
+                    let client = self.vault.as_ref().ok_or_else(|| ValidationError::new(
+                        "The token is response-wrapped, but the server has no Vault address to unwrap it"
+                    ))?;
+                    unwrap_token(client, &token)?.token
+                } else {
+                    token
+                };
+                let git = check_git(all_files(&state.roots), self.selection.git_policy, self.selection.gitignore)?;
+                let mut stats = update_files_parallel(&git.files, token.expose(), &self.selection.fields, &self.hooks, |_| {});
+                stats.git_exposed = git.exposed;
+                if let Some(audit) = &self.audit {
+                    audit.record_stats(&mut stats);
+                }
+                let exit_code = stats.exit_code();
+                state.rotations += 1;
+                state.last_rotation = Some(LastRotation {
+                    at: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+                    fingerprint: token.fingerprint(),
+                    files_updated: stats.files_updated,
+                    exit_code: exit_code.code(),
+                });
+                let report = RunReport::new(RunMode::Update, report_path(&state.roots), &stats, start.elapsed(), exit_code.code())
+                    .with_roots(RootSummary::from_outcomes(&state.roots, &stats.files))
+                    .with_token(&token);
+                Ok(serde_json::to_value(&report)?)
+            }
+            Request::Scan => {
+                let stats = scan_files_parallel(&all_files(&state.roots), &self.selection.fields, |_| {});
+                let exit_code = stats.exit_code();
+                let report = RunReport::new(RunMode::DryRun, report_path(&state.roots), &stats, start.elapsed(), exit_code.code())
+                    .with_roots(RootSummary::from_outcomes(&state.roots, &stats.files));
+                Ok(serde_json::to_value(&report)?)
+            }
+            Request::Status => Ok(serde_json::to_value(ServerStatus {
+                roots: state.roots.iter().map(|root| root.path.clone()).collect(),
+                files: state.roots.iter().map(|root| root.files.len()).sum(),
+                started_at: humantime::format_rfc3339_seconds(self.started_at).to_string(),
+                uptime_secs: self.started.elapsed().as_secs(),
+                rotations: state.rotations,
+                last_rotation: state.last_rotation.clone(),
+            })?),
+            Request::Rescan => {
+                let selection = &self.selection;
+                state.roots = find_config_files_in_roots(&selection.paths, selection.files_from.as_deref(), &selection.filter)?;
+                Ok(json!({ "files": all_files(&state.roots).len() }))
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ServerState> {
+        // A panic while handling a request leaves the state usable
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn all_files(roots: &[SearchRoot]) -> Vec<PathBuf> {
+    roots.iter().flat_map(|root| root.files.iter().cloned()).collect()
+}
+
+fn report_path(roots: &[SearchRoot]) -> &Path {
+    roots.first().map_or(Path::new("."), |root| root.path.as_path())
+}
+
+/// `$XDG_RUNTIME_DIR/vault-config-updater.sock`, or the temp directory when that is unset
+pub fn default_socket_path() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join(SOCKET_NAME)
+}
+
+/// Listens on a Unix-domain socket until `shutdown` is set, then removes the socket.
+///
+/// The socket is only accessible to the current user. A stale socket left by a crashed server
+/// is replaced; one that still accepts connections is an error. `log` receives a line for each
+/// request other than `status`.
+#[cfg(unix)]
+pub fn serve_unix<L>(server: &Server, socket_path: &Path, shutdown: &AtomicBool, log: L) -> Result<()>
+where
+    L: Fn(&str) + Sync,
+{
+    if let Ok(metadata) = fs::symlink_metadata(socket_path) {
+        // Only a socket left behind by a stopped server is replaced, never a file passed by mistake
+        if !metadata.file_type().is_socket() {
+            return Err(anyhow!("{} exists and is not a socket", socket_path.display()));
+        }
+        if UnixStream::connect(socket_path).is_ok() {
+            return Err(anyhow!("Another server is already listening on {}", socket_path.display()));
+        }
+        fs::remove_file(socket_path)?;
+    }
+    let listener = UnixListener::bind(socket_path)
+        .map_err(|e| anyhow!("Cannot listen on {}: {}", socket_path.display(), e))?;
+    fs::set_permissions(socket_path, fs::Permissions::from_mode(0o600))?;
+    listener.set_nonblocking(true)?;
+
+    let result = thread::scope(|scope| -> Result<()> {
+        while !shutdown.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(false)?;
+                    stream.set_read_timeout(Some(ACCEPT_INTERVAL * 4))?;
+                    let log = &log;
+                    scope.spawn(move || handle_connection(server, stream, shutdown, log));
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(ACCEPT_INTERVAL),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    });
+    let _ = fs::remove_file(socket_path);
+    result
+}
+
+/// Unix-domain sockets are not available on this platform, so serving is always an error
+#[cfg(not(unix))]
+pub fn serve_unix<L>(_server: &Server, socket_path: &Path, _shutdown: &AtomicBool, _log: L) -> Result<()>
+where
+    L: Fn(&str) + Sync,
+{
+    Err(anyhow!("Cannot listen on {}: serve needs Unix-domain sockets, which this platform lacks", socket_path.display()))
+}
+
+/// Answers each request line until the client closes the connection or the server shuts down
+#[cfg(unix)]
+fn handle_connection<L: Fn(&str)>(server: &Server, stream: UnixStream, shutdown: &AtomicBool, log: &L) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(stream);
+    // Rotate requests carry the token
+    let mut line = Zeroizing::new(String::new());
+    while !shutdown.load(Ordering::SeqCst) {
+        match reader.read_line(&mut line) {
+            Ok(0) => return,
+            Ok(_) => {}
+            // The read timeout only serves to notice a shutdown; a partial line is kept
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(_) => return,
+        }
+        if !line.trim().is_empty() {
+            let (command, result) = server.answer(&line);
+            if let Some(message) = summarize(command, &result) {
+                log(&message);
+            }
+            if writeln!(writer, "{}", response_line(&result)).is_err() {
+                return;
+            }
+        }
+        line.clear();
+    }
+}
+
+fn response_line(result: &Result<Value>) -> String {
+    match result {
+        Ok(result) => json!({ "ok": true, "result": result }),
+        Err(e) => json!({ "ok": false, "error": e.to_string() }),
+    }.to_string()
+}
+
+/// A log line for a handled request; `status` requests are not logged
+pub(crate) fn summarize(command: &str, result: &Result<Value>) -> Option<String> {
+    let result = match result {
+        Ok(result) => result,
+        Err(e) => return Some(format!("{} failed: {}", command, e)),
+    };
+    let stats = &result["stats"];
+    match command {
+        "rotate" => Some(format!(
+            "rotate: {} of {} files updated with token [{}]",
+            stats["files_updated"], stats["files_processed"], result["token"]["fingerprint"].as_str().unwrap_or("?")
+        )),
+        "scan" => Some(format!("scan: {} of {} files hold a token", stats["files_with_tokens"], stats["files_scanned"])),
+        "rescan" => Some(format!("rescan: {} files", result["files"])),
+        _ => None,
+    }
+}
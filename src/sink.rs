@@ -0,0 +1,67 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use zeroize::Zeroizing;
+use crate::auth::unwrap_token;
+use crate::exit_code::ValidationError;
+use crate::secret::SecretToken;
+use crate::vault::VaultClient;
+
+/// The token a Vault Agent file sink holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SinkToken {
+    /// A client token, written as plain text
+    Plain(SecretToken),
+    /// The wrapping token from the JSON written when the sink sets `wrap_ttl`
+    Wrapped(SecretToken),
+}
+
+impl SinkToken {
+    /// The client token, unwrapping it through Vault when the sink is response-wrapped
+    pub fn resolve(self, client: Option<&VaultClient>) -> Result<SecretToken> {
+        match self {
+            SinkToken::Plain(token) => Ok(token),
+            SinkToken::Wrapped(wrapping_token) => {
+                let client = client.ok_or_else(|| ValidationError::new(
+                    "The sink holds a response-wrapped token; set VAULT_ADDR or --vault-addr to unwrap it"
+                ))?;
+                Ok(unwrap_token(client, &wrapping_token)?.token)
+            }
+        }
+    }
+}
+
+/// Reads a Vault Agent file sink, telling plain tokens from response-wrapped ones
+pub fn read_sink<P: AsRef<Path>>(path: P) -> Result<SinkToken> {
+    let path = path.as_ref();
+    let content = Zeroizing::new(fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read sink {}: {}", path.display(), e))?);
+    let content = content.trim();
+    if content.is_empty() {
+        return Err(anyhow!("Sink {} is empty", path.display()));
+    }
+    if !content.starts_with('{') {
+        return Ok(SinkToken::Plain(SecretToken::new(content.lines().next().unwrap_or_default().trim())));
+    }
+
+    match serde_json::from_str::<Value>(content) {
+        Ok(Value::Object(mut info)) => match info.remove("token") {
+            Some(Value::String(token)) if !token.is_empty() => Ok(SinkToken::Wrapped(SecretToken::new(token))),
+            _ => Err(ValidationError::new(format!("Sink {} holds JSON without a token", path.display())).into()),
+        },
+        _ => Err(ValidationError::new(format!("Sink {} holds malformed JSON", path.display())).into()),
+    }
+}
+
+/// Of `sinks`, the one written most recently; sinks that do not exist yet are ignored
+pub fn latest_sink<'a, I>(sinks: I) -> Option<&'a PathBuf>
+where
+    I: IntoIterator<Item = &'a PathBuf>,
+{
+    sinks
+        .into_iter()
+        .filter_map(|sink| Some((fs::metadata(sink).ok()?.modified().ok()?, sink)))
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, sink)| sink)
+}
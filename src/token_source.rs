@@ -0,0 +1,126 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+use anyhow::{Context, Result};
+use zeroize::Zeroizing;
+use crate::auth::unwrap_token;
+use crate::exit_code::ValidationError;
+use crate::secret::SecretToken;
+use crate::vault::VaultClient;
+use crate::vault_cli::VaultCliConfig;
+
+/// Where the new Vault token comes from
+#[derive(Clone, PartialEq, Eq)]
+pub enum TokenSource {
+    /// Given directly on the command line (visible to other users via `ps`)
+    Argument(SecretToken),
+    /// First line of a file
+    File(PathBuf),
+    /// First line of standard input
+    Stdin,
+    /// Value of an environment variable
+    Env(String),
+    /// The token the `vault` CLI is currently logged in with
+    VaultCli,
+    /// Interactive prompt, hidden when reading from a terminal
+    Prompt { confirm: bool },
+}
+
+impl TokenSource {
+    /// Reads the token from this source, rejecting empty values
+    pub fn read(&self) -> Result<SecretToken> {
+        let token = match self {
+            TokenSource::Argument(token) => token.clone(),
+            TokenSource::File(path) => {
+                let content = Zeroizing::new(fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read token file {}", path.display()))?);
+                first_line(&content)
+            }
+            TokenSource::Stdin => read_token_from(io::stdin().lock())?,
+            TokenSource::Env(var) => {
+                let value = Zeroizing::new(env::var(var)
+                    .map_err(|_| ValidationError::new(format!("Environment variable {} is not set", var)))?);
+                first_line(&value)
+            }
+            TokenSource::VaultCli => VaultCliConfig::from_env()?.lookup()?,
+            TokenSource::Prompt { confirm } => prompt_token(*confirm)?,
+        };
+
+        if token.is_empty() {
+            return Err(ValidationError::new(format!("Token cannot be empty (read from {})", self)).into());
+        }
+        Ok(token)
+    }
+
+    /// Reads a response-wrapping token from this source and unwraps it.
+    ///
+    /// The wrapping token is kept in memory only and never written anywhere.
+    pub fn read_unwrapped(&self, client: &VaultClient) -> Result<SecretToken> {
+        let wrapping_token = self.read()?;
+        Ok(unwrap_token(client, &wrapping_token)?.token)
+    }
+}
+
+impl fmt::Display for TokenSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenSource::Argument(_) => f.write_str("command-line argument"),
+            TokenSource::File(path) => write!(f, "file {}", path.display()),
+            TokenSource::Stdin => f.write_str("stdin"),
+            TokenSource::Env(var) => write!(f, "environment variable {}", var),
+            TokenSource::VaultCli => f.write_str("vault CLI login"),
+            TokenSource::Prompt { .. } => f.write_str("prompt"),
+        }
+    }
+}
+
+impl fmt::Debug for TokenSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TokenSource({})", self)
+    }
+}
+
+/// Reads a token from the first line of a reader
+pub fn read_token_from<R: BufRead>(mut reader: R) -> Result<SecretToken> {
+    let mut input = Zeroizing::new(String::new());
+    reader.read_line(&mut input)?;
+    Ok(first_line(&input))
+}
+
+/// Prompts for the token without echo when stdin is a terminal.
+///
+/// Without a terminal the token is read from the first line of stdin instead,
+/// so the prompt still works when input is piped.
+pub fn prompt_token(confirm: bool) -> Result<SecretToken> {
+    let token = prompt_secret("Enter Vault token: ")?;
+    if confirm && io::stdin().is_terminal() && !token.is_empty() {
+        let again = read_hidden("Confirm Vault token: ")?;
+        if again != token {
+            return Err(ValidationError::new("Tokens do not match").into());
+        }
+    }
+    Ok(token)
+}
+
+/// Prompts for a secret without echo, or reads it from piped stdin
+pub fn prompt_secret(prompt: &str) -> Result<SecretToken> {
+    if io::stdin().is_terminal() {
+        read_hidden(prompt)
+    } else {
+        // Prompt on stderr so stdout stays clean for machine-readable output
+        eprint!("{}", prompt);
+        io::stderr().flush()?;
+        read_token_from(io::stdin().lock())
+    }
+}
+
+fn read_hidden(prompt: &str) -> Result<SecretToken> {
+    let input = Zeroizing::new(rpassword::prompt_password(prompt)?);
+    Ok(first_line(&input))
+}
+
+fn first_line(content: &str) -> SecretToken {
+    SecretToken::new(content.lines().next().unwrap_or("").trim())
+}
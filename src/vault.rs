@@ -0,0 +1,245 @@
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ureq::http::{Method, Request};
+use ureq::tls::{parse_pem, PemItem, RootCerts, TlsConfig};
+use ureq::Agent;
+use crate::exit_code::ValidationError;
+use crate::secret::SecretToken;
+
+/// Connection settings for a Vault server, mirroring the vault CLI's environment variables
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VaultConfig {
+    /// Server address, e.g. `https://vault.example.com:8200` (`VAULT_ADDR`)
+    pub address: String,
+    /// Enterprise namespace sent as `X-Vault-Namespace` (`VAULT_NAMESPACE`)
+    pub namespace: Option<String>,
+    /// PEM bundle of CA certificates to trust instead of the built-in roots (`VAULT_CACERT`)
+    pub ca_cert: Option<PathBuf>,
+    /// Disable TLS certificate verification (`VAULT_SKIP_VERIFY`)
+    pub skip_verify: bool,
+    /// Timeout for each request
+    pub timeout: Duration,
+}
+
+impl VaultConfig {
+    pub fn new<S: Into<String>>(address: S) -> Self {
+        Self {
+            address: address.into(),
+            namespace: None,
+            ca_cert: None,
+            skip_verify: false,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Error response returned by the Vault API
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VaultError {
+    pub status: u16,
+    pub errors: Vec<String>,
+}
+
+impl VaultError {
+    /// Whether Vault rejected the token used for the request
+    pub fn is_permission_denied(&self) -> bool {
+        self.status == 403
+    }
+}
+
+impl fmt::Display for VaultError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.errors.is_empty() {
+            write!(f, "Vault returned HTTP {}", self.status)
+        } else {
+            write!(f, "Vault returned HTTP {}: {}", self.status, self.errors.join("; "))
+        }
+    }
+}
+
+impl std::error::Error for VaultError {}
+
+/// Properties of a token as reported by `auth/token/lookup-self`.
+///
+/// The token ID itself is deliberately not captured.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TokenInfo {
+    #[serde(default)]
+    pub display_name: String,
+    #[serde(default)]
+    pub policies: Vec<String>,
+    /// Remaining lifetime in seconds; 0 for tokens that never expire
+    #[serde(default)]
+    pub ttl: u64,
+    #[serde(default)]
+    pub expire_time: Option<String>,
+    #[serde(default)]
+    pub renewable: bool,
+}
+
+impl TokenInfo {
+    /// Whether the token has no expiry (e.g. root tokens)
+    pub fn never_expires(&self) -> bool {
+        self.ttl == 0 && self.expire_time.is_none()
+    }
+
+    /// One-line human-readable description: policies and expiry
+    pub fn summary(&self) -> String {
+        let expiry = if self.never_expires() {
+            "never expires".to_string()
+        } else {
+            format!(
+                "expires {} (TTL {})",
+                self.expire_time.as_deref().unwrap_or("unknown"),
+                humantime::format_duration(Duration::from_secs(self.ttl))
+            )
+        };
+        format!("policies [{}], {}", self.policies.join(", "), expiry)
+    }
+}
+
+/// Minimal blocking client for the Vault HTTP API
+pub struct VaultClient {
+    config: VaultConfig,
+    agent: Agent,
+}
+
+impl VaultClient {
+    pub fn new(config: VaultConfig) -> Result<Self> {
+        let mut tls = TlsConfig::builder().disable_verification(config.skip_verify);
+        if let Some(path) = &config.ca_cert {
+            tls = tls.root_certs(load_ca_bundle(path)?);
+        }
+
+        let agent = Agent::config_builder()
+            .http_status_as_error(false)
+            .timeout_global(Some(config.timeout))
+            .tls_config(tls.build())
+            .build()
+            .new_agent();
+
+        Ok(Self { config, agent })
+    }
+
+    pub fn config(&self) -> &VaultConfig {
+        &self.config
+    }
+
+    /// Looks up the properties of `token`
+    pub fn lookup_self(&self, token: &SecretToken) -> Result<TokenInfo> {
+        let response = self.request(Method::GET, "auth/token/lookup-self", Some(token), None)?;
+        parse_data(response)
+    }
+
+    /// Revokes `token` using the token itself, which the default policy allows
+    pub fn revoke_self(&self, token: &SecretToken) -> Result<()> {
+        self.request(Method::POST, "auth/token/revoke-self", Some(token), None)?;
+        Ok(())
+    }
+
+    /// Sends a request to `/v1/<path>` and returns the decoded JSON response.
+    ///
+    /// Non-2xx responses become a [`VaultError`]; an empty body becomes `Value::Null`.
+    pub fn request(
+        &self,
+        method: Method,
+        path: &str,
+        token: Option<&SecretToken>,
+        body: Option<&Value>,
+    ) -> Result<Value> {
+        let url = format!("{}/v1/{}", self.config.address.trim_end_matches('/'), path.trim_start_matches('/'));
+        let mut builder = Request::builder().method(method).uri(&url);
+        if let Some(token) = token {
+            builder = builder.header("X-Vault-Token", token.expose());
+        }
+        if let Some(namespace) = &self.config.namespace {
+            builder = builder.header("X-Vault-Namespace", namespace);
+        }
+
+        let payload = match body {
+            Some(body) => {
+                builder = builder.header("Content-Type", "application/json");
+                serde_json::to_vec(body)?
+            }
+            None => Vec::new(),
+        };
+        let request = builder.body(payload)?;
+
+        let mut response = self.agent
+            .run(request)
+            .with_context(|| format!("Request to {} failed", url))?;
+        let status = response.status().as_u16();
+        let text = response.body_mut().read_to_string();
+
+        if !(200..300).contains(&status) {
+            // A proxy in front of Vault may answer with an HTML page, so the body is optional here
+            let errors = text.ok()
+                .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+                .and_then(|value| value.get("errors").and_then(Value::as_array).cloned())
+                .map(|errors| errors.iter().filter_map(Value::as_str).map(str::to_string).collect())
+                .unwrap_or_default();
+            return Err(VaultError { status, errors }.into());
+        }
+
+        let text = text?;
+        if text.trim().is_empty() {
+            return Ok(Value::Null);
+        }
+        serde_json::from_str(&text).with_context(|| format!("Invalid JSON in the response from {}", url))
+    }
+}
+
+/// Deserializes the `data` object of a Vault response
+fn parse_data<T: for<'de> Deserialize<'de>>(response: Value) -> Result<T> {
+    let data = response.get("data").cloned()
+        .ok_or_else(|| anyhow::anyhow!("Vault response has no data"))?;
+    Ok(serde_json::from_value(data)?)
+}
+
+fn load_ca_bundle(path: &PathBuf) -> Result<RootCerts> {
+    let pem = fs::read(path).with_context(|| format!("Failed to read CA bundle {}", path.display()))?;
+    let mut certs = Vec::new();
+    for item in parse_pem(&pem) {
+        if let PemItem::Certificate(cert) = item? {
+            certs.push(cert);
+        }
+    }
+    if certs.is_empty() {
+        return Err(anyhow::anyhow!("No certificates found in CA bundle {}", path.display()));
+    }
+    Ok(RootCerts::new_with_certs(&certs))
+}
+
+/// Checks a token against Vault before it is written anywhere.
+///
+/// Fails with a [`ValidationError`] when Vault rejects the token or when it expires
+/// sooner than `min_ttl`.
+pub fn validate_token(client: &VaultClient, token: &SecretToken, min_ttl: Duration) -> Result<TokenInfo> {
+    let info = match client.lookup_self(token) {
+        Ok(info) => info,
+        Err(e) => match e.downcast_ref::<VaultError>() {
+            Some(vault_error) if vault_error.is_permission_denied() => {
+                return Err(ValidationError::new(format!(
+                    "Token {} was rejected by Vault (invalid or expired)", token.fingerprint()
+                )).into());
+            }
+            _ => return Err(e.context("Token lookup failed")),
+        },
+    };
+
+    if !info.never_expires() && Duration::from_secs(info.ttl) < min_ttl {
+        return Err(ValidationError::new(format!(
+            "Token {} expires in {}, less than the required {}",
+            token.fingerprint(),
+            humantime::format_duration(Duration::from_secs(info.ttl)),
+            humantime::format_duration(min_ttl)
+        )).into());
+    }
+
+    Ok(info)
+}
@@ -0,0 +1,108 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use anyhow::{Context, Result};
+use regex::Regex;
+use zeroize::Zeroizing;
+use crate::exit_code::ValidationError;
+use crate::secret::SecretToken;
+
+/// Where the `vault` CLI keeps its login state, resolved the way the CLI does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VaultCliConfig {
+    /// Value of `VAULT_TOKEN`, which takes precedence over everything else
+    pub env_token: Option<SecretToken>,
+    /// CLI configuration file (`VAULT_CONFIG_PATH`, default `~/.vault`)
+    pub config_path: PathBuf,
+    /// Token file used by the built-in token helper (`~/.vault-token`)
+    pub token_path: PathBuf,
+}
+
+impl VaultCliConfig {
+    /// Resolves the lookup locations from the environment
+    pub fn from_env() -> Result<Self> {
+        let home = home_dir()
+            .ok_or_else(|| anyhow::anyhow!("Cannot locate the home directory (HOME is not set)"))?;
+        let env_token = env::var("VAULT_TOKEN")
+            .ok()
+            .map(Zeroizing::new)
+            .filter(|token| !token.trim().is_empty())
+            .map(|token| SecretToken::new(token.trim()));
+        let config_path = env::var_os("VAULT_CONFIG_PATH")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join(".vault"));
+
+        Ok(Self {
+            env_token,
+            config_path,
+            token_path: home.join(".vault-token"),
+        })
+    }
+
+    /// Finds the current token in the order the vault CLI uses:
+    /// `VAULT_TOKEN`, then the configured `token_helper`, then `~/.vault-token`
+    pub fn lookup(&self) -> Result<SecretToken> {
+        if let Some(token) = &self.env_token {
+            return Ok(token.clone());
+        }
+
+        if let Some(helper) = self.token_helper()? {
+            return run_token_helper(&helper);
+        }
+
+        if self.token_path.exists() {
+            let content = Zeroizing::new(fs::read_to_string(&self.token_path)
+                .with_context(|| format!("Failed to read {}", self.token_path.display()))?);
+            return Ok(SecretToken::new(content.trim()));
+        }
+
+        Err(ValidationError::new(
+            "No Vault CLI token found: VAULT_TOKEN is unset, no token_helper is configured \
+             and ~/.vault-token does not exist (run `vault login` first)",
+        ).into())
+    }
+
+    /// The `token_helper` configured in the CLI configuration file, if any
+    pub fn token_helper(&self) -> Result<Option<PathBuf>> {
+        if !self.config_path.is_file() {
+            return Ok(None);
+        }
+        let config = fs::read_to_string(&self.config_path)
+            .with_context(|| format!("Failed to read {}", self.config_path.display()))?;
+        Ok(parse_token_helper(&config).map(PathBuf::from))
+    }
+}
+
+/// Extracts the `token_helper` setting from a vault CLI configuration file (HCL)
+pub fn parse_token_helper(config: &str) -> Option<String> {
+    let re = Regex::new(r#"(?m)^\s*token_helper\s*=\s*"([^"]*)""#).ok()?;
+    re.captures(config)
+        .map(|captures| captures[1].to_string())
+        .filter(|helper| !helper.is_empty())
+}
+
+/// Runs `<helper> get` and returns the token it prints
+fn run_token_helper(helper: &Path) -> Result<SecretToken> {
+    let output = Command::new(helper)
+        .arg("get")
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .with_context(|| format!("Failed to run token helper {}", helper.display()))?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("Token helper {} exited with {}", helper.display(), output.status));
+    }
+
+    let stdout = Zeroizing::new(String::from_utf8(output.stdout)
+        .context("Token helper printed invalid UTF-8")?);
+    Ok(SecretToken::new(stdout.trim()))
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
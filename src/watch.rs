@@ -0,0 +1,171 @@
+use std::collections::BTreeSet;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use walkdir::WalkDir;
+use crate::file_scanner::{is_config_file, FileFilter};
+
+/// How often the shutdown flag is checked while no events arrive
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What to watch: config files below the roots, and the files tokens are read from
+#[derive(Debug, Clone, Default)]
+pub struct WatchTargets {
+    pub roots: Vec<PathBuf>,
+    pub filter: FileFilter,
+    /// Token files or Vault Agent sinks
+    pub token_files: Vec<PathBuf>,
+    /// Token file changes are reported at most once per interval; later ones wait for it to pass
+    pub min_token_interval: Duration,
+}
+
+/// The changes seen during one debounced batch of file system events
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WatchBatch {
+    /// Config files created or modified below a root, named as discovery would name them
+    pub configs: BTreeSet<PathBuf>,
+    /// Token files that were written or replaced, named as in [`WatchTargets::token_files`]
+    pub token_files: BTreeSet<PathBuf>,
+}
+
+impl WatchBatch {
+    pub fn is_empty(&self) -> bool {
+        self.configs.is_empty() && self.token_files.is_empty()
+    }
+}
+
+/// A watched token file, with the resolved path events are reported under
+struct WatchedTokenFile {
+    path: PathBuf,
+    resolved: PathBuf,
+}
+
+/// A watched root, with the resolved path that event paths are reported under
+struct WatchedRoot {
+    path: PathBuf,
+    resolved: PathBuf,
+}
+
+/// Watches `targets` until `shutdown` is set, calling `on_batch` once the events have been
+/// quiet for `debounce`.
+///
+/// An error from `on_batch` stops the watch; callers that want to keep going should log it instead.
+pub fn watch_changes<F>(targets: &WatchTargets, debounce: Duration, shutdown: &AtomicBool, mut on_batch: F) -> Result<()>
+where
+    F: FnMut(WatchBatch) -> Result<()>,
+{
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+
+    let mut roots = Vec::new();
+    for path in &targets.roots {
+        let resolved = path.canonicalize()
+            .map_err(|e| anyhow!("Cannot watch {}: {}", path.display(), e))?;
+        watcher.watch(&resolved, RecursiveMode::Recursive)
+            .map_err(|e| anyhow!("Cannot watch {}: {}", path.display(), e))?;
+        roots.push(WatchedRoot { path: path.clone(), resolved });
+    }
+    // Sinks are usually replaced by a rename, so the directory is watched rather than the file
+    let mut token_files = Vec::new();
+    for path in &targets.token_files {
+        let directory = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let directory = directory.canonicalize()
+            .map_err(|e| anyhow!("Cannot watch {}: {}", directory.display(), e))?;
+        watcher.watch(&directory, RecursiveMode::NonRecursive)
+            .map_err(|e| anyhow!("Cannot watch {}: {}", directory.display(), e))?;
+        if let Some(name) = path.file_name() {
+            token_files.push(WatchedTokenFile { path: path.clone(), resolved: directory.join(name) });
+        }
+    }
+
+    let mut pending = WatchBatch::default();
+    // Files written into a new directory may land before its watch does, so it is walked before flushing
+    let mut new_directories = BTreeSet::new();
+    let mut last_event: Option<Instant> = None;
+    // Token file changes held back by `min_token_interval`
+    let mut held_token_files = BTreeSet::new();
+    let mut last_token_batch: Option<Instant> = None;
+    while !shutdown.load(Ordering::SeqCst) {
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => {
+                if collect_event(&event, &roots, &targets.filter, &token_files, &mut pending, &mut new_directories) {
+                    last_event = Some(Instant::now());
+                }
+            }
+            Ok(Err(e)) => return Err(anyhow!("Watch failed: {}", e)),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let mut batch = WatchBatch::default();
+        if last_event.is_some_and(|at| at.elapsed() >= debounce) {
+            last_event = None;
+            for directory in mem::take(&mut new_directories) {
+                let files = WalkDir::new(&directory).into_iter().filter_map(|entry| entry.ok()).map(|entry| entry.into_path());
+                for file in files {
+                    collect_config(&file, &roots, &targets.filter, &mut pending);
+                }
+            }
+            batch = mem::take(&mut pending);
+            held_token_files.append(&mut batch.token_files);
+        }
+        if !held_token_files.is_empty()
+            && last_token_batch.is_none_or(|at| at.elapsed() >= targets.min_token_interval)
+        {
+            batch.token_files = mem::take(&mut held_token_files);
+            last_token_batch = Some(Instant::now());
+        }
+        if !batch.is_empty() {
+            on_batch(batch)?;
+        }
+    }
+    Ok(())
+}
+
+/// Adds the paths of an event to `batch` (or to `new_directories`), returning whether it was relevant
+fn collect_event(
+    event: &Event,
+    roots: &[WatchedRoot],
+    filter: &FileFilter,
+    token_files: &[WatchedTokenFile],
+    batch: &mut WatchBatch,
+    new_directories: &mut BTreeSet<PathBuf>,
+) -> bool {
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Any) {
+        return false;
+    }
+    let mut relevant = false;
+    for path in &event.paths {
+        if let Some(token_file) = token_files.iter().find(|token_file| token_file.resolved == *path) {
+            batch.token_files.insert(token_file.path.clone());
+            relevant = true;
+        } else if path.is_dir() {
+            let added = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_)));
+            if added && roots.iter().any(|root| path.starts_with(&root.resolved)) {
+                new_directories.insert(path.clone());
+                relevant = true;
+            }
+        } else {
+            relevant |= collect_config(path, roots, filter, batch);
+        }
+    }
+    relevant
+}
+
+/// Adds `path` to `batch` if it is a config file below a root that the filter accepts
+fn collect_config(path: &Path, roots: &[WatchedRoot], filter: &FileFilter, batch: &mut WatchBatch) -> bool {
+    let Some(root) = roots.iter().find(|root| path.starts_with(&root.resolved)) else {
+        return false;
+    };
+    let relative = path.strip_prefix(&root.resolved).unwrap_or(path);
+    if path.is_file() && is_config_file(path) && filter.matches(relative) {
+        batch.configs.insert(root.path.join(relative));
+        return true;
+    }
+    false
+}
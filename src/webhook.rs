@@ -0,0 +1,159 @@
+use std::io::Read;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tiny_http::{Header, Method, Response};
+use zeroize::Zeroizing;
+use crate::exit_code::ValidationError;
+use crate::secret::SecretToken;
+use crate::serve::{summarize, Request, RotateRequest, Server};
+
+/// Header carrying `sha256=<hex>`, the HMAC-SHA256 of `<timestamp>.<body>` keyed with the shared secret
+pub const SIGNATURE_HEADER: &str = "X-Vcu-Signature";
+
+/// Header carrying the Unix time, in seconds, at which the request was signed
+pub const TIMESTAMP_HEADER: &str = "X-Vcu-Timestamp";
+
+/// Signed requests older (or newer) than this are rejected, so a captured request cannot be replayed later
+pub const MAX_CLOCK_SKEW: Duration = Duration::from_secs(300);
+
+/// Larger request bodies are rejected unread
+const MAX_BODY_BYTES: u64 = 64 * 1024;
+
+/// How often the accept loop checks the shutdown flag
+const RECV_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Signs a webhook body, returning the value of [`SIGNATURE_HEADER`]
+pub fn sign_webhook(secret: &[u8], timestamp: u64, body: &[u8]) -> String {
+    let digest = webhook_mac(secret, timestamp, body).finalize().into_bytes();
+    format!("sha256={}", digest.iter().map(|byte| format!("{:02x}", byte)).collect::<String>())
+}
+
+/// Checks the signature headers of a webhook request against `secret`, comparing in constant time
+pub fn verify_webhook(
+    secret: &[u8],
+    timestamp: Option<&str>,
+    signature: Option<&str>,
+    body: &[u8],
+    now: SystemTime,
+) -> Result<()> {
+    let timestamp = timestamp.ok_or_else(|| anyhow!("Missing {} header", TIMESTAMP_HEADER))?;
+    let signature = signature.ok_or_else(|| anyhow!("Missing {} header", SIGNATURE_HEADER))?;
+    let timestamp: u64 = timestamp.trim().parse()
+        .map_err(|_| anyhow!("Invalid {} header", TIMESTAMP_HEADER))?;
+    let now = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    if now.abs_diff(timestamp) > MAX_CLOCK_SKEW.as_secs() {
+        return Err(anyhow!("The request timestamp is outside the allowed window of {}s", MAX_CLOCK_SKEW.as_secs()));
+    }
+    let digest = signature.trim().strip_prefix("sha256=").and_then(decode_hex)
+        .ok_or_else(|| anyhow!("Invalid {} header", SIGNATURE_HEADER))?;
+    webhook_mac(secret, timestamp, body).verify_slice(&digest)
+        .map_err(|_| anyhow!("The request signature does not match"))
+}
+
+fn webhook_mac(secret: &[u8], timestamp: u64, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok()).collect()
+}
+
+/// An HTTP listener for signed rotation webhooks: `POST /rotate` with a `{"token": ..., "wrapped": ...}` body.
+///
+/// A successful request is answered with the JSON report of the rotation.
+pub struct WebhookListener {
+    http: tiny_http::Server,
+}
+
+impl WebhookListener {
+    /// Binds `address`; port `0` picks a free port
+    pub fn bind(address: SocketAddr) -> Result<Self> {
+        let http = tiny_http::Server::http(address)
+            .map_err(|e| anyhow!("Cannot listen on {}: {}", address, e))?;
+        Ok(Self { http })
+    }
+
+    /// The address the listener is bound to
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.http.server_addr().to_ip()
+    }
+
+    /// Answers requests until `shutdown` is set; `log` receives a line for each request
+    pub fn serve<L>(&self, server: &Server, secret: &SecretToken, shutdown: &AtomicBool, log: L) -> Result<()>
+    where
+        L: Fn(&str),
+    {
+        while !shutdown.load(Ordering::SeqCst) {
+            if let Some(mut request) = self.http.recv_timeout(RECV_INTERVAL)? {
+                let (status, body) = handle_request(server, secret, &mut request, &log);
+                let response = Response::from_string(body.to_string())
+                    .with_status_code(status)
+                    .with_header(Header::from_bytes("Content-Type", "application/json").expect("the header is valid"));
+                // The client may have gone away; the rotation itself already happened
+                let _ = request.respond(response);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn handle_request<L: Fn(&str)>(server: &Server, secret: &SecretToken, request: &mut tiny_http::Request, log: &L) -> (u16, Value) {
+    let error = |status: u16, message: String| (status, json!({ "error": message }));
+    let remote = request.remote_addr().map_or_else(|| "unknown client".to_string(), ToString::to_string);
+    if request.url() != "/rotate" {
+        return error(404, format!("Unknown path {}", request.url()));
+    }
+    if *request.method() != Method::Post {
+        return error(405, "Use POST".to_string());
+    }
+    if request.body_length().is_some_and(|length| length as u64 > MAX_BODY_BYTES) {
+        return error(413, format!("The body exceeds {} bytes", MAX_BODY_BYTES));
+    }
+    // The body carries the token
+    let mut body = Zeroizing::new(Vec::new());
+    if request.as_reader().take(MAX_BODY_BYTES + 1).read_to_end(&mut body).is_err() {
+        return error(400, "Cannot read the request body".to_string());
+    }
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return error(413, format!("The body exceeds {} bytes", MAX_BODY_BYTES));
+    }
+
+    let header = |name: &str| {
+        request.headers().iter()
+            .find(|header| header.field.as_str().as_str().eq_ignore_ascii_case(name))
+            .map(|header| header.value.as_str())
+    };
+    let verified = verify_webhook(
+        secret.expose().as_bytes(), header(TIMESTAMP_HEADER), header(SIGNATURE_HEADER), &body, SystemTime::now(),
+    );
+    if let Err(e) = verified {
+        log(&format!("webhook from {} rejected: {}", remote, e));
+        return error(401, e.to_string());
+    }
+
+    let rotate: RotateRequest = match serde_json::from_slice(&body) {
+        Ok(rotate) => rotate,
+        Err(e) => return error(400, format!("Invalid request: {}", e)),
+    };
+    let result = server.handle(Request::Rotate(rotate));
+    if let Some(message) = summarize("rotate", &result) {
+        log(&format!("webhook from {}: {}", remote, message));
+    }
+    match result {
+        Ok(report) => (200, report),
+        Err(e) if e.downcast_ref::<ValidationError>().is_some() => error(400, e.to_string()),
+        Err(e) => error(500, e.to_string()),
+    }
+}
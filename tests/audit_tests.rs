@@ -0,0 +1,138 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tempfile::TempDir;
+use vault_config_updater::{
+    fingerprint, parse_since, read_history, update_files_parallel, AuditEntry, AuditLog, ExitCode, HistoryQuery,
+    Hooks, TokenFields,
+};
+
+fn write_config(root: &Path, relative: &str, content: &str) -> PathBuf {
+    let path = root.join(relative);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, content).unwrap();
+    path
+}
+
+fn entry(path: &str, old: &str, new: &str, run_id: &str, timestamp: &str) -> AuditEntry {
+    AuditEntry {
+        timestamp: timestamp.to_string(),
+        run_id: run_id.to_string(),
+        command: "update".to_string(),
+        user: "alice".to_string(),
+        host: "build-01".to_string(),
+        path: PathBuf::from(path),
+        old_fingerprints: vec![old.to_string()],
+        new_fingerprints: vec![new.to_string()],
+    }
+}
+
+fn write_log(path: &Path, entries: &[AuditEntry]) {
+    let lines: Vec<String> = entries.iter().map(|entry| serde_json::to_string(entry).unwrap()).collect();
+    fs::write(path, lines.join("\n") + "\n").unwrap();
+}
+
+#[test]
+fn test_records_one_entry_per_written_file() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path();
+    let api = write_config(root, "api/config.json", r#"{"vaultToken": "hvs.old", "backup": {"vaultToken": "hvs.older"}}"#);
+    write_config(root, "web/config.json", r#"{"vaultToken": "hvs.new"}"#);
+    write_config(root, "docs/config.json", r#"{"name": "docs"}"#);
+    let files = vec![api.clone(), root.join("web/config.json"), root.join("docs/config.json")];
+
+    let log_path = root.join("audit/log.jsonl");
+    let log = AuditLog::open(&log_path, "update").unwrap();
+    let mut stats = update_files_parallel(&files, "hvs.new", &TokenFields::default(), &Hooks::default(), |_| {});
+    log.record_stats(&mut stats);
+    assert!(stats.errors.is_empty(), "{:?}", stats.errors);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(fs::metadata(&log_path).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+
+    let history = read_history(&log_path, &HistoryQuery::default()).unwrap();
+    assert_eq!(history.entries_read, 1);
+    let recorded = &history.entries[0];
+    assert_eq!(recorded.path, api);
+    assert_eq!(recorded.command, "update");
+    assert_eq!(recorded.run_id, log.run_id());
+    assert_eq!(recorded.old_fingerprints, vec![fingerprint("hvs.old"), fingerprint("hvs.older")]);
+    assert_eq!(recorded.new_fingerprints, vec![fingerprint("hvs.new")]);
+    assert!(recorded.time().is_some());
+    assert!(!fs::read_to_string(&log_path).unwrap().contains("hvs."));
+
+    // A second run appends under its own run id
+    let log = AuditLog::open(&log_path, "watch").unwrap();
+    let mut stats = update_files_parallel(&files, "hvs.newer", &TokenFields::default(), &Hooks::default(), |_| {});
+    log.record_stats(&mut stats);
+    let history = read_history(&log_path, &HistoryQuery::default()).unwrap();
+    assert_eq!(history.entries_read, 3);
+    assert_eq!(history.entries[1].command, "watch");
+    assert_ne!(history.entries[1].run_id, history.entries[0].run_id);
+}
+
+#[test]
+fn test_queries_the_log() {
+    let dir = TempDir::new().unwrap();
+    let log_path = dir.path().join("audit.jsonl");
+    write_log(&log_path, &[
+        entry("/srv/api/config.json", "aaaaaaaaaaaa", "bbbbbbbbbbbb", "run1", "2026-10-01T10:00:00Z"),
+        entry("/srv/web/config.json", "aaaaaaaaaaaa", "bbbbbbbbbbbb", "run1", "2026-10-01T10:00:00Z"),
+        entry("/srv/api/config.json", "bbbbbbbbbbbb", "cccccccccccc", "run2", "2026-10-15T10:00:00Z"),
+        entry("/srv/apis/config.json", "bbbbbbbbbbbb", "cccccccccccc", "run2", "2026-10-15T10:00:00Z"),
+    ]);
+    let paths = |query: HistoryQuery| -> Vec<String> {
+        read_history(&log_path, &query).unwrap().entries.iter()
+            .map(|entry| format!("{} {}", entry.run_id, entry.path.display()))
+            .collect()
+    };
+
+    assert_eq!(paths(HistoryQuery { paths: vec!["/srv/api".into()], ..HistoryQuery::default() }),
+        vec!["run1 /srv/api/config.json", "run2 /srv/api/config.json"]);
+    assert_eq!(paths(HistoryQuery { fingerprint: Some("cccccccccccc".into()), ..HistoryQuery::default() }),
+        vec!["run2 /srv/api/config.json", "run2 /srv/apis/config.json"]);
+    assert_eq!(paths(HistoryQuery { fingerprint: Some("bbbbbbbbbbbb".into()), ..HistoryQuery::default() }).len(), 4);
+    assert_eq!(paths(HistoryQuery { run_id: Some("run1".into()), ..HistoryQuery::default() }).len(), 2);
+    let since = humantime::parse_rfc3339("2026-10-10T00:00:00Z").unwrap();
+    assert_eq!(paths(HistoryQuery { since: Some(since), ..HistoryQuery::default() }).len(), 2);
+
+    // The limit keeps the most recent entries but still counts every match
+    let limited = read_history(&log_path, &HistoryQuery { limit: Some(1), ..HistoryQuery::default() }).unwrap();
+    assert_eq!(limited.entries_matched, 4);
+    assert_eq!(limited.entries.len(), 1);
+    assert_eq!(limited.entries[0].path, Path::new("/srv/apis/config.json"));
+    assert_eq!(limited.exit_code(), ExitCode::Success);
+
+    let none = read_history(&log_path, &HistoryQuery { run_id: Some("run3".into()), ..HistoryQuery::default() }).unwrap();
+    assert_eq!(none.exit_code(), ExitCode::NothingToDo);
+}
+
+#[test]
+fn test_reports_unreadable_lines() {
+    let dir = TempDir::new().unwrap();
+    let log_path = dir.path().join("audit.jsonl");
+    write_log(&log_path, &[entry("/srv/api/config.json", "aaaaaaaaaaaa", "bbbbbbbbbbbb", "run1", "2026-10-01T10:00:00Z")]);
+    let mut content = fs::read_to_string(&log_path).unwrap();
+    content.push_str("\n{\"truncated\n");
+    fs::write(&log_path, content).unwrap();
+
+    let stats = read_history(&log_path, &HistoryQuery::default()).unwrap();
+    assert_eq!(stats.entries_read, 1);
+    assert_eq!(stats.errors.len(), 1);
+    assert!(stats.errors[0].contains("audit.jsonl:3"), "{}", stats.errors[0]);
+    assert_eq!(stats.exit_code(), ExitCode::PartialFailure);
+
+    let error = read_history(dir.path().join("missing.jsonl"), &HistoryQuery::default()).unwrap_err();
+    assert!(error.to_string().contains("Cannot read the audit log"), "{}", error);
+}
+
+#[test]
+fn test_parses_since() {
+    assert_eq!(parse_since("2026-10-01T00:00:00Z").unwrap(), humantime::parse_rfc3339("2026-10-01T00:00:00Z").unwrap());
+    let week_ago = parse_since("7d").unwrap();
+    let expected = SystemTime::now() - Duration::from_secs(7 * 24 * 3600);
+    assert!(expected.duration_since(week_ago).unwrap_or_default() < Duration::from_secs(5));
+    assert!(parse_since("last tuesday").is_err());
+}
@@ -0,0 +1,200 @@
+mod common;
+
+use std::time::Duration;
+use common::{error_response, MockVault};
+use serde_json::json;
+use vault_config_updater::{
+    login, unwrap_token, LoginCredentials, LoginMethod, SecretToken, TokenCreateRequest, ValidationError,
+    VaultClient, VaultConfig, VaultError
+};
+
+fn auth_response(token: &str) -> String {
+    json!({
+        "auth": {
+            "client_token": token,
+            "accessor": "accessor-1",
+            "policies": ["default", "app"],
+            "lease_duration": 3600,
+            "renewable": true
+        }
+    }).to_string()
+}
+
+fn mock_vault() -> MockVault {
+    MockVault::start(|request| match request.path.as_str() {
+        "/v1/auth/approle/login" if request.json()["secret_id"] == "secret-1" => (200, auth_response("hvs.from-approle")),
+        "/v1/auth/ci-approle/login" => (200, auth_response("hvs.from-custom-mount")),
+        "/v1/auth/userpass/login/alice" if request.json()["password"] == "pw" => (200, auth_response("hvs.from-userpass")),
+        "/v1/auth/token/create" | "/v1/auth/token/create/deployer" if request.token.as_deref() == Some("hvs.parent") => {
+            (200, auth_response("hvs.child"))
+        }
+        _ => (400, error_response("invalid credentials")),
+    })
+}
+
+#[test]
+fn test_approle_login() {
+    let vault = mock_vault();
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+    let credentials = LoginCredentials::AppRole {
+        role_id: "role-1".to_string(),
+        secret_id: SecretToken::new("secret-1"),
+    };
+
+    let result = login(&client, &credentials, None).unwrap();
+    assert_eq!(result.token.expose(), "hvs.from-approle");
+    assert_eq!(result.policies, vec!["default", "app"]);
+    assert_eq!(result.lease_duration, 3600);
+
+    let request = &vault.requests()[0];
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.json()["role_id"], "role-1");
+    assert_eq!(request.token, None);
+}
+
+#[test]
+fn test_login_with_custom_mount() {
+    let vault = mock_vault();
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+    let credentials = LoginCredentials::AppRole {
+        role_id: "role-1".to_string(),
+        secret_id: SecretToken::new("secret-1"),
+    };
+
+    let result = login(&client, &credentials, Some("/ci-approle/")).unwrap();
+    assert_eq!(result.token.expose(), "hvs.from-custom-mount");
+}
+
+#[test]
+fn test_userpass_login() {
+    let vault = mock_vault();
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+    let credentials = LoginCredentials::Userpass {
+        username: "alice".to_string(),
+        password: SecretToken::new("pw"),
+    };
+
+    let result = login(&client, &credentials, None).unwrap();
+    assert_eq!(result.token.expose(), "hvs.from-userpass");
+}
+
+#[test]
+fn test_username_and_role_are_encoded_as_one_path_segment() {
+    let vault = mock_vault();
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+    let credentials = LoginCredentials::Userpass {
+        username: "alice/../../sys/seal?x#y".to_string(),
+        password: SecretToken::new("pw"),
+    };
+    assert!(login(&client, &credentials, None).is_err());
+    let credentials = LoginCredentials::Token {
+        parent: SecretToken::new("hvs.parent"),
+        request: TokenCreateRequest { role: Some("deployer/../x".to_string()), ..TokenCreateRequest::default() },
+    };
+    assert!(login(&client, &credentials, None).is_err());
+
+    let paths: Vec<String> = vault.requests().into_iter().map(|request| request.path).collect();
+    assert_eq!(paths, vec![
+        "/v1/auth/userpass/login/alice%2F..%2F..%2Fsys%2Fseal%3Fx%23y",
+        "/v1/auth/token/create/deployer%2F..%2Fx",
+    ]);
+}
+
+#[test]
+fn test_token_create_uses_parent_token() {
+    let vault = mock_vault();
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+    let credentials = LoginCredentials::Token {
+        parent: SecretToken::new("hvs.parent"),
+        request: TokenCreateRequest {
+            policies: vec!["app".to_string()],
+            ttl: Some(Duration::from_secs(7200)),
+            role: Some("deployer".to_string()),
+        },
+    };
+
+    let result = login(&client, &credentials, None).unwrap();
+    assert_eq!(result.token.expose(), "hvs.child");
+    assert!(!result.orphan);
+    assert_eq!(result.parent, Some(SecretToken::new("hvs.parent")));
+
+    let request = &vault.requests()[0];
+    assert_eq!(request.path, "/v1/auth/token/create/deployer");
+    assert_eq!(request.json()["policies"], json!(["app"]));
+    assert_eq!(request.json()["ttl"], "7200s");
+}
+
+#[test]
+fn test_orphan_token_has_no_parent_to_protect() {
+    let vault = MockVault::start(|_| {
+        let mut response: serde_json::Value = serde_json::from_str(&auth_response("hvs.orphan")).unwrap();
+        response["auth"]["orphan"] = json!(true);
+        (200, response.to_string())
+    });
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+    let credentials = LoginCredentials::Token {
+        parent: SecretToken::new("hvs.parent"),
+        request: TokenCreateRequest { role: Some("orphans".to_string()), ..TokenCreateRequest::default() },
+    };
+
+    let result = login(&client, &credentials, None).unwrap();
+    assert!(result.orphan);
+    assert_eq!(result.parent, None);
+}
+
+#[test]
+fn test_failed_login_reports_vault_error() {
+    let vault = mock_vault();
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+    let credentials = LoginCredentials::Userpass {
+        username: "alice".to_string(),
+        password: SecretToken::new("wrong"),
+    };
+
+    let error = login(&client, &credentials, None).unwrap_err();
+    let vault_error = error.downcast_ref::<VaultError>().unwrap();
+    assert_eq!(vault_error.status, 400);
+    assert!(!error.to_string().contains("wrong"));
+}
+
+#[test]
+fn test_login_result_debug_hides_token() {
+    let vault = mock_vault();
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+    let credentials = LoginCredentials::AppRole {
+        role_id: "role-1".to_string(),
+        secret_id: SecretToken::new("secret-1"),
+    };
+
+    let result = login(&client, &credentials, None).unwrap();
+    assert!(!format!("{:?}", result).contains("from-approle"));
+    assert!(!format!("{:?}", credentials).contains("secret-1"));
+    assert_eq!(credentials.method(), LoginMethod::AppRole);
+}
+
+#[test]
+fn test_unwrap_token_returns_wrapped_client_token() {
+    let vault = MockVault::start(|request| match (request.path.as_str(), request.token.as_deref()) {
+        ("/v1/sys/wrapping/unwrap", Some("hvs.wrapping")) => (200, auth_response("hvs.unwrapped")),
+        _ => (400, error_response("wrapping token is not valid or does not exist")),
+    });
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+
+    let result = unwrap_token(&client, &SecretToken::new("hvs.wrapping")).unwrap();
+    assert_eq!(result.token.expose(), "hvs.unwrapped");
+    assert_eq!(result.policies, vec!["default", "app"]);
+    assert_eq!(vault.requests()[0].method, "POST");
+
+    let error = unwrap_token(&client, &SecretToken::new("hvs.used")).unwrap_err();
+    assert!(error.downcast_ref::<ValidationError>().is_some());
+    assert!(!error.to_string().contains("hvs.used"));
+}
+
+#[test]
+fn test_unwrap_token_requires_auth_data() {
+    let vault = MockVault::start(|_| (200, json!({ "data": { "foo": "bar" } }).to_string()));
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+
+    let error = unwrap_token(&client, &SecretToken::new("hvs.wrapping")).unwrap_err();
+    assert!(error.to_string().contains("does not contain a client token"));
+}
@@ -1,5 +1,11 @@
 use clap::Parser;
-use vault_config_updater::{CliArgs, parse_args};
+use vault_config_updater::{
+    CliArgs, ColorChoice, Command, LoginCredentials, LoginMethod, OutputFormat, SecretToken, TokenSource, parse_args
+};
+
+fn token_of(cli: &CliArgs) -> Option<&str> {
+    cli.update.token.as_ref().map(SecretToken::expose)
+}
 
 #[test]
 fn test_parses_token_argument() {
@@ -8,7 +14,7 @@ fn test_parses_token_argument() {
 
     assert!(result.is_ok());
     let cli = result.unwrap();
-    assert_eq!(cli.token, Some("hvs.test-token".to_string()));
+    assert_eq!(token_of(&cli), Some("hvs.test-token"));
 }
 
 #[test]
@@ -18,7 +24,7 @@ fn test_parses_path_argument() {
 
     assert!(result.is_ok());
     let cli = result.unwrap();
-    assert_eq!(cli.path, Some("/some/path".to_string()));
+    assert_eq!(cli.update.selection.paths, vec![std::path::PathBuf::from("/some/path")]);
 }
 
 #[test]
@@ -28,7 +34,7 @@ fn test_default_current_directory() {
 
     assert!(result.is_ok());
     let cli = result.unwrap();
-    assert_eq!(cli.get_search_path(), std::path::Path::new("."));
+    assert_eq!(cli.update.get_search_path(), std::path::Path::new("."));
 }
 
 #[test]
@@ -38,7 +44,7 @@ fn test_custom_directory() {
 
     assert!(result.is_ok());
     let cli = result.unwrap();
-    assert_eq!(cli.get_search_path(), std::path::Path::new("/custom/path"));
+    assert_eq!(cli.update.get_search_path(), std::path::Path::new("/custom/path"));
 }
 
 #[test]
@@ -66,7 +72,7 @@ fn test_no_token_provided() {
 
     assert!(result.is_ok());
     let cli = result.unwrap();
-    assert_eq!(cli.token, None);
+    assert!(cli.update.token.is_none());
 }
 
 #[test]
@@ -76,7 +82,7 @@ fn test_parse_args_with_token() {
 
     assert!(result.is_ok());
     let cli = result.unwrap();
-    assert_eq!(cli.token, Some("hvs.my-token".to_string()));
+    assert_eq!(token_of(&cli), Some("hvs.my-token"));
 }
 
 #[test]
@@ -112,8 +118,8 @@ fn test_dry_run_flag() {
 
     assert!(result.is_ok());
     let cli = result.unwrap();
-    assert!(cli.dry_run);
-    assert_eq!(cli.token, None);
+    assert!(cli.update.dry_run);
+    assert!(cli.update.token.is_none());
 }
 
 #[test]
@@ -123,9 +129,9 @@ fn test_dry_run_with_verbose() {
 
     assert!(result.is_ok());
     let cli = result.unwrap();
-    assert!(cli.dry_run);
+    assert!(cli.update.dry_run);
     assert!(cli.verbose);
-    assert_eq!(cli.token, None);
+    assert!(cli.update.token.is_none());
 }
 
 #[test]
@@ -135,9 +141,9 @@ fn test_dry_run_with_path() {
 
     assert!(result.is_ok());
     let cli = result.unwrap();
-    assert!(cli.dry_run);
-    assert_eq!(cli.token, Some(".".to_string()));
-    assert_eq!(cli.path, Some("/some/path".to_string()));
+    assert!(cli.update.dry_run);
+    assert_eq!(token_of(&cli), Some("."));
+    assert_eq!(cli.update.selection.paths, vec![std::path::PathBuf::from("/some/path")]);
 }
 
 #[test]
@@ -158,7 +164,7 @@ fn test_get_token_if_needed_dry_run() {
 
     assert!(result.is_ok());
     let mut cli = result.unwrap();
-    let token_result = cli.get_token_if_needed().unwrap();
+    let token_result = cli.update.get_token_if_needed().unwrap();
     assert_eq!(token_result, None);
 }
 
@@ -169,6 +175,488 @@ fn test_get_token_if_needed_normal_mode() {
 
     assert!(result.is_ok());
     let mut cli = result.unwrap();
-    let token_result = cli.get_token_if_needed().unwrap();
-    assert_eq!(token_result, Some("hvs.test-token".to_string()));
-}
\ No newline at end of file
+    let token_result = cli.update.get_token_if_needed().unwrap();
+    assert_eq!(token_result.as_ref().map(|t| t.expose()), Some("hvs.test-token"));
+}
+#[test]
+fn test_output_presentation_flags() {
+    let args = vec!["vault-config-updater", "--quiet", "--color", "never", "--ascii", "--output", "json"];
+    let cli = CliArgs::try_parse_from(args).unwrap();
+
+    let options = cli.output_options();
+    assert!(options.quiet);
+    assert!(options.ascii);
+    assert_eq!(options.color, ColorChoice::Never);
+    assert_eq!(options.format, OutputFormat::Json);
+}
+
+#[test]
+fn test_quiet_conflicts_with_verbose() {
+    let args = vec!["vault-config-updater", "--quiet", "--verbose"];
+    assert!(CliArgs::try_parse_from(args).is_err());
+}
+
+#[test]
+fn test_debug_output_never_contains_token() {
+    let args = vec!["vault-config-updater", "hvs.debug-secret"];
+    let cli = CliArgs::try_parse_from(args).unwrap();
+
+    let debug = format!("{:?}", cli);
+    assert!(!debug.contains("debug-secret"), "token leaked: {}", debug);
+    assert!(debug.contains("hvs.****"));
+}
+
+#[test]
+fn test_token_source_flags() {
+    let cli = parse_args(vec!["vault-config-updater", "--token-file", "/run/secrets/token"]).unwrap();
+    assert_eq!(cli.update.token_source(), TokenSource::File("/run/secrets/token".into()));
+    assert!(!cli.needs_interactive_input());
+
+    let cli = parse_args(vec!["vault-config-updater", "--token-stdin"]).unwrap();
+    assert_eq!(cli.update.token_source(), TokenSource::Stdin);
+
+    let cli = parse_args(vec!["vault-config-updater", "--token-env", "NEW_VAULT_TOKEN"]).unwrap();
+    assert_eq!(cli.update.token_source(), TokenSource::Env("NEW_VAULT_TOKEN".to_string()));
+
+    let cli = parse_args(vec!["vault-config-updater", "--from-vault-cli", "/some/path"]).unwrap();
+    assert_eq!(cli.update.token_source(), TokenSource::VaultCli);
+    assert_eq!(cli.update.get_search_path(), std::path::Path::new("/some/path"));
+
+    let cli = parse_args(vec!["vault-config-updater", "--confirm-token"]).unwrap();
+    assert_eq!(cli.update.token_source(), TokenSource::Prompt { confirm: true });
+}
+
+#[test]
+fn test_token_sources_are_mutually_exclusive() {
+    let args = vec!["vault-config-updater", "--token-stdin", "--token-env", "VAR"];
+    assert!(parse_args(args).is_err());
+}
+
+#[test]
+fn test_positional_is_path_with_token_source() {
+    let cli = parse_args(vec!["vault-config-updater", "--token-stdin", "/some/path"]).unwrap();
+    assert!(cli.update.token.is_none());
+    assert_eq!(cli.update.get_search_path(), std::path::Path::new("/some/path"));
+
+    // Every positional is a root once the token comes from elsewhere
+    let cli = parse_args(vec!["vault-config-updater", "--token-stdin", "./a", "/some/path"]).unwrap();
+    assert!(cli.update.token.is_none());
+    assert_eq!(cli.update.search_paths(), vec![std::path::PathBuf::from("./a"), "/some/path".into()]);
+}
+
+#[test]
+fn test_multiple_roots_and_files_from() {
+    use std::path::PathBuf;
+
+    let cli = parse_args(vec!["vault-config-updater", "hvs.token", "./a", "./b"]).unwrap();
+    assert_eq!(token_of(&cli), Some("hvs.token"));
+    assert_eq!(cli.update.search_paths(), vec![PathBuf::from("./a"), PathBuf::from("./b")]);
+
+    // A file list on its own replaces the default root
+    let cli = parse_args(vec!["vault-config-updater", "scan", "--files-from", "-"]).unwrap();
+    match &cli.command {
+        Some(Command::Scan(args)) => {
+            assert!(args.selection.reads_stdin());
+            assert!(args.search_paths().is_empty());
+        }
+        other => panic!("unexpected command: {:?}", other),
+    }
+
+    // stdin cannot carry both the file list and the token
+    assert!(parse_args(vec!["vault-config-updater", "--files-from", "-", "--token-stdin"]).is_err());
+    assert!(parse_args(vec!["vault-config-updater", "--files-from", "-"]).is_err());
+    assert!(parse_args(vec!["vault-config-updater", "verify", "--files-from", "-"]).is_err());
+    assert!(parse_args(vec!["vault-config-updater", "--files-from", "-", "--token-env", "NEW_TOKEN"]).is_ok());
+    assert!(parse_args(vec!["vault-config-updater", "--files-from", "-", "--dry-run"]).is_ok());
+}
+
+#[test]
+fn test_vault_connection_flags() {
+    let args = vec![
+        "vault-config-updater", "--vault-addr", "https://vault.example.com:8200",
+        "--vault-namespace", "team-a", "--vault-cacert", "/etc/ssl/vault.pem", "--min-ttl", "24h",
+    ];
+    let cli = CliArgs::try_parse_from(args).unwrap();
+
+    assert!(cli.update.validation.wants_validation());
+    assert_eq!(cli.update.validation.min_ttl, Some(std::time::Duration::from_secs(86_400)));
+    let config = cli.update.vault.config().unwrap();
+    assert_eq!(config.address, "https://vault.example.com:8200");
+    assert_eq!(config.namespace.as_deref(), Some("team-a"));
+    assert_eq!(config.ca_cert, Some("/etc/ssl/vault.pem".into()));
+    assert!(!config.skip_verify);
+}
+
+#[test]
+fn test_login_flags() {
+    let args = vec![
+        "vault-config-updater", "--login", "token", "--token-policy", "app", "--token-policy", "ci",
+        "--token-ttl", "720h", "--token-env", "PARENT_TOKEN",
+    ];
+    let cli = parse_args(args).unwrap();
+
+    assert_eq!(cli.update.login.login, Some(LoginMethod::Token));
+    assert!(!cli.needs_interactive_input());
+    let credentials = cli.update.login
+        .credentials(LoginMethod::Token, || Ok(SecretToken::new("hvs.parent")))
+        .unwrap();
+    match credentials {
+        LoginCredentials::Token { parent, request } => {
+            assert_eq!(parent.expose(), "hvs.parent");
+            assert_eq!(request.policies, vec!["app", "ci"]);
+            assert_eq!(request.ttl, Some(std::time::Duration::from_secs(720 * 3600)));
+        }
+        other => panic!("unexpected credentials: {:?}", other),
+    }
+}
+
+#[test]
+fn test_login_positionals_are_search_paths() {
+    // approle and userpass issue the token themselves, so a lone positional is a path
+    let cli = parse_args(vec!["vault-config-updater", "--login", "approle", "--role-id", "x", "a"]).unwrap();
+    assert_eq!(cli.update.selection.paths, vec![std::path::PathBuf::from("a")]);
+    assert!(cli.update.token.is_none());
+    let cli = parse_args(vec!["vault-config-updater", "--login", "userpass", "--username", "alice", "a", "b"]).unwrap();
+    assert_eq!(cli.update.selection.paths, vec![std::path::PathBuf::from("a"), std::path::PathBuf::from("b")]);
+
+    // --login token takes its parent token positionally unless a source is given
+    let cli = parse_args(vec!["vault-config-updater", "--login", "token", "hvs.parent", "a"]).unwrap();
+    assert_eq!(cli.update.token.as_ref().map(|token| token.expose()), Some("hvs.parent"));
+    assert_eq!(cli.update.selection.paths, vec![std::path::PathBuf::from("a")]);
+    let cli = parse_args(vec!["vault-config-updater", "--login", "token", "--from-vault-cli", "a"]).unwrap();
+    assert_eq!(cli.update.selection.paths, vec![std::path::PathBuf::from("a")]);
+}
+
+#[test]
+fn test_unwrap_flag() {
+    let cli = parse_args(vec!["vault-config-updater", "--unwrap", "--token-stdin", "./services"]).unwrap();
+    assert!(cli.update.login.unwrap);
+    assert_eq!(cli.update.get_search_path(), std::path::Path::new("./services"));
+
+    let result = parse_args(vec!["vault-config-updater", "--unwrap", "--login", "approle"]);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_inspect_subcommand() {
+    let cli = parse_args(vec!["vault-config-updater", "inspect", "--lookup", "--expiring-within", "30d", "./services"]).unwrap();
+    assert!(!cli.needs_interactive_input());
+    match cli.command {
+        Some(Command::Inspect(args)) => {
+            assert!(args.lookup);
+            assert_eq!(args.expiring_within, std::time::Duration::from_secs(30 * 86_400));
+            assert_eq!(args.get_search_path(), std::path::Path::new("./services"));
+        }
+        other => panic!("unexpected command: {:?}", other),
+    }
+
+    let cli = parse_args(vec!["vault-config-updater", "hvs.token"]).unwrap();
+    assert_eq!(cli.command, None);
+}
+
+#[test]
+fn test_verify_subcommand() {
+    let cli = parse_args(vec!["vault-config-updater", "verify", "--token-fingerprint", " 6B267D24D081 ", "./services"]).unwrap();
+    match &cli.command {
+        Some(Command::Verify(args)) => {
+            assert_eq!(args.expected_fingerprint().unwrap(), "6b267d24d081");
+            assert_eq!(args.get_search_path(), std::path::Path::new("./services"));
+        }
+        other => panic!("unexpected command: {:?}", other),
+    }
+
+    let cli = parse_args(vec!["vault-config-updater", "verify", "--token-fingerprint", "hvs.stable"]).unwrap();
+    match &cli.command {
+        Some(Command::Verify(args)) => assert!(args.expected_fingerprint().is_err()),
+        other => panic!("unexpected command: {:?}", other),
+    }
+
+    let args = vec!["vault-config-updater", "verify", "--token-fingerprint", "6b267d24d081", "--token-stdin"];
+    assert!(parse_args(args).is_err());
+}
+
+#[test]
+fn test_bare_invocation_is_update() {
+    let mut bare = parse_args(vec!["vault-config-updater", "--token-stdin", "./services"]).unwrap();
+    let mut explicit = parse_args(vec!["vault-config-updater", "update", "--token-stdin", "./services"]).unwrap();
+
+    assert_eq!(bare.command, None);
+    assert!(matches!(explicit.command, Some(Command::Update(_))));
+    assert_eq!(bare.update_args(), explicit.update_args());
+    assert_eq!(explicit.update_args().unwrap().get_search_path(), std::path::Path::new("./services"));
+}
+
+#[test]
+fn test_scan_subcommand() {
+    let mut cli = parse_args(vec!["vault-config-updater", "scan", "./services"]).unwrap();
+    match &cli.command {
+        Some(Command::Scan(args)) => assert_eq!(args.get_search_path(), std::path::Path::new("./services")),
+        other => panic!("unexpected command: {:?}", other),
+    }
+    assert!(cli.update_args().is_none());
+    assert!(!cli.needs_interactive_input());
+}
+
+#[test]
+fn test_global_flags_work_after_subcommand() {
+    let cli = parse_args(vec!["vault-config-updater", "verify", "--token-stdin", "-q", "--output", "json"]).unwrap();
+    assert!(cli.quiet);
+    assert_eq!(cli.output, OutputFormat::Json);
+
+    // Update flags belong to a bare invocation or to `update`, not to other subcommands
+    assert!(parse_args(vec!["vault-config-updater", "scan", "--dry-run"]).is_err());
+    assert!(parse_args(vec!["vault-config-updater", "--dry-run", "scan"]).is_err());
+    // Most likely a directory called `scan`, which must be spelled `update TOKEN scan` now
+    assert!(parse_args(vec!["vault-config-updater", "hvs.token", "scan"]).is_err());
+    assert!(parse_args(vec!["vault-config-updater", "inspect", "--token-stdin"]).is_err());
+}
+
+#[test]
+fn test_approle_login_requires_role_id() {
+    let cli = parse_args(vec!["vault-config-updater", "--login", "approle"]).unwrap();
+    if cli.update.login.role_id.is_none() {
+        assert!(cli.update.login.credentials(LoginMethod::AppRole, || unreachable!()).is_err());
+    }
+}
+
+#[test]
+fn test_config_profile_fills_unset_options() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let config = temp_dir.path().join("settings.toml");
+    std::fs::write(&config, r#"
+[defaults]
+keys = ["vault_token"]
+
+[profiles.prod]
+paths = ["prod"]
+include = ["**/api/**"]
+output = "json"
+vault-addr = "https://vault.prod.example.com"
+"#).unwrap();
+    let config = config.to_str().unwrap();
+
+    let cli = parse_args(vec!["vault-config-updater", "--config", config, "--profile", "prod", "--token-stdin"]).unwrap();
+    assert_eq!(cli.output, OutputFormat::Json);
+    assert_eq!(cli.update.selection.keys, vec!["vault_token"]);
+    assert_eq!(cli.update.selection.include, vec!["**/api/**"]);
+    assert_eq!(cli.update.search_paths(), vec![temp_dir.path().join("prod")]);
+    assert_eq!(cli.update.vault.vault_addr.as_deref(), Some("https://vault.prod.example.com"));
+
+    // Without a profile only the defaults apply
+    let cli = parse_args(vec!["vault-config-updater", "scan", "--config", config]).unwrap();
+    match &cli.command {
+        Some(Command::Scan(args)) => {
+            assert_eq!(args.selection.keys, vec!["vault_token"]);
+            assert!(args.selection.include.is_empty());
+            assert_eq!(args.search_paths(), vec![std::path::PathBuf::from(".")]);
+        }
+        other => panic!("unexpected command: {:?}", other),
+    }
+    assert_eq!(cli.output, OutputFormat::Text);
+
+    assert!(parse_args(vec!["vault-config-updater", "--config", config, "--profile", "staging"]).is_err());
+    assert!(parse_args(vec!["vault-config-updater", "--profile", "prod"]).is_err());
+}
+
+#[test]
+fn test_command_line_overrides_config_profile() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let config = temp_dir.path().join("settings.toml");
+    std::fs::write(&config, r#"
+[profiles.prod]
+paths = ["prod"]
+include = ["**/api/**"]
+keys = ["vault_token"]
+output = "json"
+vault-addr = "https://vault.prod.example.com"
+"#).unwrap();
+    let config = config.to_str().unwrap();
+
+    let cli = parse_args(vec![
+        "vault-config-updater", "update", "--config", config, "--profile", "prod",
+        "--token-stdin", "--include", "**/web/**", "--key", "token", "--output", "ndjson",
+        "--vault-addr", "http://127.0.0.1:8200", "./local",
+    ]).unwrap();
+    assert_eq!(cli.output, OutputFormat::Ndjson);
+    match &cli.command {
+        Some(Command::Update(args)) => {
+            assert_eq!(args.selection.include, vec!["**/web/**"]);
+            assert_eq!(args.selection.keys, vec!["token"]);
+            assert_eq!(args.search_paths(), vec![std::path::PathBuf::from("./local")]);
+            assert_eq!(args.vault.vault_addr.as_deref(), Some("http://127.0.0.1:8200"));
+        }
+        other => panic!("unexpected command: {:?}", other),
+    }
+}
+
+#[test]
+fn test_serve_takes_vault_addr_from_config() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let config = temp_dir.path().join("settings.toml");
+    std::fs::write(&config, "[defaults]\nvault-addr = \"https://vault.prod.example.com\"\n").unwrap();
+    let cli = parse_args(vec!["vault-config-updater", "serve", "--config", config.to_str().unwrap()]).unwrap();
+    match &cli.command {
+        Some(Command::Serve(args)) => assert_eq!(args.vault.vault_addr.as_deref(), Some("https://vault.prod.example.com")),
+        other => panic!("unexpected command: {:?}", other),
+    }
+}
+
+#[test]
+fn test_mapping_takes_positionals_as_paths() {
+    let cli = parse_args(vec!["vault-config-updater", "--mapping", "map.toml", "./a", "./b"]).unwrap();
+    assert_eq!(cli.update.mapping.as_deref(), Some(std::path::Path::new("map.toml")));
+    assert_eq!(cli.update.token, None);
+    assert_eq!(cli.update.selection.paths, vec![std::path::PathBuf::from("./a"), std::path::PathBuf::from("./b")]);
+    assert!(!cli.needs_interactive_input());
+
+    for conflicting in ["--token-stdin", "--dry-run", "--revoke-old", "--validate-token"] {
+        assert!(parse_args(vec!["vault-config-updater", "--mapping", "map.toml", conflicting]).is_err(), "{}", conflicting);
+    }
+}
+
+#[test]
+fn test_watch_subcommand() {
+    let cli = parse_args(vec![
+        "vault-config-updater", "watch", "--token-file", "/run/agent/sink", "--debounce", "2s", "./a", "./b",
+    ]).unwrap();
+    match &cli.command {
+        Some(Command::Watch(args)) => {
+            assert_eq!(args.debounce, std::time::Duration::from_secs(2));
+            assert_eq!(args.search_paths(), vec![std::path::PathBuf::from("./a"), std::path::PathBuf::from("./b")]);
+            assert_eq!(args.token_files(), vec![std::path::PathBuf::from("/run/agent/sink")]);
+        }
+        other => panic!("unexpected command: {:?}", other),
+    }
+
+    assert!(parse_args(vec!["vault-config-updater", "watch", "--token-env", "T", "--files-from", "list.txt"]).is_err());
+
+    let cli = parse_args(vec![
+        "vault-config-updater", "watch", "--sink", "/run/a/token", "--sink", "/run/b/token", "--min-interval", "1m",
+    ]).unwrap();
+    match &cli.command {
+        Some(Command::Watch(args)) => {
+            assert_eq!(args.token_files().len(), 2);
+            assert_eq!(args.min_interval, std::time::Duration::from_secs(60));
+        }
+        other => panic!("unexpected command: {:?}", other),
+    }
+    assert!(parse_args(vec!["vault-config-updater", "watch", "--sink", "/run/a/token", "--token-env", "T"]).is_err());
+}
+
+#[test]
+fn test_serve_subcommand() {
+    let cli = parse_args(vec!["vault-config-updater", "serve", "--socket", "/run/vcu.sock", "./services"]).unwrap();
+    match &cli.command {
+        Some(Command::Serve(args)) => {
+            assert_eq!(args.socket_path(), std::path::PathBuf::from("/run/vcu.sock"));
+            assert_eq!(args.search_paths(), vec![std::path::PathBuf::from("./services")]);
+        }
+        other => panic!("unexpected command: {:?}", other),
+    }
+
+    let cli = parse_args(vec!["vault-config-updater", "serve"]).unwrap();
+    match &cli.command {
+        Some(Command::Serve(args)) => assert!(args.socket_path().ends_with("vault-config-updater.sock")),
+        other => panic!("unexpected command: {:?}", other),
+    }
+    assert!(parse_args(vec!["vault-config-updater", "serve", "--files-from", "-"]).is_err());
+    assert!(parse_args(vec!["vault-config-updater", "serve", "--token-env", "T"]).is_err());
+}
+
+#[test]
+fn test_serve_http_needs_a_webhook_secret() {
+    let cli = parse_args(vec![
+        "vault-config-updater", "serve", "--http", "127.0.0.1:8470", "--webhook-secret-env", "HOOK_SECRET",
+    ]).unwrap();
+    match &cli.command {
+        Some(Command::Serve(args)) => {
+            assert_eq!(args.http, Some("127.0.0.1:8470".parse().unwrap()));
+            assert_eq!(args.webhook_secret(), Some(TokenSource::Env("HOOK_SECRET".to_string())));
+        }
+        other => panic!("unexpected command: {:?}", other),
+    }
+
+    assert!(parse_args(vec!["vault-config-updater", "serve", "--http", "127.0.0.1:8470"]).is_err());
+    assert!(parse_args(vec!["vault-config-updater", "serve", "--http", "localhost"]).is_err());
+    assert!(parse_args(vec![
+        "vault-config-updater", "serve", "--http", "127.0.0.1:8470",
+        "--webhook-secret-env", "HOOK_SECRET", "--webhook-secret-file", "secret",
+    ]).is_err());
+}
+
+#[test]
+fn test_no_hooks_skips_configured_hooks() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let config = temp_dir.path().join("settings.toml");
+    std::fs::write(&config, "[defaults.hooks]\npost = \"systemctl reload api\"\n").unwrap();
+    let config = config.to_str().unwrap();
+
+    let cli = parse_args(vec!["vault-config-updater", "--config", config, "--token-stdin"]).unwrap();
+    assert_eq!(cli.hooks.post.as_deref(), Some("systemctl reload api"));
+    assert!(!cli.hooks().unwrap().is_empty());
+
+    let cli = parse_args(vec!["vault-config-updater", "watch", "--config", config, "--no-hooks", "--token-stdin"]).unwrap();
+    assert!(cli.no_hooks);
+    assert!(cli.hooks().unwrap().is_empty());
+}
+
+#[test]
+fn test_history_subcommand_and_audit_log() {
+    let cli = parse_args(vec![
+        "vault-config-updater", "history", "--audit-log", "/var/log/vcu.jsonl", "/srv/api",
+        "--fingerprint", "6B267D24D081", "--run", "3f2a9c1b7e4d", "--since", "2026-10-01T00:00:00Z", "--limit", "5",
+    ]).unwrap();
+    assert_eq!(cli.audit_log.as_deref(), Some(std::path::Path::new("/var/log/vcu.jsonl")));
+    match &cli.command {
+        Some(Command::History(args)) => {
+            let query = args.query();
+            assert_eq!(query.paths, vec![std::path::PathBuf::from("/srv/api")]);
+            assert_eq!(query.fingerprint.as_deref(), Some("6b267d24d081"));
+            assert_eq!(query.run_id.as_deref(), Some("3f2a9c1b7e4d"));
+            assert_eq!(query.since, Some(humantime::parse_rfc3339("2026-10-01T00:00:00Z").unwrap()));
+            assert_eq!(query.limit, Some(5));
+        }
+        other => panic!("unexpected command: {:?}", other),
+    }
+    // Reading the log does not open it for writing
+    assert!(cli.open_audit_log().unwrap().is_none());
+    assert!(parse_args(vec!["vault-config-updater", "history", "--since", "someday"]).is_err());
+
+    // The config file names the log, relative to itself; the command line wins
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let config = temp_dir.path().join("settings.toml");
+    std::fs::write(&config, "[defaults]\naudit-log = \"audit/rotations.jsonl\"\n").unwrap();
+    let config = config.to_str().unwrap();
+    let cli = parse_args(vec!["vault-config-updater", "history", "--config", config]).unwrap();
+    assert_eq!(cli.audit_log, Some(temp_dir.path().join("audit/rotations.jsonl")));
+    let cli = parse_args(vec!["vault-config-updater", "--config", config, "--audit-log", "other.jsonl", "--token-stdin"]).unwrap();
+    assert_eq!(cli.audit_log.as_deref(), Some(std::path::Path::new("other.jsonl")));
+}
+
+#[test]
+fn test_git_policy_from_flags_and_config() {
+    use vault_config_updater::GitPolicy;
+    let cli = parse_args(vec!["vault-config-updater", "--token-stdin", "--git-policy", "fail", "--gitignore"]).unwrap();
+    assert_eq!(cli.update.git.policy(), GitPolicy::Fail);
+    assert!(cli.update.git.gitignore);
+    let cli = parse_args(vec!["vault-config-updater", "watch", "--token-stdin"]).unwrap();
+    match &cli.command {
+        Some(Command::Watch(args)) => assert_eq!(args.git.policy(), GitPolicy::Warn),
+        other => panic!("unexpected command: {:?}", other),
+    }
+    assert!(parse_args(vec!["vault-config-updater", "scan", "--git-policy", "skip"]).is_err());
+    assert!(parse_args(vec!["vault-config-updater", "--token-stdin", "--git-policy", "ignore"]).is_err());
+
+    // The config file sets the policy unless the command line does
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let config = temp_dir.path().join("settings.toml");
+    std::fs::write(&config, "[defaults]\ngit-policy = \"skip\"\n").unwrap();
+    let config = config.to_str().unwrap();
+    let cli = parse_args(vec!["vault-config-updater", "serve", "--config", config]).unwrap();
+    match &cli.command {
+        Some(Command::Serve(args)) => assert_eq!(args.git.policy(), GitPolicy::Skip),
+        other => panic!("unexpected command: {:?}", other),
+    }
+    let cli = parse_args(vec!["vault-config-updater", "--config", config, "--token-stdin", "--git-policy", "off"]).unwrap();
+    assert_eq!(cli.update.git.policy(), GitPolicy::Off);
+}
@@ -0,0 +1,114 @@
+//! Local stand-in for the Vault HTTP API used by the integration tests.
+#![allow(dead_code)]
+
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use tiny_http::{Header, Response, Server};
+
+/// A request received by the mock server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub token: Option<String>,
+    pub namespace: Option<String>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    /// The request body decoded as JSON
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).unwrap_or(serde_json::Value::Null)
+    }
+}
+
+type Handler = dyn Fn(&RecordedRequest) -> (u16, String) + Send + Sync;
+
+/// HTTP server on 127.0.0.1 that answers every request through a handler closure
+pub struct MockVault {
+    pub addr: String,
+    server: Arc<Server>,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockVault {
+    pub fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&RecordedRequest) -> (u16, String) + Send + Sync + 'static,
+    {
+        let server = Arc::new(Server::http("127.0.0.1:0").unwrap());
+        let addr = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+
+        let thread = {
+            let server = Arc::clone(&server);
+            let requests = Arc::clone(&requests);
+            thread::spawn(move || {
+                for mut request in server.incoming_requests() {
+                    let token = header(&request, "X-Vault-Token");
+                    let namespace = header(&request, "X-Vault-Namespace");
+                    let mut body = String::new();
+                    let _ = request.as_reader().read_to_string(&mut body);
+                    let recorded = RecordedRequest {
+                        method: request.method().to_string(),
+                        path: request.url().split('?').next().unwrap_or("").to_string(),
+                        token,
+                        namespace,
+                        body,
+                    };
+
+                    let (status, body) = handler(&recorded);
+                    requests.lock().unwrap().push(recorded);
+
+                    let response = Response::from_string(body)
+                        .with_status_code(status)
+                        .with_header(Header::from_bytes("Content-Type", "application/json").unwrap());
+                    let _ = request.respond(response);
+                }
+            })
+        };
+
+        Self { addr, server, requests, thread: Some(thread) }
+    }
+
+    /// All requests received so far, in arrival order
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn header(request: &tiny_http::Request, name: &'static str) -> Option<String> {
+    request.headers().iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.to_string())
+}
+
+impl Drop for MockVault {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// `lookup-self` response body for a token with the given TTL in seconds
+pub fn lookup_response(ttl: u64, policies: &[&str]) -> String {
+    serde_json::json!({
+        "data": {
+            "id": "hvs.must-not-be-captured",
+            "display_name": "token-ci",
+            "policies": policies,
+            "ttl": ttl,
+            "expire_time": if ttl == 0 { None } else { Some("2026-10-19T12:00:00Z") },
+            "renewable": true
+        }
+    }).to_string()
+}
+
+/// Vault-style error response body
+pub fn error_response(message: &str) -> String {
+    serde_json::json!({ "errors": [message] }).to_string()
+}
@@ -239,4 +239,99 @@ fn test_scan_stats_struct() {
     stats.add_error("Test error".to_string());
     assert_eq!(stats.errors.len(), 1);
     assert_eq!(stats.errors[0], "Test error");
-}
\ No newline at end of file
+}
+#[test]
+fn test_collect_distinct_vault_tokens() {
+    use vault_config_updater::{collect_distinct_vault_tokens, collect_vault_tokens_in_file, TokenFields};
+
+    let temp_dir = TempDir::new().unwrap();
+    let first = temp_dir.path().join("config.json");
+    let second = temp_dir.path().join("globalConfig.json");
+    fs::write(&first, r#"{"vaultToken": "hvs.a", "nested": {"vaultToken": "hvs.b"}}"#).unwrap();
+    fs::write(&second, r#"{"vaultToken": "hvs.a", "other": {"vaultToken": ""}}"#).unwrap();
+
+    let in_first = collect_vault_tokens_in_file(&first).unwrap();
+    assert_eq!(in_first.iter().map(|t| t.expose()).collect::<Vec<_>>(), vec!["hvs.a", "hvs.b"]);
+
+    let missing = temp_dir.path().join("missing.json");
+    let distinct = collect_distinct_vault_tokens(&[first, second, missing], &TokenFields::default());
+    let mut values: Vec<&str> = distinct.iter().map(|t| t.expose()).collect();
+    values.sort();
+    assert_eq!(values, vec!["hvs.a", "hvs.b"]);
+}
+
+#[test]
+fn test_verify_files_against_expected_token() {
+    use vault_config_updater::{
+        fingerprint, verify_files_parallel, verify_vault_tokens_in_file, ExitCode, FileStatus, TokenFields
+    };
+
+    let temp_dir = TempDir::new().unwrap();
+    let good = temp_dir.path().join("good.json");
+    let drifted = temp_dir.path().join("drifted.json");
+    let empty = temp_dir.path().join("empty.json");
+    fs::write(&good, r#"{"vaultToken": "hvs.expected", "db": {"vaultToken": "hvs.expected"}}"#).unwrap();
+    fs::write(&drifted, r#"{"vaultToken": "hvs.expected", "db": {"vaultToken": "hvs.stale"}}"#).unwrap();
+    fs::write(&empty, r#"{"host": "localhost"}"#).unwrap();
+
+    let expected = fingerprint("hvs.expected");
+    assert_eq!(verify_vault_tokens_in_file(&good, &expected).unwrap(), (2, 0));
+    assert_eq!(verify_vault_tokens_in_file(&drifted, &expected).unwrap(), (1, 1));
+
+    let stats = verify_files_parallel(&[good.clone(), drifted.clone(), empty], &expected, &TokenFields::default(), |_| {});
+    assert_eq!(stats.expected_fingerprint, expected);
+    assert_eq!(stats.files_checked, 3);
+    assert_eq!(stats.files_verified, 1);
+    assert_eq!(stats.files_drifted, 1);
+    assert_eq!(stats.drifted, vec![drifted]);
+    assert_eq!(stats.files[0].status, FileStatus::Verified);
+    assert_eq!(stats.files[1].status, FileStatus::Drifted);
+    assert_eq!(stats.files[1].tokens, 1);
+    assert_eq!(stats.files[2].status, FileStatus::NoTokens);
+    assert_eq!(stats.exit_code(), ExitCode::DriftDetected);
+
+    let stats = verify_files_parallel(&[good], &expected, &TokenFields::default(), |_| {});
+    assert_eq!(stats.exit_code(), ExitCode::Success);
+}
+
+#[test]
+fn test_token_fields_with_custom_keys() {
+    use vault_config_updater::TokenFields;
+
+    let content = r#"{"vaultToken": "hvs.a", "vault_token":"hvs.b", "token": "keep"}"#;
+    let fields = TokenFields::new(&["vault_token", "vaultToken"]).unwrap();
+    assert_eq!(fields.count(content), 2);
+    assert_eq!(fields.values(content).iter().map(|t| t.expose()).collect::<Vec<_>>(), vec!["hvs.a", "hvs.b"]);
+    assert_eq!(
+        fields.replace(content, "hvs.new"),
+        r#"{"vaultToken": "hvs.new", "vault_token": "hvs.new", "token": "keep"}"#
+    );
+
+    // Only vaultToken by default
+    assert_eq!(TokenFields::default().count(content), 1);
+    assert_eq!(TokenFields::new::<&str>(&[]).unwrap().keys(), ["vaultToken"]);
+    assert!(TokenFields::new(&["vault\"Token"]).is_err());
+}
+
+#[test]
+fn test_token_field_pointers() {
+    use vault_config_updater::TokenFields;
+
+    let content = r#"{"vaultToken": "a", "db": {"vaultToken": "b", "list": [{"vaultToken": "c"}]}, "a/b": {"vaultToken": "d"}}"#;
+    assert_eq!(
+        TokenFields::default().pointers(content),
+        vec!["/vaultToken", "/db/vaultToken", "/db/list/0/vaultToken", "/a~1b/vaultToken"]
+    );
+}
+
+#[test]
+fn test_environment_of_reads_json_pointer() {
+    use vault_config_updater::environment_of;
+
+    let content = std::fs::read_to_string("tests/fixtures/globalConfig.json").unwrap();
+    assert_eq!(environment_of(&content, "/global/environment").as_deref(), Some("production"));
+    assert_eq!(environment_of(&content, "/global/region"), None);
+    assert_eq!(environment_of(&content, "/services"), None);
+    assert_eq!(environment_of(r#"{"env": 3}"#, "/env").as_deref(), Some("3"));
+    assert_eq!(environment_of("not json", "/env"), None);
+}
@@ -54,4 +54,187 @@ fn test_ignores_other_json_files() {
 fn test_handles_nonexistent_directory() {
     let result = find_config_files(Path::new("nonexistent/directory"));
     assert!(result.is_err());
-}
\ No newline at end of file
+}
+#[test]
+fn test_filters_files_by_include_and_exclude_globs() {
+    use std::fs;
+    use tempfile::TempDir;
+    use vault_config_updater::{find_config_files_filtered, FileFilter};
+
+    let temp_dir = TempDir::new().unwrap();
+    for dir in ["prod/api", "prod/node_modules/pkg", "dev/api"] {
+        fs::create_dir_all(temp_dir.path().join(dir)).unwrap();
+        fs::write(temp_dir.path().join(dir).join("config.json"), "{}").unwrap();
+    }
+    let relative = |filter: &FileFilter| {
+        let mut files: Vec<String> = find_config_files_filtered(temp_dir.path(), filter).unwrap()
+            .iter()
+            .map(|file| file.strip_prefix(temp_dir.path()).unwrap().display().to_string())
+            .collect();
+        files.sort();
+        files
+    };
+
+    assert_eq!(relative(&FileFilter::default()).len(), 3);
+    let filter = FileFilter::new(&["prod/**"], &["**/node_modules/**"]).unwrap();
+    assert_eq!(relative(&filter), vec!["prod/api/config.json"]);
+    // `*` does not cross directories
+    let filter = FileFilter::new(&["*/config.json"], &[]).unwrap();
+    assert!(relative(&filter).is_empty());
+
+    assert!(FileFilter::new(&["prod/[a"], &[]).is_err());
+}
+
+#[test]
+fn test_reads_config_files_from_a_file_list() {
+    use std::io::Cursor;
+    use vault_config_updater::{read_file_list, FileFilter};
+
+    let list = "services/api/config.json\nREADME.md\n\n./services/web/globalConfig.json\r\nvendor/config.json\n";
+    let filter = FileFilter::new(&["services/**"], &[]).unwrap();
+    let files = read_file_list(Cursor::new(list), &filter).unwrap();
+    assert_eq!(files, vec![
+        Path::new("services/api/config.json").to_path_buf(),
+        Path::new("./services/web/globalConfig.json").to_path_buf(),
+    ]);
+}
+
+#[test]
+fn test_overlapping_roots_process_each_file_once() {
+    use std::fs;
+    use tempfile::TempDir;
+    use vault_config_updater::{find_config_files_in_roots, FileFilter};
+
+    let temp_dir = TempDir::new().unwrap();
+    let api = temp_dir.path().join("services/api");
+    fs::create_dir_all(&api).unwrap();
+    fs::write(api.join("config.json"), "{}").unwrap();
+    fs::write(temp_dir.path().join("config.json"), "{}").unwrap();
+    let list = temp_dir.path().join("files.txt");
+    fs::write(&list, format!("{}\n", api.join("config.json").display())).unwrap();
+
+    let roots = find_config_files_in_roots(
+        &[api.clone(), temp_dir.path().to_path_buf()], Some(&list), &FileFilter::default()
+    ).unwrap();
+    assert_eq!(roots.len(), 3);
+    assert_eq!(roots[0].path, api);
+    assert_eq!(roots[0].files, vec![api.join("config.json")]);
+    // The second root and the list only keep what the first root did not already claim
+    assert_eq!(roots[1].files, vec![temp_dir.path().join("config.json")]);
+    assert!(roots[2].files.is_empty());
+}
+
+/// Runs git in `dir`, failing the test when it does
+fn git(dir: &Path, args: &[&str]) {
+    let status = std::process::Command::new("git").arg("-C").arg(dir).args(args).status().unwrap();
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+/// A repository with a tracked, an untracked, an ignored and an oddly named config file, plus one outside it
+fn git_repo_with_configs() -> (tempfile::TempDir, Vec<std::path::PathBuf>) {
+    use std::fs;
+    let dir = tempfile::TempDir::new().unwrap();
+    let repo = dir.path().join("repo");
+    let outside = dir.path().join("outside/config.json");
+    let mut files: Vec<std::path::PathBuf> = ["api/config.json", "web/config.json", "cache/config.json", "docs/[draft]/config.json"]
+        .iter()
+        .map(|relative| repo.join(relative))
+        .collect();
+    files.push(outside);
+    for file in &files {
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(file, r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    }
+    git(&repo, &["init", "-q"]);
+    fs::write(repo.join(".gitignore"), "cache/\n").unwrap();
+    git(&repo, &["add", "api/config.json"]);
+    (dir, files)
+}
+
+#[test]
+fn test_reports_git_status_of_config_files() {
+    use vault_config_updater::{git_status_of, GitStatus};
+    let (dir, files) = git_repo_with_configs();
+    let repo = dir.path().join("repo");
+
+    let (statuses, errors) = git_status_of(&files);
+    assert!(errors.is_empty(), "{:?}", errors);
+    let found: Vec<(&Path, GitStatus)> = statuses.iter().map(|file| (file.path.as_path(), file.status)).collect();
+    assert_eq!(found, vec![
+        (files[0].as_path(), GitStatus::Tracked),
+        (files[1].as_path(), GitStatus::Untracked),
+        (files[2].as_path(), GitStatus::Ignored),
+        (files[3].as_path(), GitStatus::Untracked),
+    ]);
+    assert!(statuses.iter().all(|file| file.repo == repo));
+    assert_eq!(statuses[0].describe(), format!("{} is tracked by git in {}", files[0].display(), repo.display()));
+}
+
+#[test]
+fn test_git_policy_warns_skips_or_fails() {
+    use vault_config_updater::{check_git, GitPolicy, GitStatus, ValidationError};
+    let (_dir, files) = git_repo_with_configs();
+
+    let off = check_git(files.clone(), GitPolicy::Off, false).unwrap();
+    assert_eq!(off.files, files);
+    assert!(off.exposed.is_empty());
+
+    let warn = check_git(files.clone(), GitPolicy::Warn, false).unwrap();
+    assert_eq!(warn.files, files);
+    assert_eq!(warn.exposed.len(), 3);
+
+    let skip = check_git(files.clone(), GitPolicy::Skip, false).unwrap();
+    assert_eq!(skip.files, vec![files[2].clone(), files[4].clone()]);
+
+    let error = check_git(files.clone(), GitPolicy::Fail, false).unwrap_err();
+    assert!(error.downcast_ref::<ValidationError>().is_some(), "{}", error);
+    assert!(error.to_string().contains("3 files could end up in git"), "{}", error);
+
+    // Untracked files become ignored; the tracked one stays exposed
+    let fixed = check_git(files.clone(), GitPolicy::Skip, true).unwrap();
+    assert_eq!(fixed.ignored.len(), 2);
+    assert_eq!(fixed.exposed.len(), 1);
+    assert_eq!(fixed.exposed[0].status, GitStatus::Tracked);
+    assert_eq!(fixed.files, files[1..].to_vec());
+}
+
+#[test]
+fn test_adds_config_files_to_gitignore_once() {
+    use std::fs;
+    use vault_config_updater::{add_to_gitignore, git_status_of, GitStatus};
+    let (dir, files) = git_repo_with_configs();
+    let gitignore = dir.path().join("repo/.gitignore");
+    // No trailing newline, so the first entry starts a new line
+    fs::write(&gitignore, "cache/").unwrap();
+
+    let (statuses, _) = git_status_of(&files[1..4]);
+    assert!(add_to_gitignore(&statuses[0]).unwrap());
+    assert!(add_to_gitignore(&statuses[2]).unwrap());
+    assert!(!add_to_gitignore(&statuses[0]).unwrap());
+    assert_eq!(fs::read_to_string(&gitignore).unwrap(), "cache/\n/web/config.json\n/docs/\\[draft]/config.json\n");
+
+    let (statuses, _) = git_status_of(&files[1..4]);
+    assert!(statuses.iter().all(|file| file.status == GitStatus::Ignored), "{:?}", statuses);
+}
+
+#[test]
+fn test_gitignore_entry_overridden_by_another_rule_stays_exposed() {
+    use std::fs;
+    use vault_config_updater::{check_git, GitPolicy, GitStatus};
+    let (dir, files) = git_repo_with_configs();
+    // A deeper .gitignore re-includes the file whatever the top one says
+    fs::write(dir.path().join("repo/web/.gitignore"), "!config.json\n").unwrap();
+    let web = vec![files[1].clone()];
+
+    let check = check_git(web.clone(), GitPolicy::Skip, true).unwrap();
+    assert_eq!(check.ignored.len(), 1);
+    assert_eq!(check.ignored[0].status, GitStatus::Untracked);
+    assert_eq!(check.exposed.len(), 1);
+    assert!(check.files.is_empty());
+
+    // The entry is already there now, so nothing counts as added, and the file is still refused
+    let check = check_git(web.clone(), GitPolicy::Skip, true).unwrap();
+    assert!(check.ignored.is_empty());
+    assert_eq!(check.exposed[0].status, GitStatus::Untracked);
+    assert!(check_git(web, GitPolicy::Fail, true).is_err());
+}
@@ -0,0 +1,212 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use vault_config_updater::{
+    update_files_parallel, ExitCode, FileHookConfig, FileStatus, HookConfig, HookStage, HookStatus, Hooks,
+    TokenFields, ValidationError,
+};
+
+fn write_config(root: &Path, relative: &str, token: &str) -> PathBuf {
+    let path = root.join(relative);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, format!(r#"{{"vaultToken": "{}"}}"#, token)).unwrap();
+    path
+}
+
+fn file_hook(path: Option<&str>, dir: Option<&str>, pre: Option<&str>, post: Option<&str>) -> FileHookConfig {
+    FileHookConfig {
+        path: path.map(str::to_string),
+        dir: dir.map(str::to_string),
+        pre: pre.map(str::to_string),
+        post: post.map(str::to_string),
+    }
+}
+
+/// Lines appended to `log` by the hooks, sorted since per-file hooks run in parallel
+fn logged(log: &Path) -> Vec<String> {
+    let mut lines: Vec<String> = fs::read_to_string(log).unwrap_or_default().lines().map(str::to_string).collect();
+    lines.sort();
+    lines
+}
+
+#[test]
+fn test_rejects_incomplete_hook_entries() {
+    let config = |entry: FileHookConfig| HookConfig { files: vec![entry], ..HookConfig::default() };
+    for invalid in [
+        config(file_hook(None, None, None, Some("true"))),
+        config(file_hook(Some("**"), None, None, None)),
+        config(file_hook(Some("[oops"), None, None, Some("true"))),
+        HookConfig { timeout: Some("soon".to_string()), ..HookConfig::default() },
+    ] {
+        let error = Hooks::new(&invalid).unwrap_err();
+        assert!(error.downcast_ref::<ValidationError>().is_some(), "{}", error);
+    }
+    assert!(Hooks::new(&HookConfig::default()).unwrap().is_empty());
+}
+
+#[test]
+fn test_commands_are_expanded_and_quoted() {
+    let hooks = Hooks::new(&HookConfig {
+        files: vec![
+            file_hook(Some("services/*/config.json"), None, None, Some("touch {dir}/.reload && echo {path}")),
+            file_hook(None, Some("services/web"), Some("stop web"), None),
+        ],
+        ..HookConfig::default()
+    }).unwrap();
+
+    assert_eq!(
+        hooks.commands_for(HookStage::Post, Path::new("./services/api/config.json")),
+        vec!["touch './services/api'/.reload && echo './services/api/config.json'"]
+    );
+    assert_eq!(
+        hooks.commands_for(HookStage::Post, Path::new("services/it's/config.json")),
+        vec![r"touch 'services/it'\''s'/.reload && echo 'services/it'\''s/config.json'"]
+    );
+    assert_eq!(hooks.commands_for(HookStage::Pre, Path::new("services/web/config.json")), vec!["stop web"]);
+    assert!(hooks.commands_for(HookStage::Pre, Path::new("services/api/config.json")).is_empty());
+    assert!(hooks.commands_for(HookStage::Post, Path::new("other/api/config.json")).is_empty());
+}
+
+#[test]
+fn test_runs_commands_with_captured_output() {
+    let hooks = Hooks::new(&HookConfig { timeout: Some("300ms".to_string()), ..HookConfig::default() }).unwrap();
+
+    let outcome = hooks.run(HookStage::Post, "echo out; echo err >&2", Vec::new());
+    assert_eq!(outcome.status, HookStatus::Succeeded);
+    assert_eq!(outcome.exit_code, Some(0));
+    assert_eq!(outcome.stdout, "out\n");
+    assert_eq!(outcome.stderr, "err\n");
+    assert_eq!(outcome.error, None);
+
+    let outcome = hooks.run(HookStage::Post, "echo broken >&2; exit 3", Vec::new());
+    assert_eq!(outcome.status, HookStatus::Failed);
+    assert_eq!(outcome.exit_code, Some(3));
+    assert_eq!(outcome.describe(), "`echo broken >&2; exit 3` exited with status 3");
+
+    // The command started by the hook is stopped too, so its open pipe does not hold up the run
+    let start = Instant::now();
+    let outcome = hooks.run(HookStage::Post, "sleep 10 & echo started; wait", Vec::new());
+    assert!(start.elapsed() < Duration::from_secs(5), "{:?}", start.elapsed());
+    assert_eq!(outcome.status, HookStatus::TimedOut);
+    assert_eq!(outcome.stdout, "started\n");
+    assert_eq!(outcome.error.as_deref(), Some("timed out after 300ms"));
+
+    // A process that left the group keeps the pipe open, but the run does not wait for it
+    let start = Instant::now();
+    let outcome = hooks.run(HookStage::Post, "setsid sleep 3 & wait", Vec::new());
+    assert!(start.elapsed() < Duration::from_secs(2), "{:?}", start.elapsed());
+    assert_eq!(outcome.status, HookStatus::TimedOut);
+}
+
+#[test]
+fn test_post_hooks_run_once_per_command_for_written_files() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path();
+    let log = root.join("hooks.log");
+    let files = vec![
+        write_config(root, "api/config.json", "hvs.old"),
+        write_config(root, "api/globalConfig.json", "hvs.old"),
+        write_config(root, "web/config.json", "hvs.new"),
+    ];
+    let hooks = Hooks::new(&HookConfig {
+        pre: Some(format!("echo global-pre >> {}", log.display())),
+        post: Some(format!("echo global-post >> {}", log.display())),
+        files: vec![
+            file_hook(None, Some("**"), Some(&format!("echo pre {{path}} >> {}", log.display())), None),
+            file_hook(None, Some("**"), None, Some(&format!("echo restart {{dir}} >> {}", log.display()))),
+        ],
+        ..HookConfig::default()
+    }).unwrap();
+
+    let stats = update_files_parallel(&files, "hvs.new", &TokenFields::default(), &hooks, |_| {});
+    assert_eq!(stats.exit_code(), ExitCode::Success);
+    assert!(stats.errors.is_empty(), "{:?}", stats.errors);
+    let api = root.join("api");
+    assert_eq!(logged(&log), vec![
+        "global-post".to_string(),
+        "global-pre".to_string(),
+        format!("pre {}", files[0].display()),
+        format!("pre {}", files[1].display()),
+        format!("restart {}", api.display()),
+    ]);
+
+    let stages: Vec<HookStage> = stats.hooks.iter().map(|hook| hook.stage).collect();
+    assert_eq!(stages, vec![HookStage::Pre, HookStage::Pre, HookStage::Pre, HookStage::Post, HookStage::Post]);
+    let restart = &stats.hooks[3];
+    assert_eq!(restart.files, vec![files[0].clone(), files[1].clone()]);
+    assert!(stats.hooks[4].files.is_empty());
+}
+
+#[test]
+fn test_failed_global_pre_hook_leaves_files_untouched() {
+    let dir = TempDir::new().unwrap();
+    let file = write_config(dir.path(), "api/config.json", "hvs.old");
+    let hooks = Hooks::new(&HookConfig { pre: Some("exit 1".to_string()), ..HookConfig::default() }).unwrap();
+
+    let stats = update_files_parallel(std::slice::from_ref(&file), "hvs.new", &TokenFields::default(), &hooks, |_| {});
+    assert_eq!(stats.files_processed, 0);
+    assert_eq!(stats.errors, vec!["Pre-update hook `exit 1` exited with status 1"]);
+    assert_eq!(stats.exit_code(), ExitCode::Failure);
+    assert_eq!(fs::read_to_string(file).unwrap(), r#"{"vaultToken": "hvs.old"}"#);
+}
+
+#[test]
+fn test_failed_file_pre_hook_skips_only_that_file() {
+    let dir = TempDir::new().unwrap();
+    let api = write_config(dir.path(), "api/config.json", "hvs.old");
+    let web = write_config(dir.path(), "web/config.json", "hvs.old");
+    let hooks = Hooks::new(&HookConfig {
+        files: vec![file_hook(Some("**/api/config.json"), None, Some("echo busy >&2; exit 2"), Some("true"))],
+        ..HookConfig::default()
+    }).unwrap();
+
+    let stats = update_files_parallel(&[api.clone(), web.clone()], "hvs.new", &TokenFields::default(), &hooks, |_| {});
+    assert_eq!(stats.files[0].status, FileStatus::Failed);
+    assert_eq!(stats.files[0].error.as_deref(), Some("Pre-update hook `echo busy >&2; exit 2` exited with status 2"));
+    assert_eq!(stats.files[1].status, FileStatus::Updated);
+    assert_eq!(stats.errors.len(), 1);
+    assert_eq!(stats.exit_code(), ExitCode::PartialFailure);
+    assert_eq!(stats.hooks.len(), 1);
+    assert_eq!(stats.hooks[0].stderr, "busy\n");
+    assert_eq!(fs::read_to_string(api).unwrap(), r#"{"vaultToken": "hvs.old"}"#);
+    assert_eq!(fs::read_to_string(web).unwrap(), r#"{"vaultToken": "hvs.new"}"#);
+}
+
+#[test]
+fn test_failed_post_hook_is_a_partial_failure() {
+    let dir = TempDir::new().unwrap();
+    let api = write_config(dir.path(), "api/config.json", "hvs.old");
+    let web = write_config(dir.path(), "web/config.json", "hvs.old");
+    let hooks = Hooks::new(&HookConfig {
+        files: vec![file_hook(Some("**/api/*"), None, None, Some("exit 7"))],
+        ..HookConfig::default()
+    }).unwrap();
+
+    let stats = update_files_parallel(&[api.clone(), web], "hvs.new", &TokenFields::default(), &hooks, |_| {});
+    assert_eq!(stats.files_updated, 2);
+    assert_eq!(stats.errors, vec!["Post-update hook `exit 7` exited with status 7"]);
+    assert_eq!(stats.exit_code(), ExitCode::PartialFailure);
+    assert_eq!(stats.hooks[0].files, vec![api]);
+
+    let json = serde_json::to_value(&stats).unwrap();
+    assert_eq!(json["hooks"][0]["stage"], "post");
+    assert_eq!(json["hooks"][0]["status"], "failed");
+    assert_eq!(json["hooks"][0]["exit_code"], 7);
+}
+
+#[test]
+fn test_no_hooks_run_when_nothing_changes() {
+    let dir = TempDir::new().unwrap();
+    let log = dir.path().join("hooks.log");
+    let file = write_config(dir.path(), "api/config.json", "hvs.new");
+    let hooks = Hooks::new(&HookConfig {
+        post: Some(format!("echo global-post >> {}", log.display())),
+        files: vec![file_hook(Some("**"), None, None, Some(&format!("echo post >> {}", log.display())))],
+        ..HookConfig::default()
+    }).unwrap();
+
+    let stats = update_files_parallel(&[file], "hvs.new", &TokenFields::default(), &hooks, |_| {});
+    assert!(stats.hooks.is_empty());
+    assert!(!log.exists());
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use common::{error_response, lookup_response, MockVault};
+use tempfile::TempDir;
+use vault_config_updater::{
+    fingerprint, inspect_files, lookup_token_groups, ExitCode, TokenFields, TokenHealth, VaultClient, VaultConfig
+};
+
+/// Writes `content` to `<dir>/<name>/config.json`
+fn write_config(dir: &TempDir, name: &str, content: &str) -> PathBuf {
+    let service = dir.path().join(name);
+    fs::create_dir_all(&service).unwrap();
+    let path = service.join("config.json");
+    fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn test_groups_tokens_by_fingerprint() {
+    let temp_dir = TempDir::new().unwrap();
+    let a = write_config(&temp_dir, "a", r#"{"vaultToken": "hvs.shared"}"#);
+    let b = write_config(&temp_dir, "b", r#"{"vaultToken": "hvs.shared", "db": {"vaultToken": "hvs.shared"}}"#);
+    let c = write_config(&temp_dir, "c", r#"{"vaultToken": "hvs.stale"}"#);
+    let d = write_config(&temp_dir, "d", r#"{"host": "localhost"}"#);
+
+    let stats = inspect_files(&[a.clone(), b.clone(), c.clone(), d], &TokenFields::default());
+
+    assert_eq!(stats.files_scanned, 4);
+    assert_eq!(stats.files_with_tokens, 3);
+    assert_eq!(stats.distinct_tokens, 2);
+    let shared = stats.tokens.iter().find(|g| g.fingerprint == fingerprint("hvs.shared")).unwrap();
+    assert_eq!(shared.files, vec![a, b]);
+    assert_eq!(shared.occurrences, 3);
+    assert_eq!(shared.masked, "hvs.****");
+    assert_eq!(shared.health, None);
+    let stale = stats.tokens.iter().find(|g| g.fingerprint == fingerprint("hvs.stale")).unwrap();
+    assert_eq!(stale.files, vec![c]);
+    assert_eq!(stats.exit_code(), ExitCode::Success);
+
+    let json = serde_json::to_string(&stats).unwrap();
+    assert!(!json.contains("hvs.shared") && !json.contains("hvs.stale"), "token leaked: {}", json);
+}
+
+#[test]
+fn test_unreadable_files_are_reported() {
+    let temp_dir = TempDir::new().unwrap();
+    let good = write_config(&temp_dir, "a", r#"{"vaultToken": "hvs.one"}"#);
+    let missing = temp_dir.path().join("missing/config.json");
+
+    let stats = inspect_files(&[good, missing], &TokenFields::default());
+    assert_eq!(stats.errors.len(), 1);
+    assert!(stats.errors[0].contains("missing"));
+    assert_eq!(stats.exit_code(), ExitCode::PartialFailure);
+}
+
+#[test]
+fn test_lookup_flags_expiring_and_invalid_tokens() {
+    let vault = MockVault::start(|request| match request.token.as_deref() {
+        Some("hvs.healthy") => (200, lookup_response(30 * 86_400, &["app"])),
+        Some("hvs.expiring") => (200, lookup_response(3_600, &["app"])),
+        Some("hvs.root") => (200, lookup_response(0, &["root"])),
+        _ => (403, error_response("permission denied")),
+    });
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    let files = vec![
+        write_config(&temp_dir, "a", r#"{"vaultToken": "hvs.healthy"}"#),
+        write_config(&temp_dir, "b", r#"{"vaultToken": "hvs.expiring"}"#),
+        write_config(&temp_dir, "c", r#"{"vaultToken": "hvs.root"}"#),
+        write_config(&temp_dir, "d", r#"{"vaultToken": "hvs.revoked"}"#),
+    ];
+
+    let mut stats = inspect_files(&files, &TokenFields::default());
+    lookup_token_groups(&mut stats, &client, Duration::from_secs(7 * 86_400));
+
+    let health = |token: &str| stats.tokens.iter().find(|g| g.fingerprint == fingerprint(token)).unwrap().health;
+    assert_eq!(health("hvs.healthy"), Some(TokenHealth::Valid));
+    assert_eq!(health("hvs.expiring"), Some(TokenHealth::Expiring));
+    assert_eq!(health("hvs.root"), Some(TokenHealth::Valid));
+    assert_eq!(health("hvs.revoked"), Some(TokenHealth::Invalid));
+    assert_eq!(stats.flagged().count(), 2);
+    assert_eq!(stats.exit_code(), ExitCode::ValidationFailed);
+    assert_eq!(vault.requests().len(), 4);
+}
+
+#[test]
+fn test_empty_token_fields_are_not_looked_up() {
+    let vault = MockVault::start(|request| match request.token.as_deref() {
+        Some("hvs.healthy") => (200, lookup_response(30 * 86_400, &["app"])),
+        _ => (403, error_response("permission denied")),
+    });
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    let files = vec![
+        write_config(&temp_dir, "a", r#"{"vaultToken": "hvs.healthy"}"#),
+        write_config(&temp_dir, "b", r#"{"vaultToken": ""}"#),
+    ];
+
+    let mut stats = inspect_files(&files, &TokenFields::default());
+    assert_eq!(stats.files_with_tokens, 1);
+    assert_eq!(stats.distinct_tokens, 1);
+    lookup_token_groups(&mut stats, &client, Duration::from_secs(7 * 86_400));
+    assert_eq!(stats.flagged().count(), 0);
+    assert_eq!(stats.exit_code(), ExitCode::Success);
+    assert_eq!(vault.requests().len(), 1);
+}
@@ -1,6 +1,38 @@
+mod common;
+
 use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use common::{error_response, lookup_response, MockVault};
 use tempfile::TempDir;
-use vault_config_updater::{find_config_files, update_vault_tokens_in_files, scan_vault_tokens_in_files};
+use vault_config_updater::{
+    find_config_files, update_vault_tokens_in_files, scan_vault_tokens_in_files,
+    ExitCode, UpdateStats, ScanStats
+};
+
+/// Runs the compiled binary inside `dir` and returns its exit code
+fn run_binary(dir: &Path, args: &[&str], stdin: Option<&str>) -> i32 {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_vault-config-updater"))
+        .current_dir(dir)
+        // Keep a config file in the user's home out of the tests
+        .env("XDG_CONFIG_HOME", dir)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    {
+        let mut child_stdin = child.stdin.take().unwrap();
+        if let Some(input) = stdin {
+            child_stdin.write_all(input.as_bytes()).unwrap();
+        }
+    }
+
+    child.wait().unwrap().code().unwrap()
+}
 
 #[test]
 fn test_end_to_end_workflow() {
@@ -294,4 +326,824 @@ fn test_dry_run_end_to_end_workflow() {
     assert!(current_global.contains("hvs.old-token-2"));
     assert!(current_global.contains("hvs.old-token-3"));
     assert!(current_nested.contains("hvs.old-token-4"));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_exit_code_values_are_stable() {
+    assert_eq!(ExitCode::Success.code(), 0);
+    assert_eq!(ExitCode::Failure.code(), 1);
+    assert_eq!(ExitCode::Usage.code(), 2);
+    assert_eq!(ExitCode::NothingToDo.code(), 3);
+    assert_eq!(ExitCode::PartialFailure.code(), 4);
+    assert_eq!(ExitCode::ValidationFailed.code(), 5);
+    assert_eq!(ExitCode::DriftDetected.code(), 6);
+}
+
+#[test]
+fn test_exit_code_from_stats() {
+    let mut stats = UpdateStats::new();
+    stats.files_processed = 2;
+    assert_eq!(stats.exit_code(), ExitCode::NothingToDo);
+
+    stats.files_updated = 2;
+    assert_eq!(stats.exit_code(), ExitCode::Success);
+
+    stats.files_updated = 1;
+    stats.add_error("boom".to_string());
+    assert_eq!(stats.exit_code(), ExitCode::PartialFailure);
+
+    stats.files_updated = 0;
+    stats.add_error("boom".to_string());
+    assert_eq!(stats.exit_code(), ExitCode::Failure);
+
+    let mut scan = ScanStats::new();
+    scan.files_scanned = 1;
+    scan.files_with_tokens = 1;
+    assert_eq!(scan.exit_code(), ExitCode::Success);
+}
+
+#[test]
+fn test_binary_exit_code_success() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+
+    let code = run_binary(temp_dir.path(), &["hvs.new"], None);
+    assert_eq!(code, ExitCode::Success.code() as i32);
+}
+
+#[test]
+fn test_binary_exit_code_nothing_to_do() {
+    let temp_dir = TempDir::new().unwrap();
+
+    let code = run_binary(temp_dir.path(), &["hvs.new"], None);
+    assert_eq!(code, ExitCode::NothingToDo.code() as i32);
+
+    fs::write(temp_dir.path().join("config.json"), r#"{"apiKey": "key"}"#).unwrap();
+    let code = run_binary(temp_dir.path(), &["hvs.new"], None);
+    assert_eq!(code, ExitCode::NothingToDo.code() as i32);
+
+    let code = run_binary(temp_dir.path(), &["--dry-run"], None);
+    assert_eq!(code, ExitCode::NothingToDo.code() as i32);
+}
+
+#[test]
+fn test_binary_exit_code_partial_failure() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    // Invalid UTF-8 cannot be read as a string, so this file fails
+    fs::write(temp_dir.path().join("globalConfig.json"), [0xff, 0xfe, 0xfd]).unwrap();
+
+    let code = run_binary(temp_dir.path(), &["hvs.new"], None);
+    assert_eq!(code, ExitCode::PartialFailure.code() as i32);
+}
+
+#[test]
+fn test_binary_exit_code_total_failure() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("config.json"), [0xff, 0xfe, 0xfd]).unwrap();
+
+    let code = run_binary(temp_dir.path(), &["hvs.new"], None);
+    assert_eq!(code, ExitCode::Failure.code() as i32);
+
+    let code = run_binary(temp_dir.path(), &["hvs.new", "nonexistent/directory"], None);
+    assert_eq!(code, ExitCode::Failure.code() as i32);
+}
+
+#[test]
+fn test_binary_exit_code_validation_failure() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+
+    // An empty token entered at the prompt is rejected before any file is touched
+    let code = run_binary(temp_dir.path(), &[], Some("\n"));
+    assert_eq!(code, ExitCode::ValidationFailed.code() as i32);
+}
+
+#[test]
+fn test_binary_exit_code_usage_error() {
+    let temp_dir = TempDir::new().unwrap();
+    let code = run_binary(temp_dir.path(), &["--no-such-flag"], None);
+    assert_eq!(code, ExitCode::Usage.code() as i32);
+}
+
+#[test]
+fn test_binary_json_output_masks_token() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_vault-config-updater"))
+        .current_dir(temp_dir.path())
+        .args(["--output", "json", "hvs.json-secret"])
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("json-secret"));
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(report["stats"]["files_updated"], 1);
+    assert_eq!(report["exit_code"], 0);
+}
+
+#[test]
+fn test_binary_reads_token_from_stdin() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.json");
+    fs::write(&config_path, r#"{"vaultToken": "hvs.old"}"#).unwrap();
+
+    let code = run_binary(temp_dir.path(), &["--token-stdin", "."], Some("hvs.piped-token\n"));
+    assert_eq!(code, ExitCode::Success.code() as i32);
+    assert!(fs::read_to_string(&config_path).unwrap().contains("hvs.piped-token"));
+}
+
+#[test]
+fn test_binary_refuses_to_rotate_invalid_token() {
+    let vault = MockVault::start(|request| match request.token.as_deref() {
+        Some("hvs.valid") => (200, lookup_response(3600, &["default"])),
+        _ => (403, error_response("permission denied")),
+    });
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.json");
+    fs::write(&config_path, r#"{"vaultToken": "hvs.old"}"#).unwrap();
+
+    let code = run_binary(temp_dir.path(), &["--vault-addr", &vault.addr, "--validate-token", "hvs.typo"], None);
+    assert_eq!(code, ExitCode::ValidationFailed.code() as i32);
+    assert!(fs::read_to_string(&config_path).unwrap().contains("hvs.old"));
+
+    let code = run_binary(temp_dir.path(), &["--vault-addr", &vault.addr, "--min-ttl", "2h", "hvs.valid"], None);
+    assert_eq!(code, ExitCode::ValidationFailed.code() as i32);
+
+    let code = run_binary(temp_dir.path(), &["--vault-addr", &vault.addr, "--min-ttl", "30m", "hvs.valid"], None);
+    assert_eq!(code, ExitCode::Success.code() as i32);
+    assert!(fs::read_to_string(&config_path).unwrap().contains("hvs.valid"));
+}
+
+#[test]
+fn test_binary_rotates_to_token_from_approle_login() {
+    let vault = MockVault::start(|request| match request.path.as_str() {
+        "/v1/auth/approle/login" => (200, serde_json::json!({
+            "auth": { "client_token": "hvs.issued-by-login", "policies": ["app"], "lease_duration": 60 }
+        }).to_string()),
+        _ => (404, error_response("not found")),
+    });
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.json");
+    fs::write(&config_path, r#"{"vaultToken": "hvs.old"}"#).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_vault-config-updater"))
+        .current_dir(temp_dir.path())
+        .env("VAULT_SECRET_ID", "secret-1")
+        .args(["--vault-addr", &vault.addr, "--login", "approle", "--role-id", "role-1"])
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+
+    assert_eq!(status.code(), Some(ExitCode::Success.code() as i32));
+    assert!(fs::read_to_string(&config_path).unwrap().contains("hvs.issued-by-login"));
+    assert_eq!(vault.requests()[0].json()["secret_id"], "secret-1");
+}
+
+#[test]
+fn test_binary_rotates_to_unwrapped_token() {
+    let vault = MockVault::start(|request| match (request.path.as_str(), request.token.as_deref()) {
+        ("/v1/sys/wrapping/unwrap", Some("hvs.wrapping")) => (200, serde_json::json!({
+            "auth": { "client_token": "hvs.unwrapped", "policies": ["app"], "lease_duration": 60 }
+        }).to_string()),
+        _ => (400, error_response("wrapping token is not valid or does not exist")),
+    });
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.json");
+    fs::write(&config_path, r#"{"vaultToken": "hvs.old"}"#).unwrap();
+
+    let args = ["--vault-addr", &vault.addr, "--unwrap", "--token-stdin"];
+    let code = run_binary(temp_dir.path(), &args, Some("hvs.wrapping\n"));
+    assert_eq!(code, ExitCode::Success.code() as i32);
+    let content = fs::read_to_string(&config_path).unwrap();
+    assert!(content.contains("hvs.unwrapped"));
+    assert!(!content.contains("hvs.wrapping"));
+
+    // A wrapping token can only be unwrapped once
+    let code = run_binary(temp_dir.path(), &args, Some("hvs.wrapping-used\n"));
+    assert_eq!(code, ExitCode::ValidationFailed.code() as i32);
+    assert!(fs::read_to_string(&config_path).unwrap().contains("hvs.unwrapped"));
+}
+
+#[test]
+fn test_binary_revokes_old_tokens_after_update() {
+    let vault = MockVault::start(|request| match request.path.as_str() {
+        "/v1/auth/token/revoke-self" => (204, String::new()),
+        _ => (404, error_response("not found")),
+    });
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    let nested = temp_dir.path().join("svc");
+    fs::create_dir(&nested).unwrap();
+    fs::write(nested.join("config.json"), r#"{"vaultToken": "hvs.old", "b": {"vaultToken": "hvs.new"}}"#).unwrap();
+
+    let code = run_binary(temp_dir.path(), &["--vault-addr", &vault.addr, "--revoke-old", "hvs.new"], None);
+    assert_eq!(code, ExitCode::Success.code() as i32);
+
+    // The old token is revoked once; the new one, already present in a file, is kept
+    let requests = vault.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].token.as_deref(), Some("hvs.old"));
+}
+
+#[test]
+fn test_binary_refuses_to_revoke_the_parent_of_the_new_token() {
+    let vault = MockVault::start(|request| match request.path.as_str() {
+        "/v1/auth/token/create" => (200, serde_json::json!({
+            "auth": {"client_token": "hvs.child", "policies": ["default"], "lease_duration": 3600, "orphan": false}
+        }).to_string()),
+        _ => (204, String::new()),
+    });
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+
+    // The child of the deployed token would die with it
+    let args = ["--vault-addr", &vault.addr, "--login", "token", "--token-stdin", "--revoke-old"];
+    let code = run_binary(temp_dir.path(), &args, Some("hvs.old\n"));
+    assert_eq!(code, ExitCode::ValidationFailed.code() as i32);
+    assert_eq!(fs::read_to_string(temp_dir.path().join("config.json")).unwrap(), r#"{"vaultToken": "hvs.old"}"#);
+    let paths: Vec<String> = vault.requests().into_iter().map(|request| request.path).collect();
+    assert_eq!(paths, vec!["/v1/auth/token/create"]);
+}
+
+#[test]
+fn test_binary_skips_revocation_when_a_file_fails() {
+    let vault = MockVault::start(|_| (204, String::new()));
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    fs::write(temp_dir.path().join("globalConfig.json"), [0xff, 0xfe, 0xfd]).unwrap();
+
+    let code = run_binary(temp_dir.path(), &["--vault-addr", &vault.addr, "--revoke-old", "hvs.new"], None);
+    assert_eq!(code, ExitCode::PartialFailure.code() as i32);
+    assert!(vault.requests().is_empty());
+}
+
+#[test]
+fn test_binary_inspect_reports_shared_tokens() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("config.json"), r#"{"vaultToken": "hvs.shared"}"#).unwrap();
+    fs::write(temp_dir.path().join("globalConfig.json"), r#"{"vaultToken": "hvs.shared"}"#).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_vault-config-updater"))
+        .current_dir(temp_dir.path())
+        .args(["--output", "json", "inspect"])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(ExitCode::Success.code() as i32));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("hvs.shared"));
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(report["mode"], "inspect");
+    assert_eq!(report["stats"]["distinct_tokens"], 1);
+    assert_eq!(report["stats"]["tokens"][0]["files"].as_array().unwrap().len(), 2);
+    // Inspection never modifies files
+    assert!(fs::read_to_string(temp_dir.path().join("config.json")).unwrap().contains("hvs.shared"));
+}
+
+#[test]
+fn test_binary_verify_detects_drift() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("config.json"), r#"{"vaultToken": "hvs.expected"}"#).unwrap();
+
+    let code = run_binary(temp_dir.path(), &["verify", "--token-stdin"], Some("hvs.expected\n"));
+    assert_eq!(code, ExitCode::Success.code() as i32);
+
+    fs::write(temp_dir.path().join("globalConfig.json"), r#"{"vaultToken": "hvs.stale"}"#).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_vault-config-updater"))
+        .current_dir(temp_dir.path())
+        .args(["--output", "json", "verify", "--token-fingerprint", &vault_config_updater::fingerprint("hvs.expected")])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(ExitCode::DriftDetected.code() as i32));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["mode"], "verify");
+    assert_eq!(report["stats"]["files_drifted"], 1);
+    assert!(report["stats"]["drifted"][0].as_str().unwrap().ends_with("globalConfig.json"));
+}
+
+#[test]
+fn test_binary_update_and_scan_subcommands() {
+    let temp_dir = TempDir::new().unwrap();
+    let services = temp_dir.path().join("services");
+    fs::create_dir(&services).unwrap();
+    let config_path = services.join("config.json");
+    fs::write(&config_path, r#"{"vaultToken": "hvs.old"}"#).unwrap();
+
+    let code = run_binary(temp_dir.path(), &["scan", "services"], None);
+    assert_eq!(code, ExitCode::Success.code() as i32);
+    assert!(fs::read_to_string(&config_path).unwrap().contains("hvs.old"));
+
+    let code = run_binary(temp_dir.path(), &["-q", "update", "--token-stdin", "services"], Some("hvs.new\n"));
+    assert_eq!(code, ExitCode::Success.code() as i32);
+    assert!(fs::read_to_string(&config_path).unwrap().contains("hvs.new"));
+}
+
+#[test]
+fn test_discovered_config_profile_selects_files_and_keys() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("services/prod")).unwrap();
+    fs::create_dir_all(root.join("services/dev")).unwrap();
+    fs::create_dir_all(root.join("tools")).unwrap();
+    fs::write(root.join("services/prod/config.json"), r#"{"vault_token": "hvs.old"}"#).unwrap();
+    fs::write(root.join("services/dev/config.json"), r#"{"vault_token": "hvs.old"}"#).unwrap();
+    fs::write(root.join(".vault-config-updater.toml"), r#"
+[defaults]
+keys = ["vault_token"]
+
+[profiles.prod]
+paths = ["services"]
+include = ["prod/**"]
+"#).unwrap();
+
+    // Discovered from a subdirectory: its keys and globs apply, but not its paths
+    let code = run_binary(&root.join("tools"), &["--profile", "prod", "--token-stdin", "../services"], Some("hvs.new\n"));
+    assert_eq!(code, 0);
+    assert_eq!(fs::read_to_string(root.join("services/prod/config.json")).unwrap(), r#"{"vault_token": "hvs.new"}"#);
+    assert_eq!(fs::read_to_string(root.join("services/dev/config.json")).unwrap(), r#"{"vault_token": "hvs.old"}"#);
+
+    // Named with --config, the profile's root is relative to the config file
+    let args = ["--config", "../.vault-config-updater.toml", "--profile", "prod", "--token-stdin"];
+    assert_eq!(run_binary(&root.join("tools"), &args, Some("hvs.newer\n")), 0);
+    assert_eq!(fs::read_to_string(root.join("services/prod/config.json")).unwrap(), r#"{"vault_token": "hvs.newer"}"#);
+    assert_eq!(fs::read_to_string(root.join("services/dev/config.json")).unwrap(), r#"{"vault_token": "hvs.old"}"#);
+
+    assert_eq!(run_binary(root, &["--profile", "staging", "--token-stdin"], Some("hvs.new\n")), 5);
+}
+
+#[test]
+fn test_binary_reports_each_root_and_reads_files_from_stdin() {
+    let temp_dir = TempDir::new().unwrap();
+    for dir in ["api", "web", "listed"] {
+        fs::create_dir_all(temp_dir.path().join(dir)).unwrap();
+        fs::write(temp_dir.path().join(dir).join("config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    }
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_vault-config-updater"))
+        .current_dir(temp_dir.path())
+        .env("XDG_CONFIG_HOME", temp_dir.path())
+        .env("NEW_VAULT_TOKEN", "hvs.new")
+        .args(["--output", "json", "--token-env", "NEW_VAULT_TOKEN", "--files-from", "-", "api", "web"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap()
+        .write_all(b"listed/config.json\n./api/config.json\nlisted/notes.txt\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(output.status.code(), Some(ExitCode::Success.code() as i32));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["stats"]["files_updated"], 3);
+    let roots = report["roots"].as_array().unwrap();
+    assert_eq!(roots.len(), 3);
+    assert_eq!(roots[0]["path"], "api");
+    assert_eq!(roots[1]["statuses"]["updated"], 1);
+    // ./api/config.json was already found under the api root
+    assert_eq!(roots[2]["path"], "-");
+    assert_eq!(roots[2]["files"], 1);
+    assert_eq!(fs::read_to_string(temp_dir.path().join("listed/config.json")).unwrap(), r#"{"vaultToken": "hvs.new"}"#);
+}
+
+#[test]
+fn test_binary_writes_mapped_tokens_and_reports_unmatched_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    for dir in ["prod", "dev", "docs"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+        fs::write(root.join(dir).join("config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    }
+    fs::write(root.join("map.toml"), r#"
+[[rule]]
+path = "prod/**"
+source = { env = "PROD_TOKEN" }
+
+[[rule]]
+path = "dev/**"
+source = { env = "DEV_TOKEN" }
+"#).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_vault-config-updater"))
+        .current_dir(root)
+        .env("XDG_CONFIG_HOME", root)
+        .env("PROD_TOKEN", "hvs.prod")
+        .env("DEV_TOKEN", "hvs.dev")
+        .args(["--output", "json", "--mapping", "map.toml"])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+
+    // The unmatched file keeps its old token, so the rotation is incomplete
+    assert_eq!(output.status.code(), Some(ExitCode::PartialFailure.code() as i32));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["stats"]["files_updated"], 2);
+    assert_eq!(report["stats"]["unmatched"], serde_json::json!(["./docs/config.json"]));
+    assert_eq!(report["mapped_tokens"].as_array().unwrap().len(), 2);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("hvs.prod"));
+    assert_eq!(fs::read_to_string(root.join("prod/config.json")).unwrap(), r#"{"vaultToken": "hvs.prod"}"#);
+    assert_eq!(fs::read_to_string(root.join("dev/config.json")).unwrap(), r#"{"vaultToken": "hvs.dev"}"#);
+    assert_eq!(fs::read_to_string(root.join("docs/config.json")).unwrap(), r#"{"vaultToken": "hvs.old"}"#);
+
+    // A source that cannot be read stops the run before any file changes
+    let code = Command::new(env!("CARGO_BIN_EXE_vault-config-updater"))
+        .current_dir(root)
+        .env("XDG_CONFIG_HOME", root)
+        .env_remove("PROD_TOKEN")
+        .env("DEV_TOKEN", "hvs.dev2")
+        .args(["--quiet", "--mapping", "map.toml"])
+        .stdin(Stdio::null())
+        .status()
+        .unwrap()
+        .code();
+    assert_ne!(code, Some(0));
+    assert_eq!(fs::read_to_string(root.join("dev/config.json")).unwrap(), r#"{"vaultToken": "hvs.dev"}"#);
+
+    // Rule paths match below an absolute search root too
+    let code = Command::new(env!("CARGO_BIN_EXE_vault-config-updater"))
+        .current_dir(root)
+        .env("XDG_CONFIG_HOME", root)
+        .env("PROD_TOKEN", "hvs.prod2")
+        .env("DEV_TOKEN", "hvs.dev2")
+        .args(["--quiet", "--mapping", "map.toml", root.to_str().unwrap()])
+        .stdin(Stdio::null())
+        .status()
+        .unwrap()
+        .code();
+    assert_eq!(code, Some(ExitCode::PartialFailure.code() as i32));
+    assert_eq!(fs::read_to_string(root.join("prod/config.json")).unwrap(), r#"{"vaultToken": "hvs.prod2"}"#);
+    assert_eq!(fs::read_to_string(root.join("dev/config.json")).unwrap(), r#"{"vaultToken": "hvs.dev2"}"#);
+}
+
+#[test]
+fn test_binary_records_updates_in_the_audit_log() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("api")).unwrap();
+    fs::write(root.join("api/config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+
+    let args = ["--audit-log", "audit.jsonl", "--token-stdin"];
+    assert_eq!(run_binary(root, &args, Some("hvs.first\n")), ExitCode::Success.code() as i32);
+    assert_eq!(run_binary(root, &args, Some("hvs.second\n")), ExitCode::Success.code() as i32);
+    // Nothing changes, so nothing is recorded
+    assert_eq!(run_binary(root, &args, Some("hvs.second\n")), ExitCode::Success.code() as i32);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_vault-config-updater"))
+        .current_dir(root)
+        .env("XDG_CONFIG_HOME", root)
+        .args(["history", "--audit-log", "audit.jsonl", "--output", "json", "api"])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(ExitCode::Success.code() as i32));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["mode"], "history");
+    let entries = report["stats"]["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["path"], root.canonicalize().unwrap().join("api/config.json").to_str().unwrap());
+    assert_eq!(entries[1]["old_fingerprints"], entries[0]["new_fingerprints"]);
+    assert_ne!(entries[0]["run_id"], entries[1]["run_id"]);
+    assert!(!fs::read_to_string(root.join("audit.jsonl")).unwrap().contains("hvs."));
+
+    // Without a log to read, history is a usage mistake
+    assert_eq!(run_binary(root, &["history"], None), ExitCode::ValidationFailed.code() as i32);
+}
+
+#[test]
+fn test_binary_applies_the_git_policy() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("api")).unwrap();
+    fs::create_dir_all(root.join("web")).unwrap();
+    fs::write(root.join("api/config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    fs::write(root.join("web/config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    let git = |args: &[&str]| assert!(Command::new("git").current_dir(root).args(args).status().unwrap().success());
+    git(&["init", "-q"]);
+    git(&["add", "api/config.json"]);
+
+    // Nothing is written while a file is exposed
+    let code = run_binary(root, &["--token-stdin", "--git-policy", "fail"], Some("hvs.new\n"));
+    assert_eq!(code, ExitCode::ValidationFailed.code() as i32);
+    assert_eq!(fs::read_to_string(root.join("web/config.json")).unwrap(), r#"{"vaultToken": "hvs.old"}"#);
+
+    // --gitignore makes the untracked file safe; the tracked one is left alone
+    let code = run_binary(root, &["--token-stdin", "--git-policy", "skip", "--gitignore"], Some("hvs.new\n"));
+    assert_eq!(code, ExitCode::Success.code() as i32);
+    assert_eq!(fs::read_to_string(root.join(".gitignore")).unwrap(), "/web/config.json\n");
+    assert_eq!(fs::read_to_string(root.join("api/config.json")).unwrap(), r#"{"vaultToken": "hvs.old"}"#);
+    assert_eq!(fs::read_to_string(root.join("web/config.json")).unwrap(), r#"{"vaultToken": "hvs.new"}"#);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_vault-config-updater"))
+        .current_dir(root)
+        .env("XDG_CONFIG_HOME", root)
+        .args(["--token-stdin", "--output", "json"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            child.stdin.take().unwrap().write_all(b"hvs.newer\n")?;
+            child.wait_with_output()
+        })
+        .unwrap();
+    assert_eq!(output.status.code(), Some(ExitCode::Success.code() as i32));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let exposed = report["stats"]["git_exposed"].as_array().unwrap();
+    assert_eq!(exposed.len(), 1);
+    assert_eq!(exposed[0]["path"], "./api/config.json");
+    assert_eq!(exposed[0]["status"], "tracked");
+    assert_eq!(fs::read_to_string(root.join("api/config.json")).unwrap(), r#"{"vaultToken": "hvs.newer"}"#);
+}
+
+#[test]
+fn test_binary_keeps_tokens_of_files_skipped_by_the_git_policy() {
+    let vault = MockVault::start(|_| (204, String::new()));
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    for (dir, token) in [("api", "hvs.old"), ("web", "hvs.old"), ("cache", "hvs.older")] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+        fs::write(root.join(dir).join("config.json"), format!(r#"{{"vaultToken": "{}"}}"#, token)).unwrap();
+    }
+    fs::write(root.join(".gitignore"), "/web/\n/cache/\n").unwrap();
+    let git = |args: &[&str]| assert!(Command::new("git").current_dir(root).args(args).status().unwrap().success());
+    git(&["init", "-q"]);
+    git(&["add", "api/config.json"]);
+
+    let args = ["--vault-addr", &vault.addr, "--git-policy", "skip", "--revoke-old", "hvs.new"];
+    assert_eq!(run_binary(root, &args, None), ExitCode::Success.code() as i32);
+    assert_eq!(fs::read_to_string(root.join("api/config.json")).unwrap(), r#"{"vaultToken": "hvs.old"}"#);
+    assert_eq!(fs::read_to_string(root.join("web/config.json")).unwrap(), r#"{"vaultToken": "hvs.new"}"#);
+
+    // The tracked file still uses hvs.old, so only hvs.older is revoked
+    let tokens: Vec<Option<String>> = vault.requests().into_iter().map(|request| request.token).collect();
+    assert_eq!(tokens, vec![Some("hvs.older".to_string())]);
+}
+
+#[test]
+fn test_binary_ignores_hooks_from_a_discovered_project_config() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join(".vault-config-updater.toml"), "[defaults.hooks]\npost = \"touch hook-ran\"\n").unwrap();
+
+    // Found by walking up from the working directory: the hook does not run
+    fs::write(root.join("config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    assert_eq!(run_binary(root, &["hvs.new"], None), ExitCode::Success.code() as i32);
+    assert_eq!(fs::read_to_string(root.join("config.json")).unwrap(), r#"{"vaultToken": "hvs.new"}"#);
+    assert!(!root.join("hook-ran").exists());
+
+    // Named with --config: it does
+    assert_eq!(run_binary(root, &["--config", ".vault-config-updater.toml", "hvs.newer"], None), ExitCode::Success.code() as i32);
+    assert!(root.join("hook-ran").exists());
+
+    // So do the hooks of the user's own config file
+    fs::remove_file(root.join("hook-ran")).unwrap();
+    fs::remove_file(root.join(".vault-config-updater.toml")).unwrap();
+    fs::create_dir_all(root.join("vault-config-updater")).unwrap();
+    fs::write(root.join("vault-config-updater/config.toml"), "[defaults.hooks]\npost = \"touch hook-ran\"\n").unwrap();
+    assert_eq!(run_binary(root, &["hvs.newest"], None), ExitCode::Success.code() as i32);
+    assert!(root.join("hook-ran").exists());
+}
+
+#[test]
+fn test_binary_ignores_vault_addr_paths_and_audit_log_from_a_discovered_project_config() {
+    let trusted = MockVault::start(|_| (200, lookup_response(3600, &["default"])));
+    let planted = MockVault::start(|_| (200, lookup_response(3600, &["default"])));
+    let temp_dir = TempDir::new().unwrap();
+    let outside = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join("config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    fs::write(outside.path().join("config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    fs::write(root.join(".vault-config-updater.toml"), format!(
+        "[defaults]\nvault-addr = \"{}\"\npaths = [\"{}\"]\naudit-log = \"audit.jsonl\"\n",
+        planted.addr, outside.path().display()
+    )).unwrap();
+
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_vault-config-updater"))
+        .current_dir(root)
+        .env("XDG_CONFIG_HOME", root)
+        .env("VAULT_ADDR", &trusted.addr)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .unwrap()
+        .code();
+
+    // Found by walking up: VAULT_ADDR, the working directory and no audit log are used instead
+    assert_eq!(run(&["--validate-token", "hvs.new"]), Some(ExitCode::Success.code() as i32));
+    assert_eq!(trusted.requests().len(), 1);
+    assert!(planted.requests().is_empty());
+    assert_eq!(fs::read_to_string(root.join("config.json")).unwrap(), r#"{"vaultToken": "hvs.new"}"#);
+    assert_eq!(fs::read_to_string(outside.path().join("config.json")).unwrap(), r#"{"vaultToken": "hvs.old"}"#);
+    assert!(!root.join("audit.jsonl").exists());
+
+    // Named with --config, all of them apply
+    assert_eq!(run(&["--config", ".vault-config-updater.toml", "--validate-token", "hvs.newer"]), Some(ExitCode::Success.code() as i32));
+    assert_eq!(trusted.requests().len(), 1);
+    assert_eq!(planted.requests().len(), 1);
+    assert_eq!(fs::read_to_string(outside.path().join("config.json")).unwrap(), r#"{"vaultToken": "hvs.newer"}"#);
+    assert!(root.join("audit.jsonl").exists());
+}
+
+#[test]
+fn test_binary_matches_file_hooks_below_an_absolute_root() {
+    let temp_dir = TempDir::new().unwrap();
+    let work_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("svc")).unwrap();
+    fs::write(root.join("svc/config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    let config = work_dir.path().join("settings.toml");
+    fs::write(&config, "[[defaults.hooks.files]]\npath = \"svc/*.json\"\npost = \"touch {dir}/.reload\"\n").unwrap();
+
+    let args = ["--config", config.to_str().unwrap(), "hvs.new", root.to_str().unwrap()];
+    assert_eq!(run_binary(work_dir.path(), &args, None), ExitCode::Success.code() as i32);
+    assert_eq!(fs::read_to_string(root.join("svc/config.json")).unwrap(), r#"{"vaultToken": "hvs.new"}"#);
+    assert!(root.join("svc/.reload").exists());
+}
+
+/// Polls until `path` holds `expected`, for up to ten seconds
+fn wait_for_content(path: &Path, expected: &str) -> bool {
+    for _ in 0..100 {
+        if fs::read_to_string(path).is_ok_and(|content| content == expected) {
+            return true;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    false
+}
+
+#[test]
+fn test_binary_watch_applies_new_files_and_tokens_until_sigterm() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("services/api")).unwrap();
+    fs::write(root.join("services/api/config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    fs::write(root.join("sink"), "hvs.first\n").unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_vault-config-updater"))
+        .current_dir(root)
+        .env("XDG_CONFIG_HOME", root)
+        .args(["watch", "--token-file", "sink", "--debounce", "100ms", "services"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    assert!(wait_for_content(&root.join("services/api/config.json"), r#"{"vaultToken": "hvs.first"}"#));
+    std::thread::sleep(std::time::Duration::from_millis(300));
+
+    fs::create_dir_all(root.join("services/web")).unwrap();
+    fs::write(root.join("services/web/config.json"), r#"{"vaultToken": ""}"#).unwrap();
+    assert!(wait_for_content(&root.join("services/web/config.json"), r#"{"vaultToken": "hvs.first"}"#));
+
+    fs::write(root.join("sink.tmp"), "hvs.second\n").unwrap();
+    fs::rename(root.join("sink.tmp"), root.join("sink")).unwrap();
+    assert!(wait_for_content(&root.join("services/api/config.json"), r#"{"vaultToken": "hvs.second"}"#));
+    assert!(wait_for_content(&root.join("services/web/config.json"), r#"{"vaultToken": "hvs.second"}"#));
+
+    let status = Command::new("kill").args(["-TERM", &child.id().to_string()]).status().unwrap();
+    assert!(status.success());
+    assert_eq!(child.wait().unwrap().code(), Some(0));
+}
+
+#[test]
+fn test_binary_watch_unwraps_agent_sinks() {
+    let vault = MockVault::start(|request| match (request.path.as_str(), request.token.as_deref()) {
+        ("/v1/sys/wrapping/unwrap", Some("hvs.wrap-1")) => (200, serde_json::json!({
+            "auth": { "client_token": "hvs.agent-1", "policies": [], "lease_duration": 60 }
+        }).to_string()),
+        ("/v1/sys/wrapping/unwrap", Some("hvs.wrap-2")) => (200, serde_json::json!({
+            "auth": { "client_token": "hvs.agent-2", "policies": [], "lease_duration": 60 }
+        }).to_string()),
+        _ => (400, error_response("wrapping token is not valid or does not exist")),
+    });
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("services")).unwrap();
+    fs::create_dir_all(root.join("agent")).unwrap();
+    fs::write(root.join("services/config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    let wrap_info = |token: &str| serde_json::json!({ "token": token, "ttl": 300, "creation_path": "sys/wrapping/wrap" }).to_string();
+    fs::write(root.join("agent/sink"), wrap_info("hvs.wrap-1")).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_vault-config-updater"))
+        .current_dir(root)
+        .env("XDG_CONFIG_HOME", root)
+        .args([
+            "watch", "--sink", "agent/sink", "--sink", "agent/other-sink", "--vault-addr", &vault.addr,
+            "--debounce", "100ms", "--min-interval", "0s", "services",
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    assert!(wait_for_content(&root.join("services/config.json"), r#"{"vaultToken": "hvs.agent-1"}"#));
+    std::thread::sleep(std::time::Duration::from_millis(300));
+
+    // The agent renews the token and writes new wrap info to its second sink
+    fs::write(root.join("agent/other-sink"), wrap_info("hvs.wrap-2")).unwrap();
+    assert!(wait_for_content(&root.join("services/config.json"), r#"{"vaultToken": "hvs.agent-2"}"#));
+
+    let status = Command::new("kill").args(["-TERM", &child.id().to_string()]).status().unwrap();
+    assert!(status.success());
+    assert_eq!(child.wait().unwrap().code(), Some(0));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_binary_serve_rotates_on_request_until_sigterm() {
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::net::UnixStream;
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("services/api")).unwrap();
+    fs::write(root.join("services/api/config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    let socket = root.join("ctl.sock");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_vault-config-updater"))
+        .current_dir(root)
+        .env("XDG_CONFIG_HOME", root)
+        .args(["serve", "--socket", "ctl.sock", "services"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let mut stream = None;
+    for _ in 0..100 {
+        if let Ok(connected) = UnixStream::connect(&socket) {
+            stream = Some(connected);
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    let mut stream = stream.expect("the server did not start listening");
+    writeln!(stream, r#"{{"command": "rotate", "token": "hvs.new"}}"#).unwrap();
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line).unwrap();
+    let response: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(response["ok"], true);
+    assert_eq!(response["result"]["stats"]["files_updated"], 1);
+    assert_eq!(fs::read_to_string(root.join("services/api/config.json")).unwrap(), r#"{"vaultToken": "hvs.new"}"#);
+
+    let status = Command::new("kill").args(["-TERM", &child.id().to_string()]).status().unwrap();
+    assert!(status.success());
+    assert_eq!(child.wait().unwrap().code(), Some(0));
+    assert!(!socket.exists());
+}
+
+#[test]
+fn test_binary_serve_accepts_signed_webhooks() {
+    use vault_config_updater::{sign_webhook, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("services/api")).unwrap();
+    fs::write(root.join("services/api/config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    fs::write(root.join("hook-secret"), "s3cret\n").unwrap();
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let address = format!("127.0.0.1:{}", port);
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_vault-config-updater"))
+        .current_dir(root)
+        .env("XDG_CONFIG_HOME", root)
+        .args(["serve", "--socket", "ctl.sock", "--http", &address, "--webhook-secret-file", "hook-secret", "services"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let body = r#"{"token": "hvs.new"}"#;
+    let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+    let mut response = None;
+    for _ in 0..100 {
+        let sent = ureq::post(format!("http://{}/rotate", address))
+            .header(TIMESTAMP_HEADER, &timestamp.to_string())
+            .header(SIGNATURE_HEADER, &sign_webhook(b"s3cret", timestamp, body.as_bytes()))
+            .send(body);
+        if let Ok(sent) = sent {
+            response = Some(sent);
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    let mut response = response.expect("the webhook listener did not answer");
+    assert_eq!(response.status().as_u16(), 200);
+    let report: serde_json::Value = serde_json::from_str(&response.body_mut().read_to_string().unwrap()).unwrap();
+    assert_eq!(report["stats"]["files_updated"], 1);
+    assert_eq!(fs::read_to_string(root.join("services/api/config.json")).unwrap(), r#"{"vaultToken": "hvs.new"}"#);
+
+    let status = Command::new("kill").args(["-TERM", &child.id().to_string()]).status().unwrap();
+    assert!(status.success());
+    assert_eq!(child.wait().unwrap().code(), Some(0));
+}
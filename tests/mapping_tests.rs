@@ -0,0 +1,220 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use common::{error_response, MockVault};
+use serde_json::json;
+use tempfile::TempDir;
+use vault_config_updater::{
+    update_files_parallel, ExitCode, FileStatus, Hooks, MappedSource, SearchRoot, TokenAssignment, TokenField, TokenFields,
+    TokenMapping, ValidationError, VaultConfig
+};
+
+const MAPPING: &str = r#"
+[[rule]]
+path = "prod/**"
+key = "/database/*"
+source = { file = "tokens/db" }
+
+[[rule]]
+path = "prod/**"
+source = { env = "PROD_VAULT_TOKEN" }
+
+[[rule]]
+key = "/legacy/**"
+source = { login = "ci" }
+
+[login.ci]
+method = "approle"
+role-id = "role-1"
+secret-id-file = "secrets/ci"
+"#;
+
+fn field<'a>(file: &'a str, pointer: &'a str) -> TokenField<'a> {
+    TokenField { file: Path::new(file), pointer, environment: None }
+}
+
+#[test]
+fn test_first_matching_rule_wins() {
+    let mapping = TokenMapping::parse(MAPPING, Path::new("/repo")).unwrap();
+
+    assert_eq!(
+        mapping.source_for(&field("./prod/api/config.json", "/database/vaultToken")),
+        Some(&MappedSource::File("/repo/tokens/db".into()))
+    );
+    assert_eq!(
+        mapping.source_for(&field("prod/api/config.json", "/vaultToken")),
+        Some(&MappedSource::Env("PROD_VAULT_TOKEN".to_string()))
+    );
+    assert_eq!(
+        mapping.source_for(&field("dev/config.json", "/legacy/auth/vaultToken")),
+        Some(&MappedSource::Login("ci".to_string()))
+    );
+    assert_eq!(mapping.source_for(&field("dev/config.json", "/vaultToken")), None);
+    assert!(mapping.uses_key_paths());
+}
+
+#[test]
+fn test_rule_paths_match_below_the_search_root() {
+    let mut mapping = TokenMapping::parse(MAPPING, Path::new("/repo")).unwrap();
+    mapping.set_search_roots(&[
+        SearchRoot { path: "/srv/configs".into(), files: Vec::new() },
+        SearchRoot { path: "files.txt".into(), files: Vec::new() },
+    ]);
+
+    let prod = Some(&MappedSource::Env("PROD_VAULT_TOKEN".to_string()));
+    assert_eq!(mapping.source_for(&field("/srv/configs/prod/api/config.json", "/vaultToken")), prod);
+    assert_eq!(mapping.source_for(&field("/srv/configs/dev/config.json", "/vaultToken")), None);
+    // Files from a --files-from list are matched as listed
+    assert_eq!(mapping.source_for(&field("./prod/web/config.json", "/vaultToken")), prod);
+}
+
+#[test]
+fn test_invalid_mappings_are_rejected() {
+    for content in [
+        "",
+        "[[rule]]\nsource = { login = \"missing\" }",
+        "[[rule]]\npath = \"[\"\nsource = { env = \"X\" }",
+        "[[rule]]\nsource = { vault = \"X\" }",
+        "[[rule]]\nsource = { env = \"X\" }\ncolour = \"red\"",
+        "[[rule]]\nenvironment = \"production\"\nsource = { env = \"X\" }",
+        "[environment]\npointer = \"global/environment\"\nsources.production = { env = \"X\" }",
+        "[environment]\npointer = \"/global/environment\"",
+    ] {
+        let error = TokenMapping::parse(content, Path::new(".")).unwrap_err();
+        assert!(error.downcast_ref::<ValidationError>().is_some(), "{:?}: {}", content, error);
+    }
+}
+
+#[test]
+fn test_mapped_update_writes_each_files_token() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path();
+    fs::create_dir_all(root.join("prod")).unwrap();
+    fs::create_dir_all(root.join("dev")).unwrap();
+    fs::create_dir_all(root.join("docs")).unwrap();
+    fs::write(root.join("prod.token"), "hvs.prod\n").unwrap();
+    fs::write(root.join("dev.token"), "hvs.dev\n").unwrap();
+    fs::write(root.join("map.toml"), r#"
+        [[rule]]
+        path = "**/prod/**"
+        source = { file = "prod.token" }
+
+        [[rule]]
+        path = "**/dev/**"
+        source = { file = "dev.token" }
+    "#).unwrap();
+    let prod = root.join("prod/config.json");
+    let dev = root.join("dev/config.json");
+    let docs = root.join("docs/config.json");
+    for file in [&prod, &dev, &docs] {
+        fs::write(file, r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    }
+
+    let mut mapping = TokenMapping::load(root.join("map.toml")).unwrap();
+    mapping.resolve(None).unwrap();
+    let files = [prod.clone(), dev.clone(), docs.clone()];
+    let stats = update_files_parallel(&files, &mapping, &TokenFields::default(), &Hooks::default(), |_| {});
+
+    assert_eq!(stats.files_updated, 2);
+    assert_eq!(stats.unmatched, vec![docs.clone()]);
+    assert!(fs::read_to_string(&prod).unwrap().contains("hvs.prod"));
+    assert!(fs::read_to_string(&dev).unwrap().contains("hvs.dev"));
+    assert!(fs::read_to_string(&docs).unwrap().contains("hvs.old"));
+    let status = stats.files.iter().find(|outcome| outcome.path == docs).unwrap().status;
+    assert_eq!(status, FileStatus::Unmatched);
+    assert_eq!(stats.exit_code(), ExitCode::PartialFailure);
+    assert_eq!(mapping.tokens().len(), 2);
+}
+
+#[test]
+fn test_missing_source_fails_before_any_file_is_written() {
+    let dir = TempDir::new().unwrap();
+    let mut mapping = TokenMapping::parse(
+        "[[rule]]\nsource = { file = \"absent.token\" }", dir.path()
+    ).unwrap();
+
+    let error = mapping.resolve(None).unwrap_err();
+    assert!(error.to_string().contains("absent.token"), "{}", error);
+}
+
+#[test]
+fn test_login_source_uses_the_profile() {
+    let vault = MockVault::start(|request| match request.path.as_str() {
+        "/v1/auth/approle/login" if request.json()["secret_id"] == "secret-1" => (200, json!({
+            "auth": { "client_token": "hvs.from-approle", "policies": [], "lease_duration": 60, "renewable": false }
+        }).to_string()),
+        _ => (400, error_response("invalid credentials")),
+    });
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("secrets")).unwrap();
+    fs::write(dir.path().join("secrets/ci"), "secret-1\n").unwrap();
+    fs::create_dir_all(dir.path().join("tokens")).unwrap();
+    fs::write(dir.path().join("tokens/db"), "hvs.db\n").unwrap();
+    let content = MAPPING.replace("source = { env = \"PROD_VAULT_TOKEN\" }", "source = { file = \"tokens/db\" }");
+    let mut mapping = TokenMapping::parse(&content, dir.path()).unwrap();
+
+    mapping.resolve(Some(&VaultConfig::new(&vault.addr))).unwrap();
+    assert_eq!(mapping.token_for(&field("dev/config.json", "/legacy/vaultToken")), Some("hvs.from-approle"));
+    assert_eq!(mapping.token_for(&field("prod/config.json", "/vaultToken")), Some("hvs.db"));
+}
+
+#[test]
+fn test_environment_field_picks_the_token() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path();
+    fs::write(root.join("prod.token"), "hvs.prod\n").unwrap();
+    fs::write(root.join("staging.token"), "hvs.staging\n").unwrap();
+    let mut mapping = TokenMapping::parse(r#"
+        [environment]
+        pointer = "/global/environment"
+        sources.production = { file = "prod.token" }
+        sources.staging = { file = "staging.token" }
+    "#, root).unwrap();
+    mapping.resolve(None).unwrap();
+
+    let files: Vec<_> = ["prod", "staging", "qa", "none"].iter().map(|name| root.join(name).join("globalConfig.json")).collect();
+    for (file, environment) in files.iter().zip([r#""production""#, r#""staging""#, r#""qa""#, "null"]) {
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(file, format!(r#"{{"global": {{"environment": {}, "vaultToken": "hvs.old"}}}}"#, environment)).unwrap();
+    }
+    fs::copy("tests/fixtures/globalConfig.json", &files[0]).unwrap();
+
+    let stats = update_files_parallel(&files, &mapping, &TokenFields::default(), &Hooks::default(), |_| {});
+
+    assert_eq!(stats.files_updated, 2);
+    assert_eq!(stats.tokens_replaced, 3);
+    assert!(!fs::read_to_string(&files[0]).unwrap().contains("hvs.final-test-token"));
+    assert!(fs::read_to_string(&files[1]).unwrap().contains("hvs.staging"));
+    assert_eq!(stats.unknown_environment, vec![files[2].clone(), files[3].clone()]);
+    assert_eq!(stats.exit_code(), ExitCode::PartialFailure);
+    let qa = stats.files.iter().find(|outcome| outcome.path == files[2]).unwrap();
+    assert_eq!(qa.status, FileStatus::UnknownEnvironment);
+    assert_eq!(qa.environment.as_deref(), Some("qa"));
+    assert!(fs::read_to_string(&files[2]).unwrap().contains("hvs.old"));
+}
+
+#[test]
+fn test_rules_can_combine_environment_and_path() {
+    let mapping = TokenMapping::parse(r#"
+        [[rule]]
+        path = "payments/**"
+        environment = "production"
+        source = { env = "PAYMENTS_PROD_TOKEN" }
+
+        [environment]
+        pointer = "/global/environment"
+        sources.production = { env = "PROD_TOKEN" }
+    "#, Path::new(".")).unwrap();
+
+    let mut payments = field("payments/globalConfig.json", "");
+    payments.environment = Some("production");
+    assert_eq!(mapping.source_for(&payments), Some(&MappedSource::Env("PAYMENTS_PROD_TOKEN".to_string())));
+    let mut other = field("search/globalConfig.json", "");
+    other.environment = Some("production");
+    assert_eq!(mapping.source_for(&other), Some(&MappedSource::Env("PROD_TOKEN".to_string())));
+    other.environment = Some("staging");
+    assert_eq!(mapping.source_for(&other), None);
+    assert!(mapping.knows_environment("production"));
+    assert!(!mapping.knows_environment("staging"));
+}
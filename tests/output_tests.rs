@@ -0,0 +1,146 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use vault_config_updater::{
+    ColorChoice, FileOutcome, FileStatus, Icon, Output, OutputFormat, OutputOptions,
+    RunMode, RunReport, UpdateStats
+};
+
+/// In-memory stream that can be inspected after the output layer wrote to it
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn capture(options: OutputOptions) -> (Output, SharedBuffer, SharedBuffer) {
+    let stdout = SharedBuffer::default();
+    let stderr = SharedBuffer::default();
+    let out = Output::with_writers(options, Box::new(stdout.clone()), Box::new(stderr.clone()));
+    (out, stdout, stderr)
+}
+
+fn stats_with_error() -> UpdateStats {
+    let mut stats = UpdateStats::new();
+    stats.record(FileOutcome {
+        path: PathBuf::from("app/config.json"),
+        status: FileStatus::Updated,
+        tokens: 1,
+        error: None,
+        environment: None,
+        duration_ms: 1.0,
+        change: None,
+    });
+    stats.record(FileOutcome {
+        path: PathBuf::from("broken/config.json"),
+        status: FileStatus::Failed,
+        tokens: 0,
+        error: Some("permission denied".to_string()),
+        environment: None,
+        duration_ms: 1.0,
+        change: None,
+    });
+    stats
+}
+
+#[test]
+fn test_default_text_output_uses_emoji() {
+    let (out, stdout, stderr) = capture(OutputOptions::default());
+    let stats = stats_with_error();
+    let report = RunReport::new(RunMode::Update, ".".as_ref(), &stats, Duration::ZERO, 4);
+
+    out.update_summary(&report).unwrap();
+
+    let text = stdout.contents();
+    assert!(text.contains("🎉 Update completed"));
+    assert!(text.contains("   • Files updated: 1"));
+    assert!(text.contains("Failed to process broken/config.json: permission denied"));
+    assert!(stderr.contents().is_empty());
+}
+
+#[test]
+fn test_quiet_mode_only_reports_errors() {
+    let (out, stdout, stderr) = capture(OutputOptions { quiet: true, ..Default::default() });
+    let stats = stats_with_error();
+    let report = RunReport::new(RunMode::Update, ".".as_ref(), &stats, Duration::ZERO, 4);
+
+    out.info(Icon::Info, "progress");
+    out.warn("not important");
+    out.update_summary(&report).unwrap();
+
+    assert!(stdout.contents().is_empty());
+    let errors = stderr.contents();
+    assert!(errors.contains("Error: Failed to process broken/config.json"));
+    assert!(!errors.contains("not important"));
+}
+
+#[test]
+fn test_ascii_output_has_no_unicode() {
+    let (out, stdout, _) = capture(OutputOptions { ascii: true, verbose: true, ..Default::default() });
+    let stats = stats_with_error();
+    let report = RunReport::new(RunMode::Update, ".".as_ref(), &stats, Duration::ZERO, 4);
+
+    out.detail(Icon::Folder, "Searching in: \"café\"");
+    for outcome in &stats.files {
+        out.file_event(outcome);
+    }
+    out.update_summary(&report).unwrap();
+
+    let text = stdout.contents();
+    assert!(text.is_ascii(), "non-ASCII output: {}", text);
+    assert!(text.contains("[ok] Updated app/config.json"));
+    assert!(text.contains("   - Files processed: 2"));
+}
+
+#[test]
+fn test_color_choice() {
+    assert!(ColorChoice::Always.enabled(false));
+    assert!(!ColorChoice::Never.enabled(true));
+    assert!(!ColorChoice::Auto.enabled(false));
+
+    let (out, stdout, _) = capture(OutputOptions { color: ColorChoice::Always, ..Default::default() });
+    out.warn("careful");
+    assert!(stdout.contents().contains("\x1b[33m"));
+
+    let (out, stdout, _) = capture(OutputOptions { color: ColorChoice::Never, ..Default::default() });
+    out.warn("careful");
+    assert!(!stdout.contents().contains('\x1b'));
+}
+
+#[test]
+fn test_verbose_details_hidden_by_default() {
+    let (out, stdout, _) = capture(OutputOptions::default());
+    out.detail(Icon::List, "Found 3 config files:");
+    out.detail_item("config.json");
+    assert!(stdout.contents().is_empty());
+
+    let (out, stdout, _) = capture(OutputOptions { verbose: true, ..Default::default() });
+    out.detail(Icon::List, "Found 3 config files:");
+    out.detail_item("config.json");
+    assert_eq!(stdout.contents(), "📋 Found 3 config files:\n   • config.json\n");
+}
+
+#[test]
+fn test_machine_output_keeps_warnings_off_stdout() {
+    let (out, stdout, stderr) = capture(OutputOptions { format: OutputFormat::Json, verbose: true, ..Default::default() });
+    out.detail(Icon::Info, "verbose line");
+    out.warn("heads up");
+
+    assert!(stdout.contents().is_empty());
+    assert_eq!(stderr.contents(), "Warning: heads up\n");
+}
@@ -0,0 +1,107 @@
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+use vault_config_updater::{FileHookConfig, HookConfig, OutputFormat, Profile, ProjectConfig, CONFIG_FILE_NAME};
+
+const CONFIG: &str = r#"
+[defaults]
+keys = ["vaultToken", "vault_token"]
+exclude = ["**/node_modules/**"]
+output = "text"
+
+[profiles.prod]
+paths = ["services/prod", "/srv/shared"]
+include = ["**/prod/**"]
+output = "json"
+vault-addr = "https://vault.prod.example.com"
+
+[profiles.dev]
+exclude = []
+"#;
+
+#[test]
+fn test_profile_overlays_defaults() {
+    let config = ProjectConfig::parse(CONFIG, Path::new("/repo")).unwrap();
+
+    let defaults = config.settings(None).unwrap();
+    assert_eq!(defaults.keys, Some(vec!["vaultToken".to_string(), "vault_token".to_string()]));
+    assert_eq!(defaults.output, Some(OutputFormat::Text));
+    assert_eq!(defaults.paths, None);
+
+    let prod = config.settings(Some("prod")).unwrap();
+    assert_eq!(prod.keys, defaults.keys);
+    assert_eq!(prod.exclude, Some(vec!["**/node_modules/**".to_string()]));
+    assert_eq!(prod.include, Some(vec!["**/prod/**".to_string()]));
+    assert_eq!(prod.output, Some(OutputFormat::Json));
+    assert_eq!(prod.vault_addr.as_deref(), Some("https://vault.prod.example.com"));
+    // Relative roots are anchored at the directory holding the config file
+    assert_eq!(prod.paths, Some(vec![Path::new("/repo/services/prod").to_path_buf(), "/srv/shared".into()]));
+
+    // An explicitly empty list clears the default
+    assert_eq!(config.settings(Some("dev")).unwrap().exclude, Some(vec![]));
+}
+
+#[test]
+fn test_overlay_keeps_unset_values() {
+    let base = Profile { output: Some(OutputFormat::Ndjson), keys: Some(vec!["a".into()]), ..Profile::default() };
+    let merged = base.overlay(&Profile { keys: Some(vec!["b".into()]), ..Profile::default() });
+    assert_eq!(merged.output, Some(OutputFormat::Ndjson));
+    assert_eq!(merged.keys, Some(vec!["b".to_string()]));
+}
+
+#[test]
+fn test_rejects_unknown_profiles_and_settings() {
+    let config = ProjectConfig::parse(CONFIG, Path::new("/repo")).unwrap();
+    let error = config.settings(Some("staging")).unwrap_err().to_string();
+    assert!(error.contains("dev, prod"), "{}", error);
+
+    assert!(ProjectConfig::parse("[defaults]\nincludes = []\n", Path::new(".")).is_err());
+    assert!(ProjectConfig::parse("[defaults]\noutput = \"yaml\"\n", Path::new(".")).is_err());
+}
+
+#[test]
+fn test_parses_hooks() {
+    let config = ProjectConfig::parse(r#"
+[defaults.hooks]
+pre = "make backup"
+timeout = "10s"
+
+[[defaults.hooks.files]]
+dir = "services/*"
+post = "systemctl reload {dir}"
+
+[profiles.prod.hooks]
+post = "notify-send rotated"
+"#, Path::new("/repo")).unwrap();
+
+    let defaults = config.settings(None).unwrap().hooks.unwrap();
+    assert_eq!(defaults.pre.as_deref(), Some("make backup"));
+    assert_eq!(defaults.timeout.as_deref(), Some("10s"));
+    assert_eq!(defaults.files, vec![FileHookConfig {
+        dir: Some("services/*".to_string()),
+        post: Some("systemctl reload {dir}".to_string()),
+        ..FileHookConfig::default()
+    }]);
+    // A profile's hooks table replaces the default one as a whole
+    let prod = config.settings(Some("prod")).unwrap().hooks.unwrap();
+    assert_eq!(prod, HookConfig { post: Some("notify-send rotated".to_string()), ..HookConfig::default() });
+
+    assert!(ProjectConfig::parse("[defaults.hooks]\npre = \"true\"\nretries = 2\n", Path::new(".")).is_err());
+    assert!(ProjectConfig::parse("[[defaults.hooks.files]]\npost = \"true\"\n", Path::new(".")).is_err());
+    assert!(ProjectConfig::parse("[defaults.hooks]\ntimeout = \"later\"\n", Path::new(".")).is_err());
+}
+
+#[test]
+fn test_discovers_config_in_parent_directory() {
+    let temp_dir = TempDir::new().unwrap();
+    let nested = temp_dir.path().join("services").join("api");
+    fs::create_dir_all(&nested).unwrap();
+    let config_path = temp_dir.path().join(CONFIG_FILE_NAME);
+    fs::write(&config_path, CONFIG).unwrap();
+
+    assert_eq!(ProjectConfig::discover(&nested), Some(config_path.clone()));
+
+    let config = ProjectConfig::load(&config_path).unwrap();
+    let prod = config.settings(Some("prod")).unwrap();
+    assert_eq!(prod.paths.unwrap()[0], temp_dir.path().join("services/prod"));
+}
@@ -0,0 +1,112 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use serde_json::Value;
+use tempfile::TempDir;
+use vault_config_updater::{
+    ndjson_file_event, update_vault_tokens_in_files, scan_vault_tokens_in_files,
+    FileStatus, RunMode, RunReport, SecretToken, SCHEMA_VERSION
+};
+
+#[test]
+fn test_update_report_json_schema() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.json");
+    fs::write(&config_path, r#"{"vaultToken": "hvs.old"}"#).unwrap();
+
+    let token = SecretToken::new("hvs.super-secret-value");
+    let stats = update_vault_tokens_in_files(&[&config_path], token.expose());
+    let report = RunReport::new(RunMode::Update, temp_dir.path(), &stats, Duration::from_millis(5), 0)
+        .with_token(&token);
+    let json = report.to_json().unwrap();
+
+    assert!(!json.contains("super-secret-value"), "token leaked: {}", json);
+
+    let value: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["schema_version"], SCHEMA_VERSION);
+    assert_eq!(value["mode"], "update");
+    assert_eq!(value["exit_code"], 0);
+    assert_eq!(value["token"]["masked"], "hvs.****");
+    assert_eq!(value["token"]["fingerprint"], token.fingerprint());
+    assert_eq!(value["token"]["length"], token.len());
+    assert_eq!(value["stats"]["files_processed"], 1);
+    assert_eq!(value["stats"]["files_updated"], 1);
+    assert_eq!(value["stats"]["tokens_replaced"], 1);
+    assert_eq!(value["stats"]["files"][0]["status"], "updated");
+    assert_eq!(value["stats"]["files"][0]["tokens"], 1);
+    assert!(value["stats"]["files"][0]["duration_ms"].is_number());
+}
+
+#[test]
+fn test_scan_report_includes_errors() {
+    let temp_dir = TempDir::new().unwrap();
+    let missing = temp_dir.path().join("config.json");
+
+    let stats = scan_vault_tokens_in_files(&[&missing]);
+    let report = RunReport::new(RunMode::DryRun, Path::new("."), &stats, Duration::ZERO, 1);
+    let value: Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+
+    assert_eq!(value["mode"], "dry_run");
+    assert!(value.get("token").is_none());
+    assert_eq!(value["stats"]["errors"].as_array().unwrap().len(), 1);
+    assert_eq!(value["stats"]["files"][0]["status"], "failed");
+    assert!(value["stats"]["files"][0]["error"].is_string());
+}
+
+#[test]
+fn test_ndjson_events() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.json");
+    fs::write(&config_path, r#"{"vaultToken": "hvs.old", "a": {"vaultToken": "hvs.old"}}"#).unwrap();
+
+    let stats = scan_vault_tokens_in_files(&[&config_path]);
+    assert_eq!(stats.files[0].status, FileStatus::WouldUpdate);
+
+    let file_line = ndjson_file_event(&stats.files[0]).unwrap();
+    assert!(!file_line.contains('\n'));
+    let file_event: Value = serde_json::from_str(&file_line).unwrap();
+    assert_eq!(file_event["event"], "file");
+    assert_eq!(file_event["status"], "would_update");
+    assert_eq!(file_event["tokens"], 2);
+
+    let report = RunReport::new(RunMode::DryRun, temp_dir.path(), &stats, Duration::ZERO, 0);
+    let summary_line = report.to_ndjson_summary().unwrap();
+    assert!(!summary_line.contains('\n'));
+    let summary: Value = serde_json::from_str(&summary_line).unwrap();
+    assert_eq!(summary["event"], "summary");
+    assert_eq!(summary["stats"]["total_tokens_found"], 2);
+    // Per-file outcomes were already streamed
+    assert!(summary["stats"].get("files").is_none());
+}
+
+#[test]
+fn test_report_breaks_results_down_by_root() {
+    use std::path::PathBuf;
+    use vault_config_updater::{RootSummary, SearchRoot};
+
+    let temp_dir = TempDir::new().unwrap();
+    let first = temp_dir.path().join("a.json");
+    let second = temp_dir.path().join("b.json");
+    fs::write(&first, r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    fs::write(&second, r#"{"host": "localhost"}"#).unwrap();
+    let roots = vec![
+        SearchRoot { path: PathBuf::from("./a"), files: vec![first.clone()] },
+        SearchRoot { path: PathBuf::from("./b"), files: vec![second.clone()] },
+    ];
+
+    let stats = update_vault_tokens_in_files(&[&first, &second], "hvs.new");
+    let summaries = RootSummary::from_outcomes(&roots, &stats.files);
+    let report = RunReport::new(RunMode::Update, Path::new("./a"), &stats, Duration::from_millis(1), 0)
+        .with_roots(summaries.clone());
+    let value: Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+    assert_eq!(value["roots"][0]["path"], "./a");
+    assert_eq!(value["roots"][0]["files"], 1);
+    assert_eq!(value["roots"][0]["statuses"]["updated"], 1);
+    assert_eq!(value["roots"][1]["statuses"]["unchanged"], 1);
+
+    // A single root is already described by the totals
+    let report = RunReport::new(RunMode::Update, Path::new("./a"), &stats, Duration::from_millis(1), 0)
+        .with_roots(summaries[..1].to_vec());
+    let value: Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+    assert!(value.get("roots").is_none());
+}
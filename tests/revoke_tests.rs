@@ -0,0 +1,63 @@
+mod common;
+
+use common::{error_response, MockVault};
+use vault_config_updater::{
+    revoke_old_tokens, RevocationStatus, SecretToken, VaultClient, VaultConfig
+};
+
+/// Mock Vault where `hvs.old-a` and `hvs.old-b` are live, `hvs.expired` is not,
+/// and revoking `hvs.broken` hits a server error
+fn mock_vault() -> MockVault {
+    MockVault::start(|request| match (request.path.as_str(), request.token.as_deref()) {
+        ("/v1/auth/token/revoke-self", Some("hvs.old-a" | "hvs.old-b")) => (204, String::new()),
+        ("/v1/auth/token/revoke-self", Some("hvs.broken")) => (500, error_response("internal error")),
+        _ => (403, error_response("permission denied")),
+    })
+}
+
+#[test]
+fn test_revokes_each_old_token_with_itself() {
+    let vault = mock_vault();
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+    let old = vec![SecretToken::new("hvs.old-a"), SecretToken::new("hvs.old-b")];
+
+    let outcomes = revoke_old_tokens(&client, &old, &SecretToken::new("hvs.new"));
+
+    assert_eq!(outcomes.len(), 2);
+    assert!(outcomes.iter().all(|o| o.status == RevocationStatus::Revoked));
+    assert_eq!(outcomes[0].fingerprint, old[0].fingerprint());
+    assert_eq!(outcomes[0].masked, "hvs.****");
+
+    let requests = vault.requests();
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].token.as_deref(), Some("hvs.old-a"));
+    assert_eq!(requests[1].token.as_deref(), Some("hvs.old-b"));
+}
+
+#[test]
+fn test_new_token_is_never_revoked() {
+    let vault = mock_vault();
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+    let new_token = SecretToken::new("hvs.old-a");
+
+    let outcomes = revoke_old_tokens(&client, std::slice::from_ref(&new_token), &new_token);
+
+    assert!(outcomes.is_empty());
+    assert!(vault.requests().is_empty());
+}
+
+#[test]
+fn test_invalid_and_failed_revocations_are_reported() {
+    let vault = mock_vault();
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+    let old = vec![SecretToken::new("hvs.expired"), SecretToken::new("hvs.broken")];
+
+    let outcomes = revoke_old_tokens(&client, &old, &SecretToken::new("hvs.new"));
+
+    assert_eq!(outcomes[0].status, RevocationStatus::AlreadyInvalid);
+    assert_eq!(outcomes[0].error, None);
+    assert_eq!(outcomes[1].status, RevocationStatus::Failed);
+    let error = outcomes[1].error.as_deref().unwrap();
+    assert!(error.contains("internal error"));
+    assert!(!error.contains("hvs.broken"));
+}
@@ -0,0 +1,50 @@
+use vault_config_updater::{fingerprint, mask_token, SecretToken};
+
+#[test]
+fn test_debug_and_display_never_reveal_token() {
+    let token = SecretToken::new("hvs.CAESIHGBcOKn9LDs8JpZkZ");
+
+    let debug = format!("{:?}", token);
+    let display = format!("{}", token);
+    let nested = format!("{:?}", Some(vec![token.clone()]));
+
+    for rendered in [debug, display, nested] {
+        assert!(!rendered.contains("CAESIHGBcOKn9LDs8JpZkZ"), "token leaked: {}", rendered);
+        assert!(rendered.contains("hvs.****"));
+        assert!(rendered.contains(&token.fingerprint()));
+    }
+}
+
+#[test]
+fn test_expose_returns_raw_value() {
+    let token: SecretToken = "hvs.raw".parse().unwrap();
+    assert_eq!(token.expose(), "hvs.raw");
+    assert_eq!(token.len(), 7);
+    assert!(!token.is_empty());
+}
+
+#[test]
+fn test_fingerprint_is_stable_and_short() {
+    let token = SecretToken::new("hvs.stable");
+
+    assert_eq!(token.fingerprint(), SecretToken::new("hvs.stable").fingerprint());
+    assert_eq!(token.fingerprint(), fingerprint("hvs.stable"));
+    assert_eq!(token.fingerprint().len(), 12);
+    assert!(token.fingerprint().chars().all(|c| c.is_ascii_hexdigit()));
+    // Pinned so fingerprints stored in CI jobs and audit logs stay comparable
+    assert_eq!(fingerprint("hvs.stable"), "6b267d24d081");
+    assert_ne!(fingerprint("hvs.stable"), fingerprint("hvs.stable2"));
+}
+
+#[test]
+fn test_fingerprint_does_not_contain_token() {
+    let value = fingerprint("abc");
+    assert!(!value.contains("abc"));
+}
+
+#[test]
+fn test_mask_token_keeps_only_prefix() {
+    assert_eq!(mask_token("hvs.CAESIHGBcOKn9LDs8JpZkZ"), "hvs.****");
+    assert_eq!(mask_token("s.legacy-token"), "s.****");
+    assert_eq!(mask_token("no-prefix-secret"), "****");
+}
@@ -0,0 +1,192 @@
+#![cfg(unix)]
+
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use serde_json::Value;
+use tempfile::TempDir;
+use vault_config_updater::{serve_unix, Request, RotateRequest, ServeSelection, Server};
+
+fn write_config(root: &Path, name: &str, token: &str) -> PathBuf {
+    let path = root.join(name).join("config.json");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, format!(r#"{{"vaultToken": "{}"}}"#, token)).unwrap();
+    path
+}
+
+fn server_for(root: &Path) -> Server {
+    Server::new(ServeSelection { paths: vec![root.to_path_buf()], ..ServeSelection::default() }).unwrap()
+}
+
+fn respond(server: &Server, line: &str) -> Value {
+    serde_json::from_str(&server.handle_line(line)).unwrap()
+}
+
+#[test]
+fn test_parses_requests() {
+    let request: Request = serde_json::from_str(r#"{"command": "rotate", "token": "hvs.new"}"#).unwrap();
+    assert!(matches!(request, Request::Rotate(RotateRequest { token, wrapped: false }) if token.expose() == "hvs.new"));
+    let request: Request = serde_json::from_str(r#"{"command": "rotate", "token": "hvs.wrap", "wrapped": true}"#).unwrap();
+    assert!(matches!(request, Request::Rotate(RotateRequest { wrapped: true, .. })));
+    assert!(serde_json::from_str::<Request>(r#"{"command": "rotate", "token": "hvs.new", "extra": 1}"#).is_err());
+    assert_eq!(serde_json::from_str::<Request>(r#"{"command": "status"}"#).unwrap(), Request::Status);
+    assert!(serde_json::from_str::<Request>(r#"{"command": "rotate"}"#).is_err());
+}
+
+#[test]
+fn test_rotate_updates_known_files() {
+    let dir = TempDir::new().unwrap();
+    let api = write_config(dir.path(), "api", "hvs.old");
+    let web = write_config(dir.path(), "web", "hvs.old");
+    let server = server_for(dir.path());
+    assert_eq!(server.file_count(), 2);
+
+    let response = respond(&server, r#"{"command": "rotate", "token": "hvs.new"}"#);
+    assert_eq!(response["ok"], true);
+    assert_eq!(response["result"]["mode"], "update");
+    assert_eq!(response["result"]["exit_code"], 0);
+    assert_eq!(response["result"]["stats"]["files_updated"], 2);
+    assert!(!response.to_string().contains("hvs.new"));
+    assert_eq!(fs::read_to_string(api).unwrap(), r#"{"vaultToken": "hvs.new"}"#);
+    assert_eq!(fs::read_to_string(web).unwrap(), r#"{"vaultToken": "hvs.new"}"#);
+
+    let status = respond(&server, r#"{"command": "status"}"#);
+    assert_eq!(status["result"]["files"], 2);
+    assert_eq!(status["result"]["rotations"], 1);
+    assert_eq!(status["result"]["last_rotation"]["files_updated"], 2);
+    assert_eq!(status["result"]["last_rotation"]["fingerprint"], response["result"]["token"]["fingerprint"]);
+}
+
+#[test]
+fn test_scan_leaves_files_untouched() {
+    let dir = TempDir::new().unwrap();
+    let api = write_config(dir.path(), "api", "hvs.old");
+    let server = server_for(dir.path());
+
+    let response = respond(&server, r#"{"command": "scan"}"#);
+    assert_eq!(response["ok"], true);
+    assert_eq!(response["result"]["mode"], "dry_run");
+    assert_eq!(response["result"]["stats"]["files_with_tokens"], 1);
+    assert_eq!(fs::read_to_string(api).unwrap(), r#"{"vaultToken": "hvs.old"}"#);
+}
+
+#[test]
+fn test_rescan_picks_up_new_files() {
+    let dir = TempDir::new().unwrap();
+    write_config(dir.path(), "api", "hvs.old");
+    let server = server_for(dir.path());
+    let web = write_config(dir.path(), "web", "hvs.old");
+
+    respond(&server, r#"{"command": "rotate", "token": "hvs.first"}"#);
+    assert_eq!(fs::read_to_string(&web).unwrap(), r#"{"vaultToken": "hvs.old"}"#);
+
+    let response = respond(&server, r#"{"command": "rescan"}"#);
+    assert_eq!(response["result"]["files"], 2);
+    respond(&server, r#"{"command": "rotate", "token": "hvs.second"}"#);
+    assert_eq!(fs::read_to_string(&web).unwrap(), r#"{"vaultToken": "hvs.second"}"#);
+}
+
+#[test]
+fn test_rejects_invalid_requests() {
+    let dir = TempDir::new().unwrap();
+    write_config(dir.path(), "api", "hvs.old");
+    let server = server_for(dir.path());
+
+    for line in ["not json", r#"{"command": "reboot"}"#, r#"{"token": "hvs.new"}"#] {
+        let response = respond(&server, line);
+        assert_eq!(response["ok"], false, "{}", line);
+        assert!(response["error"].as_str().unwrap().starts_with("Invalid request"), "{}", line);
+    }
+    let response = respond(&server, r#"{"command": "rotate", "token": ""}"#);
+    assert_eq!(response["ok"], false);
+    assert_eq!(response["error"], "The token is empty");
+    assert_eq!(respond(&server, r#"{"command": "status"}"#)["result"]["rotations"], 0);
+}
+
+#[test]
+fn test_serves_requests_over_a_unix_socket() {
+    let dir = TempDir::new().unwrap();
+    let api = write_config(dir.path(), "services/api", "hvs.old");
+    let socket = dir.path().join("ctl.sock");
+    // A socket left behind by a crashed server is replaced
+    drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+
+    let server = server_for(&dir.path().join("services"));
+    let shutdown = AtomicBool::new(false);
+    let logged = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        let handle = scope.spawn(|| serve_unix(&server, &socket, &shutdown, |message| {
+            logged.lock().unwrap().push(message.to_string());
+        }));
+        let mut stream = None;
+        for _ in 0..100 {
+            if let Ok(connected) = UnixStream::connect(&socket) {
+                stream = Some(connected);
+                break;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        let stream = stream.expect("the server did not start listening");
+        assert_eq!(fs::metadata(&socket).unwrap().permissions().mode() & 0o777, 0o600);
+
+        let mut writer = stream.try_clone().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut exchange = |request: &str| {
+            writeln!(writer, "{}", request).unwrap();
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            serde_json::from_str::<Value>(&line).unwrap()
+        };
+        assert_eq!(exchange(r#"{"command": "rotate", "token": "hvs.new"}"#)["ok"], true);
+        assert_eq!(exchange(r#"{"command": "status"}"#)["result"]["rotations"], 1);
+        assert_eq!(fs::read_to_string(&api).unwrap(), r#"{"vaultToken": "hvs.new"}"#);
+
+        // A second server on the same socket is refused while the first one runs
+        let second = serve_unix(&server, &socket, &AtomicBool::new(true), |_| {});
+        assert!(second.unwrap_err().to_string().contains("already listening"));
+
+        shutdown.store(true, Ordering::SeqCst);
+        handle.join().unwrap().unwrap();
+    });
+    assert!(!socket.exists());
+
+    let logged = logged.into_inner().unwrap();
+    assert_eq!(logged.len(), 1);
+    assert!(logged[0].starts_with("rotate: 1 of 1 files updated"), "{}", logged[0]);
+    assert!(!logged[0].contains("hvs.new"));
+}
+
+#[test]
+fn test_does_not_replace_a_file_that_is_not_a_socket() {
+    let dir = TempDir::new().unwrap();
+    let server = server_for(dir.path());
+    let path = dir.path().join("notes.txt");
+    fs::write(&path, "keep me").unwrap();
+
+    let error = serve_unix(&server, &path, &AtomicBool::new(true), |_| {}).unwrap_err();
+    assert!(error.to_string().contains("is not a socket"), "{}", error);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "keep me");
+}
+
+#[test]
+fn test_server_is_shared_between_threads() {
+    let dir = TempDir::new().unwrap();
+    write_config(dir.path(), "api", "hvs.old");
+    let server = Arc::new(server_for(dir.path()));
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            let server = Arc::clone(&server);
+            thread::spawn(move || respond(&server, &format!(r#"{{"command": "rotate", "token": "hvs.{}"}}"#, i)))
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap()["ok"], true);
+    }
+    assert_eq!(respond(&server, r#"{"command": "status"}"#)["result"]["rotations"], 4);
+}
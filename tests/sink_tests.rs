@@ -0,0 +1,68 @@
+mod common;
+
+use std::fs;
+use std::thread;
+use std::time::Duration;
+use common::{error_response, MockVault};
+use serde_json::json;
+use tempfile::TempDir;
+use vault_config_updater::{latest_sink, read_sink, SecretToken, SinkToken, ValidationError, VaultClient, VaultConfig};
+
+#[test]
+fn test_reads_plain_and_wrapped_sinks() {
+    let dir = TempDir::new().unwrap();
+    let plain = dir.path().join("plain");
+    fs::write(&plain, "hvs.agent-token\n").unwrap();
+    assert_eq!(read_sink(&plain).unwrap(), SinkToken::Plain(SecretToken::new("hvs.agent-token")));
+
+    let wrapped = dir.path().join("wrapped");
+    fs::write(&wrapped, json!({
+        "token": "hvs.wrapping",
+        "accessor": "accessor-1",
+        "ttl": 300,
+        "creation_time": "2026-10-18T10:00:00Z",
+        "creation_path": "sys/wrapping/wrap",
+        "wrapped_accessor": "accessor-2"
+    }).to_string()).unwrap();
+    assert_eq!(read_sink(&wrapped).unwrap(), SinkToken::Wrapped(SecretToken::new("hvs.wrapping")));
+
+    for content in ["", "  \n", r#"{"accessor": "a"}"#, "{not json"] {
+        fs::write(&plain, content).unwrap();
+        assert!(read_sink(&plain).is_err(), "{:?}", content);
+    }
+    assert!(read_sink(dir.path().join("absent")).is_err());
+}
+
+#[test]
+fn test_wrapped_sink_is_unwrapped_through_vault() {
+    let vault = MockVault::start(|request| match (request.path.as_str(), request.token.as_deref()) {
+        ("/v1/sys/wrapping/unwrap", Some("hvs.wrapping")) => (200, json!({
+            "auth": { "client_token": "hvs.unwrapped", "policies": [], "lease_duration": 60, "renewable": true }
+        }).to_string()),
+        _ => (400, error_response("wrapping token is not valid or does not exist")),
+    });
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+
+    let token = SinkToken::Wrapped(SecretToken::new("hvs.wrapping")).resolve(Some(&client)).unwrap();
+    assert_eq!(token.expose(), "hvs.unwrapped");
+    assert!(SinkToken::Wrapped(SecretToken::new("hvs.used")).resolve(Some(&client)).is_err());
+
+    let error = SinkToken::Wrapped(SecretToken::new("hvs.wrapping")).resolve(None).unwrap_err();
+    assert!(error.downcast_ref::<ValidationError>().is_some());
+    assert_eq!(SinkToken::Plain(SecretToken::new("hvs.a")).resolve(None).unwrap().expose(), "hvs.a");
+}
+
+#[test]
+fn test_latest_sink_is_the_last_written() {
+    let dir = TempDir::new().unwrap();
+    let first = dir.path().join("agent-a");
+    let second = dir.path().join("agent-b");
+    let missing = dir.path().join("agent-c");
+    fs::write(&second, "hvs.b\n").unwrap();
+    thread::sleep(Duration::from_millis(20));
+    fs::write(&first, "hvs.a\n").unwrap();
+
+    let sinks = vec![first.clone(), second.clone(), missing.clone()];
+    assert_eq!(latest_sink(&sinks), Some(&first));
+    assert_eq!(latest_sink(&[missing]), None);
+}
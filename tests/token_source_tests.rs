@@ -0,0 +1,61 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+use tempfile::TempDir;
+use vault_config_updater::{read_token_from, SecretToken, TokenSource, ValidationError};
+
+#[test]
+fn test_reads_first_line_from_reader() {
+    let token = read_token_from(Cursor::new("hvs.from-reader\nignored\n")).unwrap();
+    assert_eq!(token.expose(), "hvs.from-reader");
+}
+
+#[test]
+fn test_reads_token_from_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let token_path = temp_dir.path().join("token");
+    fs::write(&token_path, "  hvs.from-file  \n").unwrap();
+
+    let token = TokenSource::File(token_path).read().unwrap();
+    assert_eq!(token.expose(), "hvs.from-file");
+}
+
+#[test]
+fn test_missing_token_file_is_an_error() {
+    let result = TokenSource::File(PathBuf::from("nonexistent/token")).read();
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_reads_token_from_env() {
+    // SAFETY: the variable name is unique to this test
+    unsafe { std::env::set_var("VCU_TEST_TOKEN_SOURCE_ENV", "hvs.from-env") };
+
+    let token = TokenSource::Env("VCU_TEST_TOKEN_SOURCE_ENV".to_string()).read().unwrap();
+    assert_eq!(token.expose(), "hvs.from-env");
+}
+
+#[test]
+fn test_unset_env_is_a_validation_error() {
+    let result = TokenSource::Env("VCU_TEST_TOKEN_SOURCE_UNSET".to_string()).read();
+    let error = result.unwrap_err();
+    assert!(error.downcast_ref::<ValidationError>().is_some());
+}
+
+#[test]
+fn test_empty_token_is_a_validation_error() {
+    let temp_dir = TempDir::new().unwrap();
+    let token_path = temp_dir.path().join("token");
+    fs::write(&token_path, "\n").unwrap();
+
+    let error = TokenSource::File(token_path).read().unwrap_err();
+    assert!(error.downcast_ref::<ValidationError>().is_some());
+}
+
+#[test]
+fn test_debug_output_hides_argument_token() {
+    let source = TokenSource::Argument(SecretToken::new("hvs.argv-secret"));
+    let debug = format!("{:?}", source);
+    assert!(!debug.contains("argv-secret"));
+    assert_eq!(source.to_string(), "command-line argument");
+}
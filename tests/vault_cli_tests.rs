@@ -0,0 +1,76 @@
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+use vault_config_updater::{parse_token_helper, SecretToken, ValidationError, VaultCliConfig};
+
+fn config_in(home: &Path) -> VaultCliConfig {
+    VaultCliConfig {
+        env_token: None,
+        config_path: home.join(".vault"),
+        token_path: home.join(".vault-token"),
+    }
+}
+
+#[test]
+fn test_parse_token_helper() {
+    let config = r#"
+# Vault CLI configuration
+token_helper = "/usr/local/bin/vault-helper"
+"#;
+    assert_eq!(parse_token_helper(config), Some("/usr/local/bin/vault-helper".to_string()));
+    assert_eq!(parse_token_helper("# token_helper = \"/x\"\n"), None);
+    assert_eq!(parse_token_helper("token_helper = \"\"\n"), None);
+    assert_eq!(parse_token_helper(""), None);
+}
+
+#[test]
+fn test_env_token_takes_precedence() {
+    let home = TempDir::new().unwrap();
+    fs::write(home.path().join(".vault-token"), "hvs.from-file").unwrap();
+
+    let mut config = config_in(home.path());
+    config.env_token = Some(SecretToken::new("hvs.from-env"));
+
+    assert_eq!(config.lookup().unwrap().expose(), "hvs.from-env");
+}
+
+#[test]
+fn test_reads_vault_token_file() {
+    let home = TempDir::new().unwrap();
+    fs::write(home.path().join(".vault-token"), "hvs.from-file\n").unwrap();
+
+    let token = config_in(home.path()).lookup().unwrap();
+    assert_eq!(token.expose(), "hvs.from-file");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_token_helper_takes_precedence_over_token_file() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let home = TempDir::new().unwrap();
+    let helper = home.path().join("helper.sh");
+    fs::write(&helper, "#!/bin/sh\n[ \"$1\" = get ] && echo hvs.from-helper\n").unwrap();
+    fs::set_permissions(&helper, fs::Permissions::from_mode(0o755)).unwrap();
+    fs::write(home.path().join(".vault"), format!("token_helper = \"{}\"\n", helper.display())).unwrap();
+    fs::write(home.path().join(".vault-token"), "hvs.from-file").unwrap();
+
+    let token = config_in(home.path()).lookup().unwrap();
+    assert_eq!(token.expose(), "hvs.from-helper");
+}
+
+#[test]
+fn test_failing_token_helper_is_an_error() {
+    let home = TempDir::new().unwrap();
+    fs::write(home.path().join(".vault"), "token_helper = \"/nonexistent/helper\"\n").unwrap();
+
+    assert!(config_in(home.path()).lookup().is_err());
+}
+
+#[test]
+fn test_no_vault_login_is_a_validation_error() {
+    let home = TempDir::new().unwrap();
+
+    let error = config_in(home.path()).lookup().unwrap_err();
+    assert!(error.downcast_ref::<ValidationError>().is_some());
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use std::fs;
+use std::time::Duration;
+use common::{error_response, lookup_response, MockVault};
+use tempfile::TempDir;
+use vault_config_updater::{
+    validate_token, SecretToken, ValidationError, VaultClient, VaultConfig, VaultError
+};
+
+/// Mock Vault that accepts only `hvs.valid` and `hvs.short`
+fn mock_vault() -> MockVault {
+    MockVault::start(|request| match (request.path.as_str(), request.token.as_deref()) {
+        ("/v1/auth/token/lookup-self", Some("hvs.valid")) => (200, lookup_response(86_400, &["default", "app"])),
+        ("/v1/auth/token/lookup-self", Some("hvs.short")) => (200, lookup_response(60, &["default"])),
+        ("/v1/auth/token/lookup-self", Some("hvs.root")) => (200, lookup_response(0, &["root"])),
+        _ => (403, error_response("permission denied")),
+    })
+}
+
+#[test]
+fn test_lookup_self_returns_token_info() {
+    let vault = mock_vault();
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+
+    let info = client.lookup_self(&SecretToken::new("hvs.valid")).unwrap();
+    assert_eq!(info.policies, vec!["default", "app"]);
+    assert_eq!(info.ttl, 86_400);
+    assert!(info.renewable);
+    assert!(info.summary().contains("policies [default, app]"));
+    assert!(!format!("{:?}", info).contains("must-not-be-captured"));
+
+    let requests = vault.requests();
+    assert_eq!(requests[0].method, "GET");
+    assert_eq!(requests[0].token.as_deref(), Some("hvs.valid"));
+    assert_eq!(requests[0].namespace, None);
+}
+
+#[test]
+fn test_namespace_header_is_sent() {
+    let vault = mock_vault();
+    let mut config = VaultConfig::new(&vault.addr);
+    config.namespace = Some("team-a".to_string());
+    let client = VaultClient::new(config).unwrap();
+
+    client.lookup_self(&SecretToken::new("hvs.valid")).unwrap();
+    assert_eq!(vault.requests()[0].namespace.as_deref(), Some("team-a"));
+}
+
+#[test]
+fn test_vault_errors_are_reported() {
+    let vault = mock_vault();
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+
+    let error = client.lookup_self(&SecretToken::new("hvs.bogus")).unwrap_err();
+    let vault_error = error.downcast_ref::<VaultError>().unwrap();
+    assert!(vault_error.is_permission_denied());
+    assert_eq!(vault_error.errors, vec!["permission denied"]);
+}
+
+#[test]
+fn test_non_json_error_pages_keep_the_http_status() {
+    let vault = MockVault::start(|_| (503, "<html><body><h1>503 Service Unavailable</h1></body></html>".to_string()));
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+
+    let error = client.lookup_self(&SecretToken::new("hvs.valid")).unwrap_err();
+    let vault_error = error.downcast_ref::<VaultError>().unwrap();
+    assert_eq!(vault_error.status, 503);
+    assert!(vault_error.errors.is_empty());
+    assert_eq!(error.to_string(), "Vault returned HTTP 503");
+
+    // A success response that is not JSON is still an error, naming the URL
+    let vault = MockVault::start(|_| (200, "<html>maintenance</html>".to_string()));
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+    let error = client.lookup_self(&SecretToken::new("hvs.valid")).unwrap_err();
+    assert!(error.to_string().contains("Invalid JSON in the response from"), "{}", error);
+}
+
+#[test]
+fn test_validate_token_rejects_invalid_token() {
+    let vault = mock_vault();
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+
+    let error = validate_token(&client, &SecretToken::new("hvs.bogus"), Duration::ZERO).unwrap_err();
+    assert!(error.downcast_ref::<ValidationError>().is_some());
+    assert!(!error.to_string().contains("hvs.bogus"));
+}
+
+#[test]
+fn test_validate_token_enforces_min_ttl() {
+    let vault = mock_vault();
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+    let min_ttl = Duration::from_secs(3600);
+
+    let error = validate_token(&client, &SecretToken::new("hvs.short"), min_ttl).unwrap_err();
+    assert!(error.downcast_ref::<ValidationError>().is_some());
+
+    assert!(validate_token(&client, &SecretToken::new("hvs.valid"), min_ttl).is_ok());
+    // Tokens without expiry always satisfy the threshold
+    assert!(validate_token(&client, &SecretToken::new("hvs.root"), min_ttl).is_ok());
+}
+
+#[test]
+fn test_unreachable_vault_is_not_a_validation_error() {
+    let client = VaultClient::new(VaultConfig::new("http://127.0.0.1:1")).unwrap();
+
+    let error = validate_token(&client, &SecretToken::new("hvs.valid"), Duration::ZERO).unwrap_err();
+    assert!(error.downcast_ref::<ValidationError>().is_none());
+}
+
+#[test]
+fn test_invalid_ca_bundle_is_rejected() {
+    let temp_dir = TempDir::new().unwrap();
+    let bundle = temp_dir.path().join("ca.pem");
+    fs::write(&bundle, "not a certificate").unwrap();
+
+    let mut config = VaultConfig::new("https://127.0.0.1:8200");
+    config.ca_cert = Some(bundle);
+    assert!(VaultClient::new(config).is_err());
+}
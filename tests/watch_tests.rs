@@ -0,0 +1,102 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use vault_config_updater::{watch_changes, FileFilter, WatchBatch, WatchTargets};
+
+const WAIT: Duration = Duration::from_secs(10);
+
+/// Runs `watch_changes` on a thread, forwarding each batch
+fn start_watch(targets: WatchTargets) -> (mpsc::Receiver<WatchBatch>, Arc<AtomicBool>, thread::JoinHandle<()>) {
+    let (sender, receiver) = mpsc::channel();
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&shutdown);
+    let handle = thread::spawn(move || {
+        watch_changes(&targets, Duration::from_millis(100), &flag, |batch| {
+            let _ = sender.send(batch);
+            Ok(())
+        }).unwrap();
+    });
+    // Give the watcher time to register before changing anything
+    thread::sleep(Duration::from_millis(300));
+    (receiver, shutdown, handle)
+}
+
+#[test]
+fn test_reports_new_config_files_below_roots() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().to_path_buf();
+    let filter = FileFilter::new(&["**"], &["**/skip/**"]).unwrap();
+    let targets = WatchTargets { roots: vec![root.clone()], filter, ..WatchTargets::default() };
+    let (batches, shutdown, handle) = start_watch(targets);
+
+    fs::create_dir_all(root.join("svc/nested")).unwrap();
+    fs::write(root.join("svc/nested/config.json"), r#"{"vaultToken": "hvs.old"}"#).unwrap();
+    fs::create_dir_all(root.join("skip")).unwrap();
+    fs::write(root.join("skip/config.json"), "{}").unwrap();
+    fs::write(root.join("notes.json"), "{}").unwrap();
+
+    let batch = batches.recv_timeout(WAIT).unwrap();
+    assert_eq!(batch.configs.into_iter().collect::<Vec<PathBuf>>(), vec![root.join("svc/nested/config.json")]);
+    assert!(batch.token_files.is_empty());
+
+    shutdown.store(true, Ordering::SeqCst);
+    handle.join().unwrap();
+}
+
+#[test]
+fn test_reports_token_file_replaced_by_rename() {
+    let dir = TempDir::new().unwrap();
+    let configs = dir.path().join("configs");
+    fs::create_dir_all(&configs).unwrap();
+    let sink = dir.path().join("sink");
+    fs::write(&sink, "hvs.first\n").unwrap();
+    let targets = WatchTargets { roots: vec![configs], token_files: vec![sink.clone()], ..WatchTargets::default() };
+    let (batches, shutdown, handle) = start_watch(targets);
+
+    fs::write(dir.path().join("sink.tmp"), "hvs.second\n").unwrap();
+    fs::rename(dir.path().join("sink.tmp"), &sink).unwrap();
+
+    let batch = batches.recv_timeout(WAIT).unwrap();
+    assert_eq!(batch.token_files.into_iter().collect::<Vec<PathBuf>>(), vec![sink]);
+    assert!(batch.configs.is_empty());
+
+    shutdown.store(true, Ordering::SeqCst);
+    handle.join().unwrap();
+}
+
+#[test]
+fn test_missing_root_fails() {
+    let dir = TempDir::new().unwrap();
+    let targets = WatchTargets { roots: vec![dir.path().join("absent")], ..WatchTargets::default() };
+    let error = watch_changes(&targets, Duration::from_millis(10), &AtomicBool::new(false), |_| Ok(())).unwrap_err();
+    assert!(error.to_string().contains("absent"), "{}", error);
+}
+
+#[test]
+fn test_token_changes_are_rate_limited() {
+    let dir = TempDir::new().unwrap();
+    let sink = dir.path().join("sink");
+    fs::write(&sink, "hvs.first\n").unwrap();
+    let targets = WatchTargets {
+        roots: vec![dir.path().to_path_buf()],
+        token_files: vec![sink.clone()],
+        min_token_interval: Duration::from_millis(1500),
+        ..WatchTargets::default()
+    };
+    let (batches, shutdown, handle) = start_watch(targets);
+
+    fs::write(&sink, "hvs.second\n").unwrap();
+    assert!(!batches.recv_timeout(WAIT).unwrap().token_files.is_empty());
+    let first = Instant::now();
+    fs::write(&sink, "hvs.third\n").unwrap();
+    assert!(!batches.recv_timeout(WAIT).unwrap().token_files.is_empty());
+    assert!(first.elapsed() >= Duration::from_millis(1200), "{:?}", first.elapsed());
+
+    shutdown.store(true, Ordering::SeqCst);
+    handle.join().unwrap();
+}
@@ -0,0 +1,183 @@
+mod common;
+
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use common::{error_response, MockVault};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+use vault_config_updater::{
+    sign_webhook, verify_webhook, SecretToken, ServeSelection, Server, VaultClient, VaultConfig, WebhookListener,
+    SIGNATURE_HEADER, TIMESTAMP_HEADER,
+};
+
+const SECRET: &str = "webhook-secret";
+
+fn write_config(root: &Path, token: &str) -> PathBuf {
+    let path = root.join("api/config.json");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, format!(r#"{{"vaultToken": "{}"}}"#, token)).unwrap();
+    path
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+/// Posts `body` to the listener, signed with `secret` unless it is `None`
+fn post(url: &str, body: &str, secret: Option<&str>) -> (u16, Value) {
+    let agent: ureq::Agent = ureq::Agent::config_builder().http_status_as_error(false).build().into();
+    let mut request = agent.post(url).header("Content-Type", "application/json");
+    if let Some(secret) = secret {
+        let timestamp = now();
+        request = request
+            .header(TIMESTAMP_HEADER, &timestamp.to_string())
+            .header(SIGNATURE_HEADER, &sign_webhook(secret.as_bytes(), timestamp, body.as_bytes()));
+    }
+    let mut response = request.send(body).unwrap();
+    let status = response.status().as_u16();
+    (status, serde_json::from_str(&response.body_mut().read_to_string().unwrap()).unwrap())
+}
+
+/// Runs a webhook listener for `server` while `test` talks to it at the given base URL
+fn with_listener<F: FnOnce(&str)>(server: &Server, log: &Mutex<Vec<String>>, test: F) {
+    let listener = WebhookListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let secret = SecretToken::new(SECRET);
+    let shutdown = AtomicBool::new(false);
+    thread::scope(|scope| {
+        let handle = scope.spawn(|| listener.serve(server, &secret, &shutdown, |message| {
+            log.lock().unwrap().push(message.to_string());
+        }));
+        // A failed assertion must still stop the listener, or the scope never ends
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| test(&url)));
+        shutdown.store(true, Ordering::SeqCst);
+        handle.join().unwrap().unwrap();
+        if let Err(payload) = outcome {
+            panic::resume_unwind(payload);
+        }
+    });
+}
+
+#[test]
+fn test_signature_round_trip() {
+    let body = br#"{"token": "hvs.new"}"#;
+    let timestamp = now();
+    let signature = sign_webhook(SECRET.as_bytes(), timestamp, body);
+    assert!(signature.starts_with("sha256="));
+    assert_eq!(signature.len(), "sha256=".len() + 64);
+    let verify = |secret: &str, timestamp: Option<String>, signature: Option<&str>, body: &[u8]| {
+        verify_webhook(secret.as_bytes(), timestamp.as_deref(), signature, body, SystemTime::now())
+    };
+
+    assert!(verify(SECRET, Some(timestamp.to_string()), Some(&signature), body).is_ok());
+    assert!(verify("other-secret", Some(timestamp.to_string()), Some(&signature), body).is_err());
+    assert!(verify(SECRET, Some(timestamp.to_string()), Some(&signature), br#"{"token": "hvs.evil"}"#).is_err());
+    assert!(verify(SECRET, Some((timestamp + 1).to_string()), Some(&signature), body).is_err());
+    assert!(verify(SECRET, None, Some(&signature), body).unwrap_err().to_string().contains(TIMESTAMP_HEADER));
+    assert!(verify(SECRET, Some(timestamp.to_string()), None, body).unwrap_err().to_string().contains(SIGNATURE_HEADER));
+    assert!(verify(SECRET, Some(timestamp.to_string()), Some("sha256=zz"), body).is_err());
+    assert!(verify(SECRET, Some("yesterday".to_string()), Some(&signature), body).is_err());
+}
+
+#[test]
+fn test_rejects_stale_signatures() {
+    let body = br#"{"token": "hvs.new"}"#;
+    let signed_at = now() - 3600;
+    let signature = sign_webhook(SECRET.as_bytes(), signed_at, body);
+    let error = verify_webhook(SECRET.as_bytes(), Some(&signed_at.to_string()), Some(&signature), body, SystemTime::now())
+        .unwrap_err();
+    assert!(error.to_string().contains("outside the allowed window"));
+
+    let then = UNIX_EPOCH + Duration::from_secs(signed_at + 60);
+    assert!(verify_webhook(SECRET.as_bytes(), Some(&signed_at.to_string()), Some(&signature), body, then).is_ok());
+}
+
+#[test]
+fn test_signed_webhook_rotates_files() {
+    let dir = TempDir::new().unwrap();
+    let config = write_config(dir.path(), "hvs.old");
+    let server = Server::new(ServeSelection { paths: vec![dir.path().to_path_buf()], ..ServeSelection::default() }).unwrap();
+    let log = Mutex::new(Vec::new());
+
+    with_listener(&server, &log, |url| {
+        let (status, report) = post(&format!("{}/rotate", url), r#"{"token": "hvs.new"}"#, Some(SECRET));
+        assert_eq!(status, 200, "{}", report);
+        assert_eq!(report["mode"], "update");
+        assert_eq!(report["exit_code"], 0);
+        assert_eq!(report["stats"]["files_updated"], 1);
+        assert_eq!(report["token"]["fingerprint"], SecretToken::new("hvs.new").fingerprint());
+        assert!(!report.to_string().contains("hvs.new"));
+    });
+    assert_eq!(fs::read_to_string(config).unwrap(), r#"{"vaultToken": "hvs.new"}"#);
+    let log = log.into_inner().unwrap();
+    assert_eq!(log.len(), 1);
+    assert!(log[0].contains("rotate: 1 of 1 files updated"), "{}", log[0]);
+}
+
+#[test]
+fn test_rejects_unsigned_and_malformed_requests() {
+    let dir = TempDir::new().unwrap();
+    let config = write_config(dir.path(), "hvs.old");
+    let server = Server::new(ServeSelection { paths: vec![dir.path().to_path_buf()], ..ServeSelection::default() }).unwrap();
+    let log = Mutex::new(Vec::new());
+
+    with_listener(&server, &log, |url| {
+        let rotate = format!("{}/rotate", url);
+        let body = r#"{"token": "hvs.new"}"#;
+        assert_eq!(post(&rotate, body, None).0, 401);
+        assert_eq!(post(&rotate, body, Some("wrong-secret")).0, 401);
+        assert_eq!(post(&format!("{}/status", url), body, Some(SECRET)).0, 404);
+        assert_eq!(post(&rotate, r#"{"token": "hvs.new", "extra": true}"#, Some(SECRET)).0, 400);
+        assert_eq!(post(&rotate, r#"{"token": ""}"#, Some(SECRET)).0, 400);
+        let (status, response) = post(&rotate, &format!(r#"{{"token": "{}"}}"#, "x".repeat(70 * 1024)), Some(SECRET));
+        assert_eq!(status, 413, "{}", response);
+
+        let agent: ureq::Agent = ureq::Agent::config_builder().http_status_as_error(false).build().into();
+        assert_eq!(agent.get(&rotate).call().unwrap().status().as_u16(), 405);
+    });
+    assert_eq!(fs::read_to_string(config).unwrap(), r#"{"vaultToken": "hvs.old"}"#);
+    let log = log.into_inner().unwrap();
+    assert!(log.iter().filter(|line| line.contains("rejected")).count() == 2, "{:?}", log);
+}
+
+#[test]
+fn test_wrapped_tokens_are_unwrapped_through_vault() {
+    let vault = MockVault::start(|request| match (request.path.as_str(), request.token.as_deref()) {
+        ("/v1/sys/wrapping/unwrap", Some("hvs.wrapping")) => (200, json!({
+            "auth": { "client_token": "hvs.unwrapped", "policies": [], "lease_duration": 60 }
+        }).to_string()),
+        _ => (400, error_response("wrapping token is not valid or does not exist")),
+    });
+    let dir = TempDir::new().unwrap();
+    let config = write_config(dir.path(), "hvs.old");
+    let selection = ServeSelection { paths: vec![dir.path().to_path_buf()], ..ServeSelection::default() };
+    let log = Mutex::new(Vec::new());
+
+    // Without a Vault address the wrapped token is refused
+    let server = Server::new(selection.clone()).unwrap();
+    with_listener(&server, &log, |url| {
+        let (status, response) = post(&format!("{}/rotate", url), r#"{"token": "hvs.wrapping", "wrapped": true}"#, Some(SECRET));
+        assert_eq!(status, 400);
+        assert!(response["error"].as_str().unwrap().contains("no Vault address"));
+    });
+
+    let client = VaultClient::new(VaultConfig::new(&vault.addr)).unwrap();
+    let server = Server::new(selection).unwrap().with_vault(client);
+    with_listener(&server, &log, |url| {
+        let body = r#"{"token": "hvs.wrapping", "wrapped": true}"#;
+        let (status, report) = post(&format!("{}/rotate", url), body, Some(SECRET));
+        assert_eq!(status, 200, "{}", report);
+        assert_eq!(report["token"]["fingerprint"], SecretToken::new("hvs.unwrapped").fingerprint());
+
+        // A wrapping token Vault no longer knows is refused
+        let (status, response) = post(&format!("{}/rotate", url), r#"{"token": "hvs.used", "wrapped": true}"#, Some(SECRET));
+        assert_eq!(status, 400);
+        assert!(response["error"].as_str().unwrap().contains("rejected by Vault"), "{}", response);
+    });
+    assert_eq!(fs::read_to_string(config).unwrap(), r#"{"vaultToken": "hvs.unwrapped"}"#);
+}